@@ -1,6 +1,20 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_prost_build::configure()
         .out_dir("src/grpc_daemon")
+        // JSON Lines and CSV mapping of imported and exported alerts
+        .type_attribute(
+            "alert.AlertRequestData",
+            "#[derive(serde::Serialize, serde::Deserialize)]\n#[serde(default, deny_unknown_fields)]",
+        )
+        .type_attribute(
+            "alert.Attachment",
+            "#[derive(serde::Serialize, serde::Deserialize)]\n#[serde(default, deny_unknown_fields)]",
+        )
+        // CSV records have no room for them, the url columns stand in
+        .field_attribute(
+            "alert.AlertRequestData.attachments",
+            "#[serde(skip_serializing_if = \"Vec::is_empty\")]",
+        )
         .compile_protos(&["src/proto/alert.proto"], &["proto"])
         .unwrap();
     Ok(())
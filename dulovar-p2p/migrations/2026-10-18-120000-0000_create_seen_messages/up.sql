@@ -0,0 +1,7 @@
+-- Gossip message ids already processed, so restarts do not reprocess them
+CREATE TABLE IF NOT EXISTS seen_messages (
+  message_id TEXT PRIMARY KEY NOT NULL,
+  seen_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS seen_messages_seen_at ON seen_messages (seen_at);
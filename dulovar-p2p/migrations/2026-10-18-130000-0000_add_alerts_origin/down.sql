@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX alerts_origin_seq;
+DROP INDEX alerts_alert_id;
+
+ALTER TABLE alerts DROP COLUMN seq;
+ALTER TABLE alerts DROP COLUMN origin;
+ALTER TABLE alerts DROP COLUMN alert_id;
@@ -0,0 +1,8 @@
+-- Content id of the alert and its place in the sequence of the node that
+-- published it, used to deduplicate and to sync missed alerts between peers
+ALTER TABLE alerts ADD COLUMN alert_id TEXT;
+ALTER TABLE alerts ADD COLUMN origin TEXT;
+ALTER TABLE alerts ADD COLUMN seq BIGINT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS alerts_alert_id ON alerts (alert_id);
+CREATE INDEX IF NOT EXISTS alerts_origin_seq ON alerts (origin, seq);
@@ -0,0 +1,5 @@
+-- Peers banned through the admin RPC, blocked again on restart
+CREATE TABLE IF NOT EXISTS banned_peers (
+  peer_id TEXT PRIMARY KEY NOT NULL,
+  banned_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+-- Alerts published locally and not gossiped yet, written in the same
+-- transaction as the alert so they survive a crash
+CREATE TABLE IF NOT EXISTS outbox (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  alert_id TEXT NOT NULL,
+  payload BLOB NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX alerts_revokes;
+
+ALTER TABLE alerts DROP COLUMN revokes;
@@ -0,0 +1,5 @@
+-- Content id of the alert a revocation revokes, null for other alerts. The
+-- revocation only counts when it comes from the origin of the alert.
+ALTER TABLE alerts ADD COLUMN revokes TEXT;
+
+CREATE INDEX IF NOT EXISTS alerts_revokes ON alerts (revokes);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS alerts_search_update;
+DROP TRIGGER IF EXISTS alerts_search_delete;
+DROP TRIGGER IF EXISTS alerts_search_insert;
+DROP TABLE IF EXISTS alerts_search;
@@ -0,0 +1,33 @@
+-- Trigram index of the names, description and alert name, so searches
+-- match parts of words and tolerate typos. Diacritics are folded, `Pérez`
+-- matches `perez`. The text stays in `alerts`, triggers keep the index in
+-- sync.
+CREATE VIRTUAL TABLE IF NOT EXISTS alerts_search USING fts5(
+  first_name,
+  last_name,
+  description,
+  name_alert,
+  content = 'alerts',
+  content_rowid = 'id',
+  tokenize = 'trigram remove_diacritics 1'
+);
+
+CREATE TRIGGER IF NOT EXISTS alerts_search_insert AFTER INSERT ON alerts BEGIN
+  INSERT INTO alerts_search (rowid, first_name, last_name, description, name_alert)
+  VALUES (new.id, new.first_name, new.last_name, new.description, new.name_alert);
+END;
+
+CREATE TRIGGER IF NOT EXISTS alerts_search_delete AFTER DELETE ON alerts BEGIN
+  INSERT INTO alerts_search (alerts_search, rowid, first_name, last_name, description, name_alert)
+  VALUES ('delete', old.id, old.first_name, old.last_name, old.description, old.name_alert);
+END;
+
+CREATE TRIGGER IF NOT EXISTS alerts_search_update AFTER UPDATE ON alerts BEGIN
+  INSERT INTO alerts_search (alerts_search, rowid, first_name, last_name, description, name_alert)
+  VALUES ('delete', old.id, old.first_name, old.last_name, old.description, old.name_alert);
+  INSERT INTO alerts_search (rowid, first_name, last_name, description, name_alert)
+  VALUES (new.id, new.first_name, new.last_name, new.description, new.name_alert);
+END;
+
+-- Alerts stored before the index
+INSERT INTO alerts_search (alerts_search) VALUES ('rebuild');
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX photos_alert_id;
+
+ALTER TABLE photos DROP COLUMN caption;
+ALTER TABLE photos DROP COLUMN sha256;
+ALTER TABLE photos DROP COLUMN mime_type;
@@ -0,0 +1,7 @@
+-- Attachments of an alert beyond its url, in the order they were given.
+-- The url columns of alerts keep the first three for older nodes.
+ALTER TABLE photos ADD COLUMN mime_type TEXT;
+ALTER TABLE photos ADD COLUMN sha256 TEXT;
+ALTER TABLE photos ADD COLUMN caption TEXT;
+
+CREATE INDEX IF NOT EXISTS photos_alert_id ON photos (alert_id);
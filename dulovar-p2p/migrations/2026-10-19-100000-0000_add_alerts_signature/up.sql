@@ -0,0 +1,3 @@
+-- Signature of the alert by its origin, so any node can serve it to peers
+-- syncing. Null for alerts stored before alerts were signed.
+ALTER TABLE alerts ADD COLUMN signature TEXT;
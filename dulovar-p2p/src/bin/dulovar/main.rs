@@ -0,0 +1,576 @@
+//! Command line client of a dulovar node, over its gRPC services
+
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use tonic::Status;
+use tonic::transport::Channel;
+
+use dulovar_p2p::grpc_daemon::alert::{
+    AlertRequestData, Attachment, DialRequest, DisconnectPeerRequest, ExportAlertsRequest,
+    GetAlertRequest, ImportAlertsRequest, ListAlertsRequest, ListPeersRequest, NodeInfoRequest,
+    PeerBanRequest, RecordFormat, RevokeAlertRequest, SearchAlertsRequest, SubscribeAlertsRequest,
+    alert_service_client::AlertServiceClient, node_admin_client::NodeAdminClient,
+};
+use dulovar_p2p::grpc_daemon::records;
+
+mod output;
+use output::Format;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "dulovar",
+    version,
+    about = "Submit, read and follow the alerts of a dulovar node"
+)]
+struct Cli {
+    /// gRPC endpoint of the node
+    #[arg(
+        long,
+        env = "DULOVAR_GRPC_ADDR",
+        default_value = "http://[::1]:50051",
+        global = true
+    )]
+    addr: String,
+    #[arg(long, short, value_enum, default_value_t = Format::Table, global = true)]
+    output: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Submit an alert, from flags or from a JSON file
+    Submit(Box<Submission>),
+    /// Show an alert by its id
+    Get { alert_id: String },
+    /// List stored alerts, newest first
+    List {
+        #[arg(long)]
+        country: Option<String>,
+        #[arg(long = "type")]
+        type_alert: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+        #[arg(long, default_value_t = 0)]
+        offset: u32,
+    },
+    /// Search the names, description and alert name, tolerating typos
+    Search {
+        query: String,
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Print alerts as the node stores them, until interrupted
+    #[command(visible_alias = "tail")]
+    Subscribe {
+        #[arg(long)]
+        country: Option<String>,
+        #[arg(long = "type")]
+        type_alert: Option<String>,
+    },
+    /// Revoke an alert published by this node
+    Revoke { alert_id: String },
+    /// Import alerts from a JSON Lines or CSV file, publishing them
+    Import {
+        file: PathBuf,
+        /// Format of the file, guessed from its extension when missing
+        #[arg(long, value_enum)]
+        format: Option<Records>,
+        /// Only validate the records
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export alerts as JSON Lines or CSV, oldest first
+    Export {
+        /// Written to stdout when missing
+        #[arg(long, short)]
+        file: Option<PathBuf>,
+        /// Format of the records, guessed from the file extension when
+        /// missing
+        #[arg(long, value_enum)]
+        format: Option<Records>,
+        #[arg(long)]
+        country: Option<String>,
+        #[arg(long = "type")]
+        type_alert: Option<String>,
+        #[arg(long)]
+        include_revoked: bool,
+    },
+    /// Inspect and operate the node
+    #[command(subcommand)]
+    Admin(Admin),
+}
+
+#[derive(Debug, Subcommand)]
+enum Admin {
+    /// Addresses, topics, routing table and database of the node
+    Info,
+    /// Connected peers
+    Peers,
+    /// Dial a multiaddr
+    Dial { address: String },
+    /// Close the connections to a peer
+    Disconnect { peer_id: String },
+    /// Ban a peer, persisted across restarts
+    Ban { peer_id: String },
+    /// Lift the ban of a peer
+    Unban { peer_id: String },
+}
+
+/// Format of imported and exported records
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Records {
+    Jsonl,
+    Csv,
+}
+
+impl Records {
+    /// `format`, or the one of the file extension, JSON Lines by default
+    fn of(format: Option<Self>, file: Option<&PathBuf>) -> RecordFormat {
+        let csv = file
+            .and_then(|file| file.extension())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        match format {
+            Some(Records::Csv) => RecordFormat::Csv,
+            Some(Records::Jsonl) => RecordFormat::JsonLines,
+            None if csv => RecordFormat::Csv,
+            None => RecordFormat::JsonLines,
+        }
+    }
+}
+
+/// Alert to submit. Fields of the JSON file are named like the flags, with
+/// underscores.
+#[derive(Debug, Default, Args, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Submission {
+    /// JSON file with the fields of the alert, instead of the flags
+    #[arg(long, conflicts_with_all = ["first_name", "last_name", "description", "yob", "url", "country", "type_alert", "name_alert"])]
+    #[serde(skip)]
+    file: Option<PathBuf>,
+    #[arg(long)]
+    first_name: Option<String>,
+    #[arg(long)]
+    last_name: Option<String>,
+    #[arg(long)]
+    description: Option<String>,
+    /// Year of birth
+    #[arg(long)]
+    yob: Option<i32>,
+    /// Photo URLs, attached in this order
+    #[arg(long, num_args = 1..)]
+    #[serde(skip)]
+    url: Vec<String>,
+    /// Attachments with their mime type, checksum and caption
+    #[arg(skip)]
+    attachments: Vec<Attachment>,
+    #[arg(skip)]
+    url_1: Option<String>,
+    #[arg(skip)]
+    url_2: Option<String>,
+    #[arg(skip)]
+    url_3: Option<String>,
+    /// ISO 3166-1 alpha-2 country code
+    #[arg(long, required_unless_present = "file")]
+    country: Option<String>,
+    #[arg(long = "type", required_unless_present = "file")]
+    type_alert: Option<String>,
+    #[arg(long = "name", required_unless_present = "file")]
+    name_alert: Option<String>,
+}
+
+impl Submission {
+    /// The submission itself, or the one read from its file
+    fn load(self) -> Result<Self, Box<dyn Error>> {
+        match &self.file {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+                Ok(serde_json::from_str(&json)
+                    .map_err(|e| format!("invalid alert in {}: {e}", path.display()))?)
+            }
+            None => Ok(self),
+        }
+    }
+}
+
+impl From<Submission> for AlertRequestData {
+    fn from(submission: Submission) -> Self {
+        let urls = submission.url.into_iter().map(|url| Attachment {
+            url,
+            ..Attachment::default()
+        });
+        Self {
+            first_name: submission.first_name.unwrap_or_default(),
+            last_name: submission.last_name.unwrap_or_default(),
+            description: submission.description.unwrap_or_default(),
+            yob: submission.yob.unwrap_or_default(),
+            // The node merges them with the attachments
+            url_1: submission.url_1.unwrap_or_default(),
+            url_2: submission.url_2.unwrap_or_default(),
+            url_3: submission.url_3.unwrap_or_default(),
+            country: submission.country.unwrap_or_default(),
+            type_alert: submission.type_alert.unwrap_or_default(),
+            name_alert: submission.name_alert.unwrap_or_default(),
+            attachments: submission.attachments.into_iter().chain(urls).collect(),
+        }
+    }
+}
+
+async fn alerts_client(addr: &str) -> Result<AlertServiceClient<Channel>, Box<dyn Error>> {
+    AlertServiceClient::connect(addr.to_string())
+        .await
+        .map_err(|e| format!("cannot connect to {addr}: {e}").into())
+}
+
+async fn admin_client(addr: &str) -> Result<NodeAdminClient<Channel>, Box<dyn Error>> {
+    NodeAdminClient::connect(addr.to_string())
+        .await
+        .map_err(|e| format!("cannot connect to {addr}: {e}").into())
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let format = cli.output;
+    let addr = cli.addr.as_str();
+    let text = match cli.command {
+        Command::Submit(submission) => {
+            let data = AlertRequestData::from(submission.load()?);
+            let mut confirmations = alerts_client(addr)
+                .await?
+                .process_and_stream(data)
+                .await?
+                .into_inner();
+            let mut received = Vec::new();
+            while let Some(confirmation) = confirmations.next().await {
+                received.push(confirmation?);
+            }
+            output::message(
+                received
+                    .iter()
+                    .map(|c| format!("{}  {}", c.confirmation_id, c.status_message))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                json!(
+                    received
+                        .iter()
+                        .map(|c| json!({
+                            "confirmation_id": c.confirmation_id,
+                            "status_message": c.status_message,
+                        }))
+                        .collect::<Vec<_>>()
+                ),
+                format,
+            )
+        }
+        Command::Get { alert_id } => {
+            let record = alerts_client(addr)
+                .await?
+                .get_alert(GetAlertRequest { alert_id })
+                .await?
+                .into_inner();
+            output::alert(&record, format)
+        }
+        Command::List {
+            country,
+            type_alert,
+            limit,
+            offset,
+        } => {
+            let response = alerts_client(addr)
+                .await?
+                .list_alerts(ListAlertsRequest {
+                    country: country.unwrap_or_default(),
+                    type_alert: type_alert.unwrap_or_default(),
+                    limit,
+                    offset,
+                })
+                .await?
+                .into_inner();
+            output::alerts(&response.alerts, format)
+        }
+        Command::Search { query, limit } => {
+            let response = alerts_client(addr)
+                .await?
+                .search_alerts(SearchAlertsRequest { query, limit })
+                .await?
+                .into_inner();
+            output::matches(&response.matches, format)
+        }
+        Command::Subscribe {
+            country,
+            type_alert,
+        } => {
+            let mut records = alerts_client(addr)
+                .await?
+                .subscribe_alerts(SubscribeAlertsRequest {
+                    country: country.unwrap_or_default(),
+                    type_alert: type_alert.unwrap_or_default(),
+                })
+                .await?
+                .into_inner();
+            let mut stdout = std::io::stdout();
+            while let Some(record) = records.next().await {
+                writeln!(stdout, "{}", output::streamed_alert(&record?, format))?;
+                stdout.flush()?;
+            }
+            return Err("the node closed the subscription".into());
+        }
+        Command::Revoke { alert_id } => {
+            let response = alerts_client(addr)
+                .await?
+                .revoke_alert(RevokeAlertRequest {
+                    alert_id: alert_id.clone(),
+                })
+                .await?
+                .into_inner();
+            output::message(
+                format!("revoked {alert_id}, revocation {}", response.revocation_id),
+                json!({ "alert_id": alert_id, "revocation_id": response.revocation_id }),
+                format,
+            )
+        }
+        Command::Import {
+            file,
+            format: records_format,
+            dry_run,
+        } => {
+            let records_format = Records::of(records_format, Some(&file));
+            let text = std::fs::read_to_string(&file)
+                .map_err(|e| format!("cannot read {}: {e}", file.display()))?;
+            let requests: Vec<_> = records::split(records_format, &text)?
+                .into_iter()
+                .map(|record| ImportAlertsRequest {
+                    format: records_format.into(),
+                    dry_run,
+                    record,
+                })
+                .collect();
+            let response = alerts_client(addr)
+                .await?
+                .import_alerts(futures::stream::iter(requests))
+                .await?
+                .into_inner();
+            output::import(&response, format)
+        }
+        Command::Export {
+            file,
+            format: records_format,
+            country,
+            type_alert,
+            include_revoked,
+        } => {
+            let records_format = Records::of(records_format, file.as_ref());
+            let mut records = alerts_client(addr)
+                .await?
+                .export_alerts(ExportAlertsRequest {
+                    format: records_format.into(),
+                    country: country.unwrap_or_default(),
+                    type_alert: type_alert.unwrap_or_default(),
+                    include_revoked,
+                })
+                .await?
+                .into_inner();
+            let mut out: Box<dyn Write> = match &file {
+                Some(file) => Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(file)
+                        .map_err(|e| format!("cannot create {}: {e}", file.display()))?,
+                )),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            while let Some(response) = records.next().await {
+                writeln!(out, "{}", response?.record)?;
+            }
+            out.flush()?;
+            return Ok(());
+        }
+        Command::Admin(command) => admin(addr, command, format).await?,
+    };
+    println!("{text}");
+    Ok(())
+}
+
+async fn admin(addr: &str, command: Admin, format: Format) -> Result<String, Box<dyn Error>> {
+    let mut client = admin_client(addr).await?;
+    Ok(match command {
+        Admin::Info => {
+            let info = client.get_node_info(NodeInfoRequest {}).await?.into_inner();
+            output::node_info(&info, format)
+        }
+        Admin::Peers => {
+            let response = client.list_peers(ListPeersRequest {}).await?.into_inner();
+            output::peers(&response.peers, format)
+        }
+        Admin::Dial { address } => {
+            client
+                .dial(DialRequest {
+                    address: address.clone(),
+                })
+                .await?;
+            output::message(
+                format!("dialing {address}"),
+                json!({ "address": address }),
+                format,
+            )
+        }
+        Admin::Disconnect { peer_id } => {
+            let response = client
+                .disconnect_peer(DisconnectPeerRequest { peer_id })
+                .await?
+                .into_inner();
+            output::message(
+                if response.disconnected {
+                    "disconnected".into()
+                } else {
+                    "peer was not connected".into()
+                },
+                json!({ "disconnected": response.disconnected }),
+                format,
+            )
+        }
+        Admin::Ban { peer_id } => set_ban(&mut client, peer_id, true, format).await?,
+        Admin::Unban { peer_id } => set_ban(&mut client, peer_id, false, format).await?,
+    })
+}
+
+async fn set_ban(
+    client: &mut NodeAdminClient<Channel>,
+    peer_id: String,
+    banned: bool,
+    format: Format,
+) -> Result<String, Box<dyn Error>> {
+    let response = client
+        .set_peer_ban(PeerBanRequest { peer_id, banned })
+        .await?
+        .into_inner();
+    let state = if response.banned {
+        "banned"
+    } else {
+        "not banned"
+    };
+    Ok(output::message(
+        if response.changed {
+            format!("{} is now {state}", response.peer_id)
+        } else {
+            format!("{} already was {state}", response.peer_id)
+        },
+        json!({
+            "peer_id": response.peer_id,
+            "banned": response.banned,
+            "changed": response.changed,
+        }),
+        format,
+    ))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            match e.downcast_ref::<Status>() {
+                Some(status) => eprintln!("error: {:?}: {}", status.code(), status.message()),
+                None => eprintln!("error: {e}"),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("dulovar").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_submit_from_flags() {
+        let cli = parse(&[
+            "submit",
+            "--country",
+            "AR",
+            "--type",
+            "amber",
+            "--name",
+            "missing",
+            "--url",
+            "a",
+            "b",
+            "c",
+            "d",
+        ])
+        .unwrap();
+        let Command::Submit(submission) = cli.command else {
+            panic!("unexpected {:?}", cli.command);
+        };
+        let data = AlertRequestData::from(submission.load().unwrap());
+        assert_eq!(data.country, "AR");
+        assert_eq!(data.type_alert, "amber");
+        let urls: Vec<&str> = data.attachments.iter().map(|a| a.url.as_str()).collect();
+        assert_eq!(urls, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_submit_from_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{"country":"UY","type_alert":"red","name_alert":"n","url_2":"u",
+                "attachments":[{{"url":"v","mime_type":"image/png","caption":"at home"}}]}}"#
+        )
+        .unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let cli = parse(&["-o", "json", "submit", "--file", path]).unwrap();
+        assert_eq!(cli.output, Format::Json);
+        let Command::Submit(submission) = cli.command else {
+            panic!("unexpected {:?}", cli.command);
+        };
+        let data = AlertRequestData::from(submission.load().unwrap());
+        assert_eq!(data.country, "UY");
+        assert_eq!(data.url_2, "u");
+        assert_eq!(data.attachments[0].caption, "at home");
+
+        // The file replaces the flags
+        assert!(parse(&["submit", "--file", path, "--country", "AR"]).is_err());
+    }
+
+    #[test]
+    fn test_records_format_follows_the_extension() {
+        let csv = PathBuf::from("alerts.CSV");
+        assert_eq!(Records::of(None, Some(&csv)), RecordFormat::Csv);
+        assert_eq!(
+            Records::of(Some(Records::Jsonl), Some(&csv)),
+            RecordFormat::JsonLines
+        );
+        assert_eq!(
+            Records::of(None, Some(&PathBuf::from("alerts.txt"))),
+            RecordFormat::JsonLines
+        );
+        assert_eq!(Records::of(None, None), RecordFormat::JsonLines);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for args in [
+            &["submit", "--country", "AR"][..],
+            &["list", "--limit", "many"],
+            &["admin"],
+            &["get"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+        assert!(matches!(
+            parse(&["tail", "--country", "AR"]).unwrap().command,
+            Command::Subscribe { .. }
+        ));
+    }
+}
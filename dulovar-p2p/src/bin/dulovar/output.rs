@@ -0,0 +1,440 @@
+use clap::ValueEnum;
+use serde_json::{Value, json};
+
+use dulovar_p2p::grpc_daemon::alert::{
+    AlertMatch, AlertRecord, ImportAlertsResponse, NodeInfo, PeerInfo,
+};
+
+/// Characters of the alert ids shown in tables, enough to tell them apart
+const SHORT_ID: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Aligned columns for people
+    Table,
+    /// JSON for scripts, one object per line when streaming
+    Json,
+}
+
+/// Columns padded to their widest cell, the last one left unpadded
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let last = cells.len().saturating_sub(1);
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if i == last {
+                    cell.to_string()
+                } else {
+                    format!("{cell:<width$}", width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    std::iter::once(line(headers.to_vec()))
+        .chain(
+            rows.iter()
+                .map(|row| line(row.iter().map(String::as_str).collect())),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn short(id: &str) -> &str {
+    id.get(..SHORT_ID).unwrap_or(id)
+}
+
+pub fn record_json(record: &AlertRecord) -> Value {
+    let data = record.data.clone().unwrap_or_default();
+    json!({
+        "alert_id": record.alert_id,
+        "origin": record.origin,
+        "seq": record.seq,
+        "created_at": record.created_at,
+        "revoked": record.revoked,
+        "data": {
+            "first_name": data.first_name,
+            "last_name": data.last_name,
+            "description": data.description,
+            "yob": data.yob,
+            "url_1": data.url_1,
+            "url_2": data.url_2,
+            "url_3": data.url_3,
+            "country": data.country,
+            "type_alert": data.type_alert,
+            "name_alert": data.name_alert,
+            "attachments": data.attachments.iter().map(|attachment| json!({
+                "url": attachment.url,
+                "mime_type": attachment.mime_type,
+                "sha256": attachment.sha256,
+                "caption": attachment.caption,
+            })).collect::<Vec<_>>(),
+        },
+    })
+}
+
+const ALERT_HEADERS: [&str; 7] = [
+    "ALERT ID", "STORED", "COUNTRY", "TYPE", "NAME", "PERSON", "REVOKED",
+];
+
+fn alert_row(record: &AlertRecord) -> Vec<String> {
+    let data = record.data.clone().unwrap_or_default();
+    vec![
+        short(&record.alert_id).to_string(),
+        record.created_at.clone(),
+        data.country,
+        data.type_alert,
+        data.name_alert,
+        format!("{} {}", data.first_name, data.last_name)
+            .trim()
+            .to_string(),
+        if record.revoked { "yes" } else { "no" }.to_string(),
+    ]
+}
+
+pub fn alerts(records: &[AlertRecord], format: Format) -> String {
+    match format {
+        Format::Table => table(
+            &ALERT_HEADERS,
+            &records.iter().map(alert_row).collect::<Vec<_>>(),
+        ),
+        Format::Json => pretty(&Value::Array(records.iter().map(record_json).collect())),
+    }
+}
+
+/// Search results with their score, best first
+pub fn matches(matches: &[AlertMatch], format: Format) -> String {
+    match format {
+        Format::Table => {
+            let headers: Vec<&str> = std::iter::once("SCORE").chain(ALERT_HEADERS).collect();
+            let rows: Vec<_> = matches
+                .iter()
+                .map(|found| {
+                    let record = found.alert.clone().unwrap_or_default();
+                    std::iter::once(format!("{:.2}", found.score))
+                        .chain(alert_row(&record))
+                        .collect()
+                })
+                .collect();
+            table(&headers, &rows)
+        }
+        Format::Json => pretty(&Value::Array(
+            matches
+                .iter()
+                .map(|found| {
+                    let mut value = record_json(&found.alert.clone().unwrap_or_default());
+                    value["score"] = json!(found.score);
+                    value
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Every field of a single alert, one per line
+pub fn alert(record: &AlertRecord, format: Format) -> String {
+    if format == Format::Json {
+        return pretty(&record_json(record));
+    }
+    let data = record.data.clone().unwrap_or_default();
+    let mut fields = vec![
+        ("alert id", record.alert_id.clone()),
+        ("origin", record.origin.clone()),
+        ("seq", record.seq.to_string()),
+        ("stored", record.created_at.clone()),
+        ("revoked", record.revoked.to_string()),
+        ("country", data.country),
+        ("type", data.type_alert),
+        ("name", data.name_alert),
+        ("first name", data.first_name),
+        ("last name", data.last_name),
+        ("year of birth", data.yob.to_string()),
+        ("description", data.description),
+    ];
+    // The legacy urls are the first attachments
+    for attachment in data.attachments {
+        let details: Vec<String> = [attachment.mime_type, attachment.caption]
+            .into_iter()
+            .filter(|detail| !detail.is_empty())
+            .collect();
+        let value = if details.is_empty() {
+            attachment.url
+        } else {
+            format!("{} ({})", attachment.url, details.join(", "))
+        };
+        fields.push(("attachment", value));
+    }
+    fields
+        .iter()
+        .map(|(name, value)| format!("{:<14}{value}", format!("{name}:")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Line of an alert received by `subscribe`, flushed as it arrives
+pub fn streamed_alert(record: &AlertRecord, format: Format) -> String {
+    match format {
+        Format::Table => alert_row(record)
+            .into_iter()
+            .filter(|cell| !cell.is_empty())
+            .collect::<Vec<_>>()
+            .join("  "),
+        Format::Json => record_json(record).to_string(),
+    }
+}
+
+pub fn node_info(info: &NodeInfo, format: Format) -> String {
+    let db = info.db.unwrap_or_default();
+    if format == Format::Json {
+        return pretty(&json!({
+            "peer_id": info.peer_id,
+            "listen_addresses": info.listen_addresses,
+            "external_addresses": info.external_addresses,
+            "topics": info.topics.iter().map(|topic| json!({
+                "topic": topic.topic,
+                "mesh_peers": topic.mesh_peers,
+            })).collect::<Vec<_>>(),
+            "kademlia_peers": info.kademlia_peers,
+            "db": {
+                "alerts": db.alerts,
+                "outbox": db.outbox,
+                "seen_messages": db.seen_messages,
+                "banned_peers": db.banned_peers,
+            },
+        }));
+    }
+    let mut lines = vec![
+        format!("peer id:         {}", info.peer_id),
+        format!("listening on:    {}", info.listen_addresses.join(", ")),
+        format!("external:        {}", info.external_addresses.join(", ")),
+        format!("kademlia peers:  {}", info.kademlia_peers),
+        format!(
+            "database:        {} alerts, {} in outbox, {} seen messages, {} banned peers",
+            db.alerts, db.outbox, db.seen_messages, db.banned_peers
+        ),
+        String::new(),
+    ];
+    let rows: Vec<_> = info
+        .topics
+        .iter()
+        .map(|topic| {
+            vec![
+                topic.topic.clone(),
+                topic.mesh_peers.len().to_string(),
+                topic.mesh_peers.join(", "),
+            ]
+        })
+        .collect();
+    lines.push(table(&["TOPIC", "MESH", "MESH PEERS"], &rows));
+    lines.join("\n")
+}
+
+pub fn peers(peers: &[PeerInfo], format: Format) -> String {
+    match format {
+        Format::Table => table(
+            &["PEER ID", "RTT", "AGENT", "ADDRESSES"],
+            &peers
+                .iter()
+                .map(|peer| {
+                    vec![
+                        peer.peer_id.clone(),
+                        peer.rtt_ms
+                            .map(|rtt| format!("{rtt} ms"))
+                            .unwrap_or_default(),
+                        peer.agent_version.clone(),
+                        peer.addresses.join(", "),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Format::Json => pretty(&Value::Array(
+            peers
+                .iter()
+                .map(|peer| {
+                    json!({
+                        "peer_id": peer.peer_id,
+                        "addresses": peer.addresses,
+                        "rtt_ms": peer.rtt_ms,
+                        "agent_version": peer.agent_version,
+                    })
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Failed records of an import, then its totals
+pub fn import(response: &ImportAlertsResponse, format: Format) -> String {
+    if format == Format::Json {
+        return pretty(&json!({
+            "imported": response.imported,
+            "failed": response.failed,
+            "dry_run": response.dry_run,
+            "results": response.results.iter().map(|result| json!({
+                "record": result.record,
+                "error": result.error,
+            })).collect::<Vec<_>>(),
+        }));
+    }
+    let failures: Vec<_> = response
+        .results
+        .iter()
+        .filter(|result| !result.error.is_empty())
+        .map(|result| vec![result.record.to_string(), result.error.clone()])
+        .collect();
+    let verb = if response.dry_run {
+        "valid"
+    } else {
+        "imported"
+    };
+    let totals = format!("{} {verb}, {} failed", response.imported, response.failed);
+    if failures.is_empty() {
+        totals
+    } else {
+        format!("{}\n\n{totals}", table(&["RECORD", "ERROR"], &failures))
+    }
+}
+
+/// Short answer of an action, or its fields as JSON
+pub fn message(text: String, fields: Value, format: Format) -> String {
+    match format {
+        Format::Table => text,
+        Format::Json => pretty(&fields),
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dulovar_p2p::grpc_daemon::alert::{AlertRequestData, Attachment, ImportResult};
+
+    fn record() -> AlertRecord {
+        AlertRecord {
+            alert_id: "0123456789abcdef0123".into(),
+            data: Some(AlertRequestData {
+                first_name: "Ana".into(),
+                last_name: "Pérez".into(),
+                country: "AR".into(),
+                type_alert: "amber".into(),
+                name_alert: "missing".into(),
+                url_1: "https://example.org/ana.jpg".into(),
+                attachments: vec![Attachment {
+                    url: "https://example.org/ana.jpg".into(),
+                    mime_type: "image/jpeg".into(),
+                    caption: "at home".into(),
+                    ..Attachment::default()
+                }],
+                ..AlertRequestData::default()
+            }),
+            origin: "12D3KooW".into(),
+            seq: 3,
+            created_at: "2026-10-18 16:00:00".into(),
+            revoked: true,
+        }
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let rendered = table(
+            &["A", "LONG HEADER", "C"],
+            &[vec!["wide cell".into(), "x".into(), "last".into()]],
+        );
+        assert_eq!(
+            rendered,
+            "A          LONG HEADER  C\nwide cell  x            last"
+        );
+    }
+
+    #[test]
+    fn test_alerts_table_shortens_ids() {
+        let rendered = alerts(&[record()], Format::Table);
+        let row = rendered.lines().nth(1).unwrap();
+        assert!(row.starts_with("0123456789ab  2026-10-18 16:00:00  AR"));
+        assert!(row.contains("Ana Pérez"));
+        assert!(row.ends_with("yes"));
+    }
+
+    #[test]
+    fn test_matches_show_their_score() {
+        let found = [AlertMatch {
+            alert: Some(record()),
+            score: 0.756,
+        }];
+        let rendered = matches(&found, Format::Table);
+        assert!(rendered.starts_with("SCORE  ALERT ID"));
+        assert!(
+            rendered
+                .lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("0.76   0123456789ab")
+        );
+
+        let value: Value = serde_json::from_str(&matches(&found, Format::Json)).unwrap();
+        assert_eq!(value[0]["score"], 0.756);
+        assert_eq!(value[0]["alert_id"], "0123456789abcdef0123");
+    }
+
+    #[test]
+    fn test_alert_lists_attachments() {
+        let rendered = alert(&record(), Format::Table);
+        assert!(
+            rendered
+                .lines()
+                .any(|line| line
+                    == "attachment:   https://example.org/ana.jpg (image/jpeg, at home)")
+        );
+        assert!(!rendered.contains("url 1"));
+    }
+
+    #[test]
+    fn test_import_lists_failures() {
+        let response = ImportAlertsResponse {
+            results: vec![
+                ImportResult {
+                    record: 1,
+                    error: String::new(),
+                },
+                ImportResult {
+                    record: 2,
+                    error: "invalid country code".into(),
+                },
+            ],
+            imported: 1,
+            failed: 1,
+            dry_run: true,
+        };
+        assert_eq!(
+            import(&response, Format::Table),
+            "RECORD  ERROR\n2       invalid country code\n\n1 valid, 1 failed"
+        );
+    }
+
+    #[test]
+    fn test_alert_json_keeps_every_field() {
+        let value: Value = serde_json::from_str(&alerts(&[record()], Format::Json)).unwrap();
+        assert_eq!(value[0]["alert_id"], "0123456789abcdef0123");
+        assert_eq!(value[0]["seq"], 3);
+        assert_eq!(value[0]["revoked"], true);
+        assert_eq!(value[0]["data"]["last_name"], "Pérez");
+        assert_eq!(value[0]["data"]["attachments"][0]["caption"], "at home");
+
+        let line = streamed_alert(&record(), Format::Json);
+        assert!(!line.contains('\n'));
+    }
+}
@@ -0,0 +1,174 @@
+pub mod alert_store;
+pub mod ban_store;
+pub mod search;
+pub mod seen_cache;
+
+use std::fmt;
+use std::path::Path;
+
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+
+/// Migrations embedded in the binary, as `(version, up.sql)`. The version is
+/// the directory name prefix without dashes, like the Diesel CLI records it,
+/// so databases migrated with either stay compatible.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "202511062338120000",
+        include_str!("../migrations/2025-11-06-233812-0000_create_alerts/up.sql"),
+    ),
+    (
+        "202610181200000000",
+        include_str!("../migrations/2026-10-18-120000-0000_create_seen_messages/up.sql"),
+    ),
+    (
+        "202610181300000000",
+        include_str!("../migrations/2026-10-18-130000-0000_add_alerts_origin/up.sql"),
+    ),
+    (
+        "202610181400000000",
+        include_str!("../migrations/2026-10-18-140000-0000_create_banned_peers/up.sql"),
+    ),
+    (
+        "202610181500000000",
+        include_str!("../migrations/2026-10-18-150000-0000_create_outbox/up.sql"),
+    ),
+    (
+        "202610181600000000",
+        include_str!("../migrations/2026-10-18-160000-0000_add_alerts_revokes/up.sql"),
+    ),
+    (
+        "202610181700000000",
+        include_str!("../migrations/2026-10-18-170000-0000_create_alerts_search/up.sql"),
+    ),
+    (
+        "202610190900000000",
+        include_str!("../migrations/2026-10-19-090000-0000_add_photos_attachments/up.sql"),
+    ),
+    (
+        "202610191000000000",
+        include_str!("../migrations/2026-10-19-100000-0000_add_alerts_signature/up.sql"),
+    ),
+];
+
+/// Errors of the local SQLite database
+#[derive(Debug)]
+pub enum DbError {
+    /// The database file could not be opened
+    Connection(ConnectionError),
+    /// A statement failed
+    Query(diesel::result::Error),
+    /// The directory of the database could not be created
+    Io(std::io::Error),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Connection(e) => write!(f, "cannot open database: {e}"),
+            DbError::Query(e) => write!(f, "database query failed: {e}"),
+            DbError::Io(e) => write!(f, "cannot create database directory: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Connection(e) => Some(e),
+            DbError::Query(e) => Some(e),
+            DbError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<ConnectionError> for DbError {
+    fn from(e: ConnectionError) -> Self {
+        DbError::Connection(e)
+    }
+}
+
+impl From<diesel::result::Error> for DbError {
+    fn from(e: diesel::result::Error) -> Self {
+        DbError::Query(e)
+    }
+}
+
+impl From<std::io::Error> for DbError {
+    fn from(e: std::io::Error) -> Self {
+        DbError::Io(e)
+    }
+}
+
+/// Opens the database at `path`, creating it if needed, and applies the
+/// pending migrations
+pub fn establish_connection(path: impl AsRef<Path>) -> Result<SqliteConnection, DbError> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut conn = SqliteConnection::establish(&path.to_string_lossy())?;
+    conn.batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;")?;
+    run_migrations(&mut conn)?;
+    Ok(conn)
+}
+
+#[derive(QueryableByName)]
+struct AppliedMigration {
+    #[diesel(sql_type = Text)]
+    version: String,
+}
+
+/// Applies the embedded migrations that are not recorded yet
+pub fn run_migrations(conn: &mut SqliteConnection) -> Result<(), DbError> {
+    conn.batch_execute(
+        "CREATE TABLE IF NOT EXISTS __diesel_schema_migrations (
+            version VARCHAR(50) PRIMARY KEY NOT NULL,
+            run_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+
+    let applied: Vec<String> = diesel::sql_query("SELECT version FROM __diesel_schema_migrations")
+        .load::<AppliedMigration>(conn)?
+        .into_iter()
+        .map(|m| m.version)
+        .collect();
+
+    for (version, up) in MIGRATIONS {
+        if applied.iter().any(|v| v == version) {
+            continue;
+        }
+        conn.transaction(|conn| {
+            conn.batch_execute(up)?;
+            diesel::sql_query("INSERT INTO __diesel_schema_migrations (version) VALUES (?)")
+                .bind::<Text, _>(*version)
+                .execute(conn)
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_applied_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/database.db");
+
+        let mut conn = establish_connection(&path).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let applied = diesel::sql_query("SELECT version FROM __diesel_schema_migrations")
+            .load::<AppliedMigration>(&mut conn)
+            .unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+
+        // Reopening keeps the data
+        drop(conn);
+        assert!(establish_connection(&path).is_ok());
+    }
+}
@@ -0,0 +1,930 @@
+use std::collections::{HashMap, HashSet};
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text};
+use libp2p::identity::Keypair;
+
+use crate::db::DbError;
+use crate::db::search;
+use crate::p2p_kad::alert_message::{AlertMessage, Attachment};
+use crate::schema::{alerts, outbox, photos};
+
+/// Row of the `alerts` table
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = alerts)]
+struct AlertRow {
+    id: Option<i32>,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    description: Option<String>,
+    yob: Option<i32>,
+    url_1: Option<String>,
+    url_2: Option<String>,
+    url_3: Option<String>,
+    country: Option<String>,
+    type_alert: Option<String>,
+    name_alert: Option<String>,
+    origin: Option<String>,
+    seq: Option<i64>,
+    revokes: Option<String>,
+    signature: Option<String>,
+}
+
+impl From<AlertRow> for AlertMessage {
+    fn from(row: AlertRow) -> Self {
+        Self {
+            first_name: row.first_name.unwrap_or_default(),
+            last_name: row.last_name.unwrap_or_default(),
+            description: row.description.unwrap_or_default(),
+            yob: row.yob.unwrap_or_default(),
+            url_1: row.url_1.unwrap_or_default(),
+            url_2: row.url_2.unwrap_or_default(),
+            url_3: row.url_3.unwrap_or_default(),
+            country: row.country.unwrap_or_default(),
+            type_alert: row.type_alert.unwrap_or_default(),
+            name_alert: row.name_alert.unwrap_or_default(),
+            origin: row.origin.unwrap_or_default(),
+            seq: row.seq.unwrap_or_default() as u64,
+            revokes: row.revokes,
+            signature: row.signature.unwrap_or_default(),
+            // Read from `photos`
+            attachments: Vec::new(),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = alerts)]
+struct NewAlert<'a> {
+    first_name: &'a str,
+    last_name: &'a str,
+    description: &'a str,
+    yob: i32,
+    url_1: &'a str,
+    url_2: &'a str,
+    url_3: &'a str,
+    country: &'a str,
+    type_alert: &'a str,
+    name_alert: &'a str,
+    alert_id: String,
+    origin: &'a str,
+    seq: i64,
+    revokes: Option<&'a str>,
+    signature: Option<&'a str>,
+}
+
+impl<'a> From<&'a AlertMessage> for NewAlert<'a> {
+    fn from(alert: &'a AlertMessage) -> Self {
+        Self {
+            first_name: &alert.first_name,
+            last_name: &alert.last_name,
+            description: &alert.description,
+            yob: alert.yob,
+            url_1: &alert.url_1,
+            url_2: &alert.url_2,
+            url_3: &alert.url_3,
+            country: &alert.country,
+            type_alert: &alert.type_alert,
+            name_alert: &alert.name_alert,
+            alert_id: alert.content_id(),
+            origin: &alert.origin,
+            seq: alert.seq as i64,
+            revokes: alert.revokes.as_deref(),
+            signature: Some(alert.signature.as_str()).filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+/// Row of the `photos` table
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = photos)]
+struct PhotoRow {
+    alert_id: Option<i32>,
+    url: Option<String>,
+    mime_type: Option<String>,
+    sha256: Option<String>,
+    caption: Option<String>,
+}
+
+impl From<PhotoRow> for Attachment {
+    fn from(row: PhotoRow) -> Self {
+        Self {
+            url: row.url.unwrap_or_default(),
+            mime_type: row.mime_type.unwrap_or_default(),
+            sha256: row.sha256.unwrap_or_default(),
+            caption: row.caption.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = photos)]
+struct NewPhoto<'a> {
+    alert_id: i32,
+    url: &'a str,
+    mime_type: &'a str,
+    sha256: &'a str,
+    caption: &'a str,
+}
+
+/// Stores the attachments of the alert just stored with the content id
+/// `alert_id`, keeping their order
+fn insert_photos(
+    conn: &mut SqliteConnection,
+    alert_id: &str,
+    attachments: &[Attachment],
+) -> QueryResult<()> {
+    if attachments.is_empty() {
+        return Ok(());
+    }
+    let id: Option<i32> = alerts::table
+        .filter(alerts::alert_id.eq(alert_id))
+        .select(alerts::id)
+        .first(conn)?;
+    let Some(id) = id else {
+        return Ok(());
+    };
+    let rows: Vec<NewPhoto> = attachments
+        .iter()
+        .map(|attachment| NewPhoto {
+            alert_id: id,
+            url: &attachment.url,
+            mime_type: &attachment.mime_type,
+            sha256: &attachment.sha256,
+            caption: &attachment.caption,
+        })
+        .collect();
+    diesel::insert_into(photos::table)
+        .values(&rows)
+        .execute(conn)
+        .map(|_| ())
+}
+
+/// Row of `alerts` with the columns only this node knows
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = alerts)]
+struct StoredRow {
+    alert_id: Option<String>,
+    created_at: Option<String>,
+    #[diesel(embed)]
+    alert: AlertRow,
+}
+
+/// Alert as read back from the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredAlert {
+    /// Row id, increasing with the order alerts were stored in
+    pub id: i32,
+    pub alert_id: String,
+    pub alert: AlertMessage,
+    /// When this node stored it, in SQLite `YYYY-MM-DD HH:MM:SS` UTC
+    pub created_at: String,
+    /// Revoked by its origin
+    pub revoked: bool,
+}
+
+/// Restricts listed alerts to a country and an alert type
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertFilter {
+    pub country: Option<String>,
+    pub type_alert: Option<String>,
+}
+
+impl AlertFilter {
+    /// Whether `alert` passes the filter, for alerts that are not read
+    /// from the database
+    pub fn matches(&self, alert: &AlertMessage) -> bool {
+        self.country
+            .as_ref()
+            .is_none_or(|country| country.eq_ignore_ascii_case(&alert.country))
+            && self
+                .type_alert
+                .as_ref()
+                .is_none_or(|type_alert| *type_alert == alert.type_alert)
+    }
+}
+
+/// Reads answered to the gRPC services. Revocations are not listed, the
+/// alerts they revoke are flagged instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertQuery {
+    /// The alert with this content id
+    Get(String),
+    /// Newest alerts first
+    List {
+        filter: AlertFilter,
+        limit: usize,
+        offset: usize,
+    },
+    /// Oldest alerts first, from the one stored after the row id `after`, so
+    /// pages are not shifted by alerts stored meanwhile. Row ids are set on
+    /// alerts stored before content ids too.
+    Export {
+        filter: AlertFilter,
+        after: i32,
+        limit: usize,
+    },
+}
+
+/// Alert found by a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub alert: StoredAlert,
+    /// Similarity of the names, description and alert name with the
+    /// searched words, from 0 to 1
+    pub score: f64,
+}
+
+#[derive(QueryableByName)]
+struct SearchCandidate {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+/// Index candidates read for each result, ranked again by similarity
+const CANDIDATES_PER_RESULT: usize = 10;
+
+/// Alert published locally and waiting to be gossiped
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = outbox)]
+pub struct OutboxEntry {
+    pub id: i32,
+    pub alert_id: String,
+    /// Alert as sent in gossip messages
+    pub payload: Vec<u8>,
+    /// Publish attempts that found no peer to send to
+    pub attempts: i32,
+}
+
+/// Alerts known by this node, published locally or received from peers
+pub struct AlertStore {
+    conn: SqliteConnection,
+}
+
+impl AlertStore {
+    pub fn new(conn: SqliteConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Signs `alert` as the next alert published by the node of `keypair` and
+    /// stores it, along with its entry in the outbox. Returns false, leaving
+    /// `alert` as is, when an alert with the same content is already known.
+    pub fn insert_local(
+        &mut self,
+        alert: &mut AlertMessage,
+        keypair: &Keypair,
+    ) -> Result<bool, DbError> {
+        let origin = keypair.public().to_peer_id().to_string();
+        let inserted = self.conn.transaction(|conn| {
+            let known: i64 = alerts::table
+                .filter(alerts::alert_id.eq(alert.content_id()))
+                .count()
+                .get_result(conn)?;
+            if known > 0 {
+                return QueryResult::Ok(false);
+            }
+            let last: Option<i64> = alerts::table
+                .filter(alerts::origin.eq(origin))
+                .select(diesel::dsl::max(alerts::seq))
+                .first(conn)?;
+            alert.sign(keypair, last.unwrap_or_default() as u64 + 1);
+
+            diesel::insert_into(alerts::table)
+                .values(NewAlert::from(&*alert))
+                .execute(conn)?;
+            insert_photos(conn, &alert.content_id(), &alert.attachments)?;
+            diesel::insert_into(outbox::table)
+                .values((
+                    outbox::alert_id.eq(alert.content_id()),
+                    outbox::payload.eq(alert.to_bytes()),
+                ))
+                .execute(conn)?;
+            Ok(true)
+        })?;
+        Ok(inserted)
+    }
+
+    /// Stores an alert received from a peer, returns true when it was new
+    pub fn insert(&mut self, alert: &AlertMessage) -> Result<bool, DbError> {
+        let inserted = self.conn.transaction(|conn| {
+            let inserted = diesel::insert_or_ignore_into(alerts::table)
+                .values(NewAlert::from(alert))
+                .execute(conn)?;
+            if inserted > 0 {
+                insert_photos(conn, &alert.content_id(), &alert.attachments)?;
+            }
+            QueryResult::Ok(inserted > 0)
+        })?;
+        Ok(inserted)
+    }
+
+    pub fn query(&mut self, query: &AlertQuery) -> Result<Vec<StoredAlert>, DbError> {
+        let rows: Vec<StoredRow> = match query {
+            AlertQuery::Get(alert_id) => alerts::table
+                .filter(alerts::alert_id.eq(alert_id))
+                .select(StoredRow::as_select())
+                .load(&mut self.conn)?,
+            AlertQuery::List {
+                filter,
+                limit,
+                offset,
+            } => {
+                let mut rows = alerts::table
+                    .filter(alerts::revokes.is_null())
+                    .order(alerts::id.desc())
+                    .limit(*limit as i64)
+                    .offset(*offset as i64)
+                    .select(StoredRow::as_select())
+                    .into_boxed();
+                if let Some(country) = &filter.country {
+                    rows = rows.filter(alerts::country.eq(country.to_ascii_uppercase()));
+                }
+                if let Some(type_alert) = &filter.type_alert {
+                    rows = rows.filter(alerts::type_alert.eq(type_alert));
+                }
+                rows.load(&mut self.conn)?
+            }
+            AlertQuery::Export {
+                filter,
+                after,
+                limit,
+            } => {
+                let mut rows = alerts::table
+                    .filter(alerts::id.gt(after))
+                    .filter(alerts::revokes.is_null())
+                    .order(alerts::id.asc())
+                    .limit(*limit as i64)
+                    .select(StoredRow::as_select())
+                    .into_boxed();
+                if let Some(country) = &filter.country {
+                    rows = rows.filter(alerts::country.eq(country.to_ascii_uppercase()));
+                }
+                if let Some(type_alert) = &filter.type_alert {
+                    rows = rows.filter(alerts::type_alert.eq(type_alert));
+                }
+                rows.load(&mut self.conn)?
+            }
+        };
+        self.with_revocations(rows)
+    }
+
+    /// Origin of the alert with this content id, when it is known
+    pub fn origin_of(&mut self, alert_id: &str) -> Result<Option<String>, DbError> {
+        Ok(alerts::table
+            .filter(alerts::alert_id.eq(alert_id))
+            .select(alerts::origin)
+            .first::<Option<String>>(&mut self.conn)
+            .optional()?
+            .flatten())
+    }
+
+    /// Alerts whose names, description or alert name hold words similar to
+    /// the words of `text`, best matches first. Revocations are left out.
+    pub fn search(&mut self, text: &str, limit: usize) -> Result<Vec<SearchMatch>, DbError> {
+        let Some(expression) = search::match_expression(text) else {
+            return Ok(Vec::new());
+        };
+        // Alerts sharing the most trigrams with the searched words first
+        let candidates: Vec<i32> = diesel::sql_query(
+            "SELECT rowid AS id FROM alerts_search WHERE alerts_search MATCH ? ORDER BY rank LIMIT ?",
+        )
+        .bind::<Text, _>(expression)
+        .bind::<BigInt, _>((limit * CANDIDATES_PER_RESULT) as i64)
+        .load::<SearchCandidate>(&mut self.conn)?
+        .into_iter()
+        .map(|candidate| candidate.id)
+        .collect();
+
+        let mut rows: Vec<StoredRow> = alerts::table
+            .filter(alerts::id.eq_any(&candidates))
+            .filter(alerts::revokes.is_null())
+            .select(StoredRow::as_select())
+            .load(&mut self.conn)?;
+        rows.sort_by_key(|row| candidates.iter().position(|c| Some(*c) == row.alert.id));
+        let alerts = self.with_revocations(rows)?;
+
+        let searched = search::words(text);
+        let mut matches: Vec<SearchMatch> = alerts
+            .into_iter()
+            .map(|alert| {
+                let score = search::score(
+                    &searched,
+                    &[
+                        &alert.alert.first_name,
+                        &alert.alert.last_name,
+                        &alert.alert.description,
+                        &alert.alert.name_alert,
+                    ],
+                );
+                SearchMatch { alert, score }
+            })
+            .filter(|found| found.score >= search::MIN_SCORE)
+            .collect();
+        // Stable, ties keep the index ranking
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    /// Flags the alerts revoked by a revocation of their own origin
+    fn with_revocations(&mut self, rows: Vec<StoredRow>) -> Result<Vec<StoredAlert>, DbError> {
+        let ids: Vec<&str> = rows
+            .iter()
+            .filter_map(|row| row.alert_id.as_deref())
+            .collect();
+        let revocations: HashSet<(String, String)> = alerts::table
+            .filter(alerts::revokes.eq_any(&ids))
+            .select((alerts::revokes, alerts::origin))
+            .load::<(Option<String>, Option<String>)>(&mut self.conn)?
+            .into_iter()
+            .filter_map(|(revokes, origin)| Some((revokes?, origin?)))
+            .collect();
+
+        let (stored, alerts): (Vec<_>, Vec<_>) = rows
+            .into_iter()
+            .map(|row| ((row.alert.id, row.alert_id, row.created_at), row.alert))
+            .unzip();
+        let alerts = self.with_attachments(alerts)?;
+        Ok(stored
+            .into_iter()
+            .zip(alerts)
+            .map(|((id, alert_id, created_at), alert)| {
+                let alert_id = alert_id.unwrap_or_default();
+                let revoked = revocations.contains(&(alert_id.clone(), alert.origin.clone()));
+                StoredAlert {
+                    id: id.unwrap_or_default(),
+                    alert_id,
+                    alert,
+                    created_at: created_at.unwrap_or_default(),
+                    revoked,
+                }
+            })
+            .collect())
+    }
+
+    /// Alerts of `rows` with their attachments, as they were published
+    fn with_attachments(&mut self, rows: Vec<AlertRow>) -> Result<Vec<AlertMessage>, DbError> {
+        let ids: Vec<i32> = rows.iter().filter_map(|row| row.id).collect();
+        let mut attachments: HashMap<i32, Vec<Attachment>> = HashMap::new();
+        let photos: Vec<PhotoRow> = photos::table
+            .filter(photos::alert_id.eq_any(&ids))
+            .order(photos::id.asc())
+            .select(PhotoRow::as_select())
+            .load(&mut self.conn)?;
+        for photo in photos {
+            if let Some(alert_id) = photo.alert_id {
+                attachments.entry(alert_id).or_default().push(photo.into());
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let id = row.id;
+                let mut alert = AlertMessage::from(row);
+                if let Some(found) = id.and_then(|id| attachments.remove(&id)) {
+                    alert.attachments = found;
+                }
+                alert
+            })
+            .collect())
+    }
+
+    /// Number of alerts stored
+    pub fn count(&mut self) -> Result<i64, DbError> {
+        Ok(alerts::table.count().get_result(&mut self.conn)?)
+    }
+
+    /// The last `limit` alerts stored, newest first
+    pub fn recent(&mut self, limit: usize) -> Result<Vec<AlertMessage>, DbError> {
+        let rows: Vec<AlertRow> = alerts::table
+            .order(alerts::id.desc())
+            .limit(limit as i64)
+            .select(AlertRow::as_select())
+            .load(&mut self.conn)?;
+        self.with_attachments(rows)
+    }
+
+    /// Up to `limit` outbox entries, oldest first
+    pub fn outbox(&mut self, limit: usize) -> Result<Vec<OutboxEntry>, DbError> {
+        Ok(outbox::table
+            .order(outbox::id.asc())
+            .limit(limit as i64)
+            .select(OutboxEntry::as_select())
+            .load(&mut self.conn)?)
+    }
+
+    /// Number of alerts waiting in the outbox
+    pub fn outbox_len(&mut self) -> Result<i64, DbError> {
+        Ok(outbox::table.count().get_result(&mut self.conn)?)
+    }
+
+    /// Removes an entry once its alert was gossiped
+    pub fn remove_from_outbox(&mut self, id: i32) -> Result<(), DbError> {
+        diesel::delete(outbox::table.find(id)).execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    /// Counts a failed attempt, the entry is retried later
+    pub fn outbox_attempted(&mut self, id: i32) -> Result<(), DbError> {
+        diesel::update(outbox::table.find(id))
+            .set(outbox::attempts.eq(outbox::attempts + 1))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    /// Highest sequence number known for each origin
+    pub fn watermarks(&mut self) -> Result<HashMap<String, u64>, DbError> {
+        let rows: Vec<(Option<String>, Option<i64>)> = alerts::table
+            .filter(alerts::origin.ne(""))
+            .group_by(alerts::origin)
+            .select((alerts::origin, diesel::dsl::max(alerts::seq)))
+            .load(&mut self.conn)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(origin, seq)| Some((origin?, seq? as u64)))
+            .collect())
+    }
+
+    /// Up to `limit` signed alerts of `origin` above sequence `after`, in
+    /// order. Alerts stored before alerts were signed cannot be verified by
+    /// peers and are left out.
+    pub fn missing(
+        &mut self,
+        origin: &str,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<AlertMessage>, DbError> {
+        let rows: Vec<AlertRow> = alerts::table
+            .filter(alerts::origin.eq(origin))
+            .filter(alerts::seq.gt(after as i64))
+            .filter(alerts::signature.is_not_null())
+            .order(alerts::seq.asc())
+            .limit(limit as i64)
+            .select(AlertRow::as_select())
+            .load(&mut self.conn)?;
+        self.with_attachments(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_connection;
+
+    fn store() -> (tempfile::TempDir, AlertStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = establish_connection(dir.path().join("database.db")).unwrap();
+        (dir, AlertStore::new(conn))
+    }
+
+    /// Keypair of the node named `name`
+    fn keypair(name: &str) -> Keypair {
+        let mut secret = [0; 32];
+        secret[..name.len()].copy_from_slice(name.as_bytes());
+        Keypair::ed25519_from_bytes(secret).unwrap()
+    }
+
+    fn origin(name: &str) -> String {
+        keypair(name).public().to_peer_id().to_string()
+    }
+
+    fn alert(name: &str) -> AlertMessage {
+        AlertMessage {
+            name_alert: name.into(),
+            type_alert: "amber".into(),
+            ..AlertMessage::default()
+        }
+    }
+
+    #[test]
+    fn test_insert_local_assigns_sequence() {
+        let (_dir, mut store) = store();
+
+        let mut first = alert("first");
+        let mut second = alert("second");
+        store.insert_local(&mut first, &keypair("a")).unwrap();
+        store.insert_local(&mut second, &keypair("a")).unwrap();
+
+        assert_eq!((first.origin.clone(), first.seq), (origin("a"), 1));
+        assert!(first.verify_signature());
+        assert_eq!(second.seq, 2);
+        assert_eq!(
+            store.watermarks().unwrap(),
+            HashMap::from([(origin("a"), 2)])
+        );
+    }
+
+    #[test]
+    fn test_local_alerts_go_through_the_outbox() {
+        let (_dir, mut store) = store();
+        let mut first = alert("first");
+        store.insert_local(&mut first, &keypair("a")).unwrap();
+        store
+            .insert_local(&mut alert("second"), &keypair("a"))
+            .unwrap();
+
+        let entries = store.outbox(10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].alert_id, first.content_id());
+        assert_eq!(
+            AlertMessage::from_bytes(&entries[0].payload).unwrap(),
+            first
+        );
+
+        store.outbox_attempted(entries[0].id).unwrap();
+        store.remove_from_outbox(entries[1].id).unwrap();
+        let entries = store.outbox(10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].attempts, 1);
+        assert_eq!(store.outbox_len().unwrap(), 1);
+
+        // Alerts received from peers are not republished
+        let received = AlertMessage {
+            origin: "origin-b".into(),
+            seq: 1,
+            ..alert("received")
+        };
+        store.insert(&received).unwrap();
+        assert_eq!(store.outbox_len().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_query_flags_revoked_alerts() {
+        let (_dir, mut store) = store();
+        let mut target = alert("target");
+        target.country = "AR".into();
+        store.insert_local(&mut target, &keypair("a")).unwrap();
+        store
+            .insert_local(&mut alert("other 50%"), &keypair("a"))
+            .unwrap();
+        let target_id = target.content_id();
+
+        // Only a revocation of the same origin counts
+        let forged = AlertMessage {
+            origin: "origin-b".into(),
+            seq: 1,
+            revokes: Some(target_id.clone()),
+            ..alert("")
+        };
+        store.insert(&forged).unwrap();
+        let found = store.query(&AlertQuery::Get(target_id.clone())).unwrap();
+        assert!(!found[0].revoked);
+        assert!(!found[0].created_at.is_empty());
+
+        let mut revocation = AlertMessage {
+            revokes: Some(target_id.clone()),
+            ..alert("")
+        };
+        store.insert_local(&mut revocation, &keypair("a")).unwrap();
+        let found = store.query(&AlertQuery::Get(target_id.clone())).unwrap();
+        assert!(found[0].revoked);
+        assert_eq!(store.origin_of(&target_id).unwrap().unwrap(), origin("a"));
+        assert!(store.origin_of("unknown").unwrap().is_none());
+
+        // Revocations are not listed
+        let listed = store
+            .query(&AlertQuery::List {
+                filter: AlertFilter::default(),
+                limit: 10,
+                offset: 0,
+            })
+            .unwrap();
+        let names: Vec<&str> = listed.iter().map(|a| a.alert.name_alert.as_str()).collect();
+        assert_eq!(names, ["other 50%", "target"]);
+
+        let filter = AlertFilter {
+            country: Some("ar".into()),
+            type_alert: Some("amber".into()),
+        };
+        let listed = store
+            .query(&AlertQuery::List {
+                filter: filter.clone(),
+                limit: 10,
+                offset: 0,
+            })
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(filter.matches(&listed[0].alert));
+    }
+
+    #[test]
+    fn test_search_ranks_similar_names() {
+        let (_dir, mut store) = store();
+        for (first_name, last_name) in [("José", "Pérez"), ("Ana", "Peres"), ("Ana", "Gómez")] {
+            let mut alert = AlertMessage {
+                first_name: first_name.into(),
+                last_name: last_name.into(),
+                ..alert("missing person")
+            };
+            store.insert_local(&mut alert, &keypair("a")).unwrap();
+        }
+        let names = |matches: &[SearchMatch]| -> Vec<String> {
+            matches
+                .iter()
+                .map(|m| format!("{} {}", m.alert.alert.first_name, m.alert.alert.last_name))
+                .collect()
+        };
+
+        // Typos and diacritics still match, the closest first
+        let found = store.search("perez", 10).unwrap();
+        assert_eq!(names(&found), ["José Pérez", "Ana Peres"]);
+        assert_eq!(found[0].score, 1.0);
+        assert!(found[1].score < 1.0);
+
+        let found = store.search("Ana Perez", 10).unwrap();
+        assert_eq!(names(&found)[0], "Ana Peres");
+        assert_eq!(found.len(), 3);
+        assert_eq!(store.search("Ana Perez", 1).unwrap().len(), 1);
+
+        // Revocations are left out
+        let target = store.search("Gómez", 1).unwrap().remove(0).alert;
+        let mut revocation = AlertMessage {
+            description: "Gómez".into(),
+            revokes: Some(target.alert_id.clone()),
+            ..alert("")
+        };
+        store.insert_local(&mut revocation, &keypair("a")).unwrap();
+        let found = store.search("gomez", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].alert.revoked);
+
+        assert!(store.search("xyzzy", 10).unwrap().is_empty());
+        assert!(store.search("an", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_export_pages_oldest_first() {
+        let (_dir, mut store) = store();
+        for name in ["a", "b", "c"] {
+            store.insert_local(&mut alert(name), &keypair("a")).unwrap();
+        }
+        let page = |store: &mut AlertStore, after: i32| {
+            store
+                .query(&AlertQuery::Export {
+                    filter: AlertFilter::default(),
+                    after,
+                    limit: 2,
+                })
+                .unwrap()
+        };
+
+        let first = page(&mut store, 0);
+        let names: Vec<&str> = first.iter().map(|a| a.alert.name_alert.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+
+        // Not shifted by an alert stored between pages
+        store.insert_local(&mut alert("d"), &keypair("a")).unwrap();
+        let second = page(&mut store, first[1].id);
+        let names: Vec<&str> = second.iter().map(|a| a.alert.name_alert.as_str()).collect();
+        assert_eq!(names, ["c", "d"]);
+    }
+
+    #[test]
+    fn test_export_pages_through_legacy_alerts() {
+        let (_dir, mut store) = store();
+        // Stored before alerts had a content id
+        for name in ["a", "b", "c"] {
+            diesel::insert_into(alerts::table)
+                .values((alerts::name_alert.eq(name), alerts::type_alert.eq("amber")))
+                .execute(&mut store.conn)
+                .unwrap();
+        }
+        store.insert_local(&mut alert("d"), &keypair("a")).unwrap();
+
+        let mut after = 0;
+        let mut names = Vec::new();
+        loop {
+            let page = store
+                .query(&AlertQuery::Export {
+                    filter: AlertFilter::default(),
+                    after,
+                    limit: 2,
+                })
+                .unwrap();
+            names.extend(page.iter().map(|a| a.alert.name_alert.clone()));
+            match page.last() {
+                Some(last) if page.len() == 2 => after = last.id,
+                _ => break,
+            }
+        }
+        assert_eq!(names, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_insert_is_idempotent() {
+        let (_dir, mut store) = store();
+        let alert = AlertMessage {
+            origin: "origin-a".into(),
+            seq: 7,
+            ..alert("received")
+        };
+
+        assert!(store.insert(&alert).unwrap());
+        assert!(!store.insert(&alert).unwrap());
+    }
+
+    #[test]
+    fn test_attachments_are_read_back_in_order() {
+        let (_dir, mut store) = store();
+        let photo = |url: &str| Attachment {
+            url: url.into(),
+            mime_type: "image/jpeg".into(),
+            sha256: "ab".repeat(32),
+            caption: format!("photo {url}"),
+        };
+        let mut received = AlertMessage {
+            attachments: vec![photo("c"), photo("a"), photo("b")],
+            ..alert("received")
+        };
+        received.sign(&keypair("a"), 1);
+        assert!(store.insert(&received).unwrap());
+        // A duplicate does not store its attachments twice
+        assert!(!store.insert(&received).unwrap());
+        let mut local = AlertMessage {
+            attachments: vec![photo("d")],
+            ..alert("local")
+        };
+        store.insert_local(&mut local, &keypair("b")).unwrap();
+        store.insert(&alert("bare")).unwrap();
+
+        let stored = store
+            .query(&AlertQuery::Get(received.content_id()))
+            .unwrap();
+        assert_eq!(stored[0].alert, received);
+        assert_eq!(stored[0].alert.content_id(), stored[0].alert_id);
+
+        // Alerts synced to peers keep their content id
+        let recent = store.recent(10).unwrap();
+        assert_eq!(recent, [alert("bare"), local.clone(), received.clone()]);
+        assert_eq!(store.missing(&origin("a"), 0, 10).unwrap(), [received]);
+        assert_eq!(store.missing(&origin("b"), 0, 10).unwrap(), [local]);
+    }
+
+    #[test]
+    fn test_missing_above_watermarks() {
+        let (_dir, mut store) = store();
+        for name in ["a1", "a2", "a3"] {
+            store.insert_local(&mut alert(name), &keypair("a")).unwrap();
+        }
+        store.insert_local(&mut alert("b1"), &keypair("b")).unwrap();
+
+        let missing = store.missing(&origin("a"), 1, 10).unwrap();
+        let names: Vec<&str> = missing.iter().map(|a| a.name_alert.as_str()).collect();
+        assert_eq!(names, ["a2", "a3"]);
+
+        assert_eq!(
+            (missing[0].origin.clone(), missing[0].seq),
+            (origin("a"), 2)
+        );
+
+        assert_eq!(store.missing(&origin("a"), 0, 2).unwrap().len(), 2);
+        assert_eq!(
+            store.watermarks().unwrap(),
+            HashMap::from([(origin("a"), 3), (origin("b"), 1)])
+        );
+
+        let recent = store.recent(2).unwrap();
+        let names: Vec<&str> = recent.iter().map(|a| a.name_alert.as_str()).collect();
+        assert_eq!(names, ["b1", "a3"]);
+
+        // Stored before alerts were signed, peers could not verify it
+        let unsigned = AlertMessage {
+            origin: origin("a"),
+            seq: 4,
+            ..alert("a4")
+        };
+        store.insert(&unsigned).unwrap();
+        assert!(store.missing(&origin("a"), 3, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_known_content_is_not_published_again() {
+        let (_dir, mut store) = store();
+        let received = AlertMessage {
+            origin: "origin-a".into(),
+            seq: 4,
+            ..alert("known")
+        };
+        store.insert(&received).unwrap();
+
+        let mut local = alert("known");
+        assert!(!store.insert_local(&mut local, &keypair("b")).unwrap());
+        assert_eq!(local, alert("known"));
+        assert!(store.outbox(10).unwrap().is_empty());
+
+        assert!(
+            store
+                .insert_local(&mut alert("new"), &keypair("b"))
+                .unwrap()
+        );
+        assert!(
+            !store
+                .insert_local(&mut alert("new"), &keypair("b"))
+                .unwrap()
+        );
+        assert_eq!(store.outbox(10).unwrap().len(), 1);
+        assert_eq!(store.watermarks().unwrap()[&origin("b")], 1);
+    }
+}
@@ -0,0 +1,79 @@
+use diesel::prelude::*;
+use libp2p::PeerId;
+
+use crate::db::DbError;
+use crate::schema::banned_peers;
+
+/// Peers banned at runtime, blocked again when the node restarts
+pub struct BanStore {
+    conn: SqliteConnection,
+}
+
+impl BanStore {
+    pub fn new(conn: SqliteConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Records a ban, returns true when the peer was not banned yet
+    pub fn ban(&mut self, peer: &PeerId) -> Result<bool, DbError> {
+        let inserted = diesel::insert_or_ignore_into(banned_peers::table)
+            .values(banned_peers::peer_id.eq(peer.to_string()))
+            .execute(&mut self.conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// Lifts a ban, returns true when the peer was banned
+    pub fn unban(&mut self, peer: &PeerId) -> Result<bool, DbError> {
+        let deleted =
+            diesel::delete(banned_peers::table.find(peer.to_string())).execute(&mut self.conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Number of banned peers
+    pub fn count(&mut self) -> Result<i64, DbError> {
+        Ok(banned_peers::table.count().get_result(&mut self.conn)?)
+    }
+
+    /// Banned peers, skipping rows that are not valid peer ids
+    pub fn banned(&mut self) -> Result<Vec<PeerId>, DbError> {
+        let ids: Vec<String> = banned_peers::table
+            .select(banned_peers::peer_id)
+            .load(&mut self.conn)?;
+        Ok(ids.iter().filter_map(|id| id.parse().ok()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_connection;
+
+    #[test]
+    fn test_ban_and_unban() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bans = BanStore::new(establish_connection(dir.path().join("database.db")).unwrap());
+        let peer = PeerId::random();
+
+        assert!(bans.ban(&peer).unwrap());
+        assert!(!bans.ban(&peer).unwrap());
+        assert_eq!(bans.banned().unwrap(), [peer]);
+
+        assert!(bans.unban(&peer).unwrap());
+        assert!(!bans.unban(&peer).unwrap());
+        assert!(bans.banned().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bans_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("database.db");
+        let peer = PeerId::random();
+
+        BanStore::new(establish_connection(&path).unwrap())
+            .ban(&peer)
+            .unwrap();
+
+        let mut bans = BanStore::new(establish_connection(&path).unwrap());
+        assert_eq!(bans.banned().unwrap(), [peer]);
+    }
+}
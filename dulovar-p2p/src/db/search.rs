@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Similarity below which an alert does not match. Above the 0.3 of
+/// `pg_trgm`, which lets `perez` match `person`.
+pub const MIN_SCORE: f64 = 0.4;
+
+/// Shortest word the trigram index can find
+pub const MIN_WORD_LEN: usize = 3;
+
+/// Lowercase words of `text`, diacritics removed and letters without
+/// decomposition transliterated, so `Müller` and `Muller` or `Łukasz` and
+/// `Lukasz` compare equal
+pub fn words(text: &str) -> Vec<String> {
+    let folded: String = text
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .flat_map(|c| match c {
+            'ß' => "ss".chars().collect(),
+            'æ' => "ae".chars().collect(),
+            'œ' => "oe".chars().collect(),
+            'ø' => vec!['o'],
+            'ł' => vec!['l'],
+            'đ' => vec!['d'],
+            c if c.is_alphanumeric() => vec![c],
+            _ => vec![' '],
+        })
+        .collect();
+    folded.split_whitespace().map(str::to_string).collect()
+}
+
+/// Trigrams of a word padded like `pg_trgm` does, so its start weighs more
+/// and short words still have some
+fn trigrams(word: &str) -> HashSet<[char; 3]> {
+    let padded: Vec<char> = "  "
+        .chars()
+        .chain(word.chars())
+        .chain(std::iter::once(' '))
+        .collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Shared trigrams of two words over their distinct trigrams, from 0 to 1
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// How well `fields` match the searched words: the mean of the best
+/// similarity of each searched word with a word of the fields
+pub fn score(searched: &[String], fields: &[&str]) -> f64 {
+    if searched.is_empty() {
+        return 0.0;
+    }
+    let words: Vec<String> = fields.iter().flat_map(|field| words(field)).collect();
+    let total: f64 = searched
+        .iter()
+        .map(|searched| {
+            words
+                .iter()
+                .map(|word| similarity(searched, word))
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    total / searched.len() as f64
+}
+
+/// FTS5 query of the alerts sharing a trigram with the words of `text`,
+/// ranked by how many they share. `None` when no word is long enough.
+pub fn match_expression(text: &str) -> Option<String> {
+    let mut trigrams: Vec<String> = words(text)
+        .iter()
+        .flat_map(|word| {
+            let chars: Vec<char> = word.chars().collect();
+            chars
+                .windows(MIN_WORD_LEN)
+                .map(|w| w.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    trigrams.sort();
+    trigrams.dedup();
+    if trigrams.is_empty() {
+        return None;
+    }
+    // Words only hold alphanumerics, nothing to escape inside the quotes
+    Some(
+        trigrams
+            .iter()
+            .map(|trigram| format!("\"{trigram}\""))
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_are_folded() {
+        assert_eq!(
+            words("Müller-Łukasz, STRAßE"),
+            ["muller", "lukasz", "strasse"]
+        );
+        assert_eq!(words("José  Pérez"), ["jose", "perez"]);
+    }
+
+    #[test]
+    fn test_similarity_tolerates_typos() {
+        assert_eq!(similarity("perez", "perez"), 1.0);
+        assert!(similarity("perez", "peres") > MIN_SCORE);
+        assert!(similarity("mueller", "muller") > MIN_SCORE);
+        assert!(similarity("perez", "gomez") < MIN_SCORE);
+        assert!(similarity("perez", "person") < MIN_SCORE);
+    }
+
+    #[test]
+    fn test_score_takes_the_best_word_of_each_searched_word() {
+        let searched = words("Peres Ana");
+        let exact = score(&searched, &["Ana", "Pérez"]);
+        let partial = score(&searched, &["Ana", "Gómez"]);
+        assert!(exact > partial && partial > 0.0);
+        assert_eq!(score(&[], &["Ana"]), 0.0);
+    }
+
+    #[test]
+    fn test_match_expression() {
+        assert_eq!(
+            match_expression("Ana, Pérez").as_deref(),
+            Some("\"ana\" OR \"ere\" OR \"per\" OR \"rez\"")
+        );
+        assert_eq!(match_expression("li 5"), None);
+    }
+}
@@ -0,0 +1,100 @@
+use std::num::NonZeroUsize;
+
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use lru::LruCache;
+
+use crate::db::DbError;
+use crate::schema::seen_messages;
+
+/// Alert ids kept in memory in front of the database
+const LRU_CAPACITY: usize = 10_000;
+
+/// Seen alert ids are forgotten after this long
+const RETENTION: &str = "-30 days";
+
+/// Content ids of the alerts this node already processed, whatever topic or
+/// peer they came from. The database keeps them across restarts and an LRU
+/// cache answers the recent ones without a query.
+pub struct SeenCache {
+    conn: SqliteConnection,
+    recent: LruCache<String, ()>,
+}
+
+impl SeenCache {
+    /// Uses the `seen_messages` table of `conn`, dropping expired entries
+    pub fn new(mut conn: SqliteConnection) -> Result<Self, DbError> {
+        diesel::sql_query("DELETE FROM seen_messages WHERE seen_at < datetime('now', ?)")
+            .bind::<Text, _>(RETENTION)
+            .execute(&mut conn)?;
+
+        Ok(Self {
+            conn,
+            recent: LruCache::new(NonZeroUsize::new(LRU_CAPACITY).unwrap()),
+        })
+    }
+
+    /// Records `alert_id`, returns true when it was not seen before
+    pub fn insert(&mut self, alert_id: &str) -> Result<bool, DbError> {
+        if self.recent.get(alert_id).is_some() {
+            return Ok(false);
+        }
+
+        let inserted = diesel::insert_or_ignore_into(seen_messages::table)
+            .values(seen_messages::message_id.eq(alert_id))
+            .execute(&mut self.conn)?;
+        self.recent.put(alert_id.to_string(), ());
+        Ok(inserted > 0)
+    }
+
+    /// Number of alert ids kept in the database
+    pub fn count(&mut self) -> Result<i64, DbError> {
+        Ok(seen_messages::table.count().get_result(&mut self.conn)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_connection;
+
+    #[test]
+    fn test_insert_reports_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache =
+            SeenCache::new(establish_connection(dir.path().join("database.db")).unwrap()).unwrap();
+
+        assert!(cache.insert("a").unwrap());
+        assert!(!cache.insert("a").unwrap());
+        assert!(cache.insert("b").unwrap());
+    }
+
+    #[test]
+    fn test_seen_ids_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("database.db");
+
+        let mut cache = SeenCache::new(establish_connection(&path).unwrap()).unwrap();
+        assert!(cache.insert("a").unwrap());
+        drop(cache);
+
+        let mut cache = SeenCache::new(establish_connection(&path).unwrap()).unwrap();
+        assert!(!cache.insert("a").unwrap());
+    }
+
+    #[test]
+    fn test_expired_ids_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("database.db");
+
+        let mut conn = establish_connection(&path).unwrap();
+        diesel::sql_query(
+            "INSERT INTO seen_messages (message_id, seen_at) VALUES ('old', datetime('now', '-31 days'))",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        let mut cache = SeenCache::new(conn).unwrap();
+        assert!(cache.insert("old").unwrap());
+    }
+}
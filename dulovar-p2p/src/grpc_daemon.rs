@@ -1,32 +1,44 @@
+pub mod admin_service;
 pub mod alert;
 pub mod alert_service;
-use tokio::sync::mpsc;
+pub mod records;
+use tokio_util::sync::CancellationToken;
 use tonic::transport::Server;
 
 use crate::grpc_daemon::{
-    alert::alert_service_server::AlertServiceServer, alert_service::AlertStreamer,
+    admin_service::NodeAdminService,
+    alert::{alert_service_server::AlertServiceServer, node_admin_server::NodeAdminServer},
+    alert_service::AlertStreamer,
 };
+use crate::metrics::Metrics;
+use crate::p2p_kad::command::CommandSender;
 
 pub struct GrpcDaemon {
-    sender: mpsc::UnboundedSender<String>,
+    sender: CommandSender,
+    metrics: Metrics,
 }
 
 impl GrpcDaemon {
-    pub fn new(sender: mpsc::UnboundedSender<String>) -> Self {
-        Self { sender }
+    pub fn new(sender: CommandSender, metrics: Metrics) -> Self {
+        Self { sender, metrics }
     }
 
+    /// Serves until `shutdown` is cancelled, letting the requests in flight
+    /// finish
     pub async fn run_server(
         &self,
         addr: std::net::SocketAddr,
+        shutdown: CancellationToken,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let streamer = AlertStreamer::new(self.sender.clone());
+        let streamer = AlertStreamer::new(self.sender.clone(), self.metrics.clone());
+        let admin = NodeAdminService::new(self.sender.clone(), self.metrics.clone());
 
-        println!("gRPC service running on: {}", addr);
+        tracing::info!(%addr, "gRPC service running");
 
         Server::builder()
             .add_service(AlertServiceServer::new(streamer))
-            .serve(addr)
+            .add_service(NodeAdminServer::new(admin))
+            .serve_with_shutdown(addr, shutdown.cancelled_owned())
             .await?;
 
         Ok(())
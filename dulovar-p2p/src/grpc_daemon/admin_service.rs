@@ -0,0 +1,295 @@
+use libp2p::{Multiaddr, PeerId};
+use tokio::sync::oneshot;
+use tonic::{Request, Response, Status};
+
+use crate::grpc_daemon::alert::{
+    DbStats, DialRequest, DialResponse, DisconnectPeerRequest, DisconnectPeerResponse,
+    ListPeersRequest, ListPeersResponse, NodeInfo, NodeInfoRequest, PeerBanRequest,
+    PeerBanResponse, PeerInfo, TopicMesh, node_admin_server::NodeAdmin,
+};
+use crate::metrics::Metrics;
+use crate::p2p_kad::command::{Command, CommandSender};
+use crate::p2p_kad::introspection::NodeStatus;
+
+pub struct NodeAdminService {
+    sender: CommandSender,
+    metrics: Metrics,
+}
+
+impl From<NodeStatus> for NodeInfo {
+    fn from(status: NodeStatus) -> Self {
+        Self {
+            peer_id: status.peer_id.to_string(),
+            listen_addresses: status
+                .listen_addresses
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            external_addresses: status
+                .external_addresses
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            topics: status
+                .topics
+                .into_iter()
+                .map(|(topic, mesh_peers)| TopicMesh {
+                    topic,
+                    mesh_peers: mesh_peers.iter().map(ToString::to_string).collect(),
+                })
+                .collect(),
+            kademlia_peers: status.kademlia_peers as u64,
+            db: Some(DbStats {
+                alerts: status.db.alerts,
+                outbox: status.db.outbox,
+                seen_messages: status.db.seen_messages,
+                banned_peers: status.db.banned_peers,
+            }),
+        }
+    }
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, Status> {
+    peer_id
+        .parse()
+        .map_err(|e| Status::invalid_argument(format!("invalid peer id: {e}")))
+}
+
+impl NodeAdminService {
+    pub fn new(sender: CommandSender, metrics: Metrics) -> Self {
+        Self { sender, metrics }
+    }
+
+    /// Sends the command built around a reply channel and waits for the node
+    /// to answer
+    async fn ask<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> Command,
+    ) -> Result<T, Status> {
+        let (reply, response) = oneshot::channel();
+        if self.sender.send(command(reply)).await.is_err() {
+            return Err(Status::unavailable("P2P node is not running"));
+        }
+        response
+            .await
+            .map_err(|_| Status::unavailable("P2P node stopped"))
+    }
+
+    async fn ban(
+        &self,
+        request: Request<PeerBanRequest>,
+    ) -> Result<Response<PeerBanResponse>, Status> {
+        let request = request.into_inner();
+        let peer = parse_peer_id(&request.peer_id)?;
+
+        let changed = self
+            .ask(|reply| {
+                if request.banned {
+                    Command::Ban { peer, reply }
+                } else {
+                    Command::Unban { peer, reply }
+                }
+            })
+            .await?
+            .map_err(|e| Status::internal(format!("failed to persist ban: {e}")))?;
+
+        Ok(Response::new(PeerBanResponse {
+            peer_id: peer.to_string(),
+            banned: request.banned,
+            changed,
+        }))
+    }
+
+    async fn node_info(&self) -> Result<Response<NodeInfo>, Status> {
+        let status = self
+            .ask(|reply| Command::Status { reply })
+            .await?
+            .map_err(|e| Status::internal(format!("failed to read database: {e}")))?;
+        Ok(Response::new(status.into()))
+    }
+
+    async fn peers(&self) -> Result<Response<ListPeersResponse>, Status> {
+        let peers = self.ask(|reply| Command::Peers { reply }).await?;
+        Ok(Response::new(ListPeersResponse {
+            peers: peers
+                .into_iter()
+                .map(|(peer, connected)| PeerInfo {
+                    peer_id: peer.to_string(),
+                    addresses: connected
+                        .addresses
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                    rtt_ms: connected.rtt.map(|rtt| rtt.as_millis() as u64),
+                    agent_version: connected.agent_version.unwrap_or_default(),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn dial_address(
+        &self,
+        request: Request<DialRequest>,
+    ) -> Result<Response<DialResponse>, Status> {
+        let address: Multiaddr = request
+            .into_inner()
+            .address
+            .parse()
+            .map_err(|e| Status::invalid_argument(format!("invalid multiaddr: {e}")))?;
+        self.ask(|reply| Command::Dial { address, reply })
+            .await?
+            .map_err(|e| Status::failed_precondition(format!("cannot dial: {e}")))?;
+        Ok(Response::new(DialResponse {}))
+    }
+
+    async fn disconnect(
+        &self,
+        request: Request<DisconnectPeerRequest>,
+    ) -> Result<Response<DisconnectPeerResponse>, Status> {
+        let peer = parse_peer_id(&request.into_inner().peer_id)?;
+        let disconnected = self
+            .ask(|reply| Command::Disconnect { peer, reply })
+            .await?;
+        Ok(Response::new(DisconnectPeerResponse { disconnected }))
+    }
+}
+
+#[tonic::async_trait]
+impl NodeAdmin for NodeAdminService {
+    async fn set_peer_ban(
+        &self,
+        request: Request<PeerBanRequest>,
+    ) -> Result<Response<PeerBanResponse>, Status> {
+        self.metrics
+            .time_grpc("SetPeerBan", self.ban(request))
+            .await
+    }
+
+    async fn get_node_info(
+        &self,
+        _request: Request<NodeInfoRequest>,
+    ) -> Result<Response<NodeInfo>, Status> {
+        self.metrics
+            .time_grpc("GetNodeInfo", self.node_info())
+            .await
+    }
+
+    async fn list_peers(
+        &self,
+        _request: Request<ListPeersRequest>,
+    ) -> Result<Response<ListPeersResponse>, Status> {
+        self.metrics.time_grpc("ListPeers", self.peers()).await
+    }
+
+    async fn dial(&self, request: Request<DialRequest>) -> Result<Response<DialResponse>, Status> {
+        self.metrics
+            .time_grpc("Dial", self.dial_address(request))
+            .await
+    }
+
+    async fn disconnect_peer(
+        &self,
+        request: Request<DisconnectPeerRequest>,
+    ) -> Result<Response<DisconnectPeerResponse>, Status> {
+        self.metrics
+            .time_grpc("DisconnectPeer", self.disconnect(request))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p_kad::command;
+    use crate::p2p_kad::introspection::ConnectedPeer;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_invalid_peer_id_is_rejected() {
+        let (sender, _receiver) = command::channel(1, Metrics::default());
+        let service = NodeAdminService::new(sender, Metrics::default());
+
+        let status = service
+            .set_peer_ban(Request::new(PeerBanRequest {
+                peer_id: "not-a-peer".into(),
+                banned: true,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_ban_is_sent_to_the_node() {
+        let (sender, mut receiver) = command::channel(1, Metrics::default());
+        let service = NodeAdminService::new(sender, Metrics::default());
+        let peer = PeerId::random();
+
+        let node = tokio::spawn(async move {
+            match receiver.recv().await {
+                Some(Command::Ban {
+                    peer: banned,
+                    reply,
+                }) => {
+                    assert_eq!(banned, peer);
+                    reply.send(Ok(true)).unwrap();
+                }
+                other => panic!("unexpected command {other:?}"),
+            }
+        });
+
+        let response = service
+            .set_peer_ban(Request::new(PeerBanRequest {
+                peer_id: peer.to_string(),
+                banned: true,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        node.await.unwrap();
+        assert!(response.banned);
+        assert!(response.changed);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_multiaddr_is_rejected() {
+        let (sender, _receiver) = command::channel(1, Metrics::default());
+        let service = NodeAdminService::new(sender, Metrics::default());
+
+        let status = service
+            .dial(Request::new(DialRequest {
+                address: "not-an-address".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_peers_are_listed() {
+        let (sender, mut receiver) = command::channel(1, Metrics::default());
+        let service = NodeAdminService::new(sender, Metrics::default());
+        let peer = PeerId::random();
+
+        tokio::spawn(async move {
+            if let Some(Command::Peers { reply }) = receiver.recv().await {
+                let connected = ConnectedPeer {
+                    addresses: vec!["/ip4/192.0.2.1/tcp/4001".parse().unwrap()],
+                    rtt: Some(Duration::from_millis(25)),
+                    agent_version: None,
+                };
+                reply.send(vec![(peer, connected)]).unwrap();
+            }
+        });
+
+        let response = service
+            .list_peers(Request::new(ListPeersRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.peers.len(), 1);
+        assert_eq!(response.peers[0].peer_id, peer.to_string());
+        assert_eq!(response.peers[0].rtt_ms, Some(25));
+        assert_eq!(response.peers[0].agent_version, "");
+    }
+}
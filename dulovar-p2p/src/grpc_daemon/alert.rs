@@ -1,6 +1,8 @@
 // This file is @generated by prost-build.
 /// Message from JS
-#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AlertRequestData {
     #[prost(string, tag = "1")]
     pub first_name: ::prost::alloc::string::String,
@@ -11,6 +13,8 @@ pub struct AlertRequestData {
     /// Year of Birth (Año de nacimiento)
     #[prost(int32, tag = "4")]
     pub yob: i32,
+    /// Legacy photo urls, added to the attachments when missing from them.
+    /// Read alerts have the urls of their first three attachments.
     #[prost(string, tag = "5")]
     pub url_1: ::prost::alloc::string::String,
     #[prost(string, tag = "6")]
@@ -23,6 +27,25 @@ pub struct AlertRequestData {
     pub type_alert: ::prost::alloc::string::String,
     #[prost(string, tag = "10")]
     pub name_alert: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "11")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: ::prost::alloc::vec::Vec<Attachment>,
+}
+/// Photo or document of an alert, at most 16 per alert
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct Attachment {
+    #[prost(string, tag = "1")]
+    pub url: ::prost::alloc::string::String,
+    /// Like image/jpeg, empty when unknown
+    #[prost(string, tag = "2")]
+    pub mime_type: ::prost::alloc::string::String,
+    /// Hex SHA-256 of the content, empty when unknown
+    #[prost(string, tag = "3")]
+    pub sha256: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub caption: ::prost::alloc::string::String,
 }
 /// Response from Rust
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -32,6 +55,268 @@ pub struct AlertConfirmation {
     #[prost(string, tag = "2")]
     pub status_message: ::prost::alloc::string::String,
 }
+/// Ban or unban a peer, by its base58 peer id
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct PeerBanRequest {
+    #[prost(string, tag = "1")]
+    pub peer_id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub banned: bool,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct PeerBanResponse {
+    #[prost(string, tag = "1")]
+    pub peer_id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub banned: bool,
+    /// False when the peer already was in the requested state
+    #[prost(bool, tag = "3")]
+    pub changed: bool,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct NodeInfoRequest {}
+/// Peers of a gossipsub topic mesh
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct TopicMesh {
+    #[prost(string, tag = "1")]
+    pub topic: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub mesh_peers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Rows of the local database tables
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DbStats {
+    #[prost(int64, tag = "1")]
+    pub alerts: i64,
+    /// Local alerts not gossiped yet
+    #[prost(int64, tag = "2")]
+    pub outbox: i64,
+    #[prost(int64, tag = "3")]
+    pub seen_messages: i64,
+    #[prost(int64, tag = "4")]
+    pub banned_peers: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeInfo {
+    #[prost(string, tag = "1")]
+    pub peer_id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub listen_addresses: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "3")]
+    pub external_addresses: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag = "4")]
+    pub topics: ::prost::alloc::vec::Vec<TopicMesh>,
+    /// Peers in the Kademlia routing table
+    #[prost(uint64, tag = "5")]
+    pub kademlia_peers: u64,
+    #[prost(message, optional, tag = "6")]
+    pub db: ::core::option::Option<DbStats>,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ListPeersRequest {}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct PeerInfo {
+    #[prost(string, tag = "1")]
+    pub peer_id: ::prost::alloc::string::String,
+    /// Remote addresses of the open connections
+    #[prost(string, repeated, tag = "2")]
+    pub addresses: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Last ping round trip, absent until the first ping
+    #[prost(uint64, optional, tag = "3")]
+    pub rtt_ms: ::core::option::Option<u64>,
+    /// Announced through identify, empty until identified
+    #[prost(string, tag = "4")]
+    pub agent_version: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListPeersResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub peers: ::prost::alloc::vec::Vec<PeerInfo>,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DialRequest {
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DialResponse {}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DisconnectPeerRequest {
+    #[prost(string, tag = "1")]
+    pub peer_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DisconnectPeerResponse {
+    /// False when the peer was not connected
+    #[prost(bool, tag = "1")]
+    pub disconnected: bool,
+}
+/// Stored alert, by its content id
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AlertRecord {
+    /// Hex SHA-256 of the alert, the same on every node
+    #[prost(string, tag = "1")]
+    pub alert_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub data: ::core::option::Option<AlertRequestData>,
+    /// Peer id of the node that published it
+    #[prost(string, tag = "3")]
+    pub origin: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "4")]
+    pub seq: u64,
+    /// When this node stored it, `YYYY-MM-DD HH:MM:SS` UTC
+    #[prost(string, tag = "5")]
+    pub created_at: ::prost::alloc::string::String,
+    /// Revoked by the node that published it
+    #[prost(bool, tag = "6")]
+    pub revoked: bool,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct GetAlertRequest {
+    #[prost(string, tag = "1")]
+    pub alert_id: ::prost::alloc::string::String,
+}
+/// Newest alerts first, empty fields match every alert
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ListAlertsRequest {
+    #[prost(string, tag = "1")]
+    pub country: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub type_alert: ::prost::alloc::string::String,
+    /// 20 when 0, at most 500
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+    #[prost(uint32, tag = "4")]
+    pub offset: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListAlertsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub alerts: ::prost::alloc::vec::Vec<AlertRecord>,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SearchAlertsRequest {
+    /// Words matched against the names, description and alert name, ignoring
+    /// case, diacritics and small typos. At least one word of 3 characters.
+    #[prost(string, tag = "1")]
+    pub query: ::prost::alloc::string::String,
+    /// 20 when 0, at most 500
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AlertMatch {
+    #[prost(message, optional, tag = "1")]
+    pub alert: ::core::option::Option<AlertRecord>,
+    /// Trigram similarity of the alert with the searched words, from 0 to 1
+    #[prost(double, tag = "2")]
+    pub score: f64,
+}
+/// Best matches first
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchAlertsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub matches: ::prost::alloc::vec::Vec<AlertMatch>,
+}
+/// Alerts stored from now on, empty fields match every alert
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SubscribeAlertsRequest {
+    #[prost(string, tag = "1")]
+    pub country: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub type_alert: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct RevokeAlertRequest {
+    #[prost(string, tag = "1")]
+    pub alert_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct RevokeAlertResponse {
+    /// Content id of the revocation gossiped to the peers
+    #[prost(string, tag = "1")]
+    pub revocation_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ImportAlertsRequest {
+    /// Read from the first message of the stream
+    #[prost(enumeration = "RecordFormat", tag = "1")]
+    pub format: i32,
+    /// Validates the records without storing them. Read from the first
+    /// message of the stream.
+    #[prost(bool, tag = "2")]
+    pub dry_run: bool,
+    /// One JSON object, or one CSV record, the header first
+    #[prost(string, tag = "3")]
+    pub record: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ImportResult {
+    /// Position of the record in the stream, from 1, the CSV header excluded
+    #[prost(uint64, tag = "1")]
+    pub record: u64,
+    /// Why the record was not imported, empty when it was
+    #[prost(string, tag = "2")]
+    pub error: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImportAlertsResponse {
+    /// One per record
+    #[prost(message, repeated, tag = "1")]
+    pub results: ::prost::alloc::vec::Vec<ImportResult>,
+    #[prost(uint64, tag = "2")]
+    pub imported: u64,
+    #[prost(uint64, tag = "3")]
+    pub failed: u64,
+    #[prost(bool, tag = "4")]
+    pub dry_run: bool,
+}
+/// Oldest alerts first, empty fields match every alert
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ExportAlertsRequest {
+    #[prost(enumeration = "RecordFormat", tag = "1")]
+    pub format: i32,
+    #[prost(string, tag = "2")]
+    pub country: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub type_alert: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub include_revoked: bool,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ExportAlertsResponse {
+    /// One JSON object, or one CSV record, the header first
+    #[prost(string, tag = "1")]
+    pub record: ::prost::alloc::string::String,
+}
+/// Mapping of `AlertRequestData` in imports and exports, fields named as in
+/// the message. CSV records need a header row, missing columns are empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RecordFormat {
+    JsonLines = 0,
+    Csv = 1,
+}
+impl RecordFormat {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::JsonLines => "RECORD_FORMAT_JSON_LINES",
+            Self::Csv => "RECORD_FORMAT_CSV",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "RECORD_FORMAT_JSON_LINES" => Some(Self::JsonLines),
+            "RECORD_FORMAT_CSV" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod alert_service_client {
     #![allow(
@@ -148,6 +433,177 @@ pub mod alert_service_client {
                 .insert(GrpcMethod::new("alert.AlertService", "ProcessAndStream"));
             self.inner.server_streaming(req, path, codec).await
         }
+        pub async fn get_alert(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetAlertRequest>,
+        ) -> std::result::Result<tonic::Response<super::AlertRecord>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/GetAlert",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "GetAlert"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Revocations are not listed, the alerts they revoke are flagged instead
+        pub async fn list_alerts(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListAlertsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/ListAlerts",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "ListAlerts"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn search_alerts(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchAlertsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/SearchAlerts",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "SearchAlerts"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn subscribe_alerts(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::AlertRecord>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/SubscribeAlerts",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "SubscribeAlerts"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Only the node that published an alert can revoke it
+        pub async fn revoke_alert(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RevokeAlertRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RevokeAlertResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/RevokeAlert",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "RevokeAlert"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Records are published like submitted alerts, the ones failing to parse
+        /// or validate are reported and skipped
+        pub async fn import_alerts(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<
+                Message = super::ImportAlertsRequest,
+            >,
+        ) -> std::result::Result<
+            tonic::Response<super::ImportAlertsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/ImportAlerts",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "ImportAlerts"));
+            self.inner.client_streaming(req, path, codec).await
+        }
+        pub async fn export_alerts(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExportAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ExportAlertsResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.AlertService/ExportAlerts",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.AlertService", "ExportAlerts"));
+            self.inner.server_streaming(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -176,6 +632,68 @@ pub mod alert_service_server {
             tonic::Response<Self::ProcessAndStreamStream>,
             tonic::Status,
         >;
+        async fn get_alert(
+            &self,
+            request: tonic::Request<super::GetAlertRequest>,
+        ) -> std::result::Result<tonic::Response<super::AlertRecord>, tonic::Status>;
+        /// Revocations are not listed, the alerts they revoke are flagged instead
+        async fn list_alerts(
+            &self,
+            request: tonic::Request<super::ListAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListAlertsResponse>,
+            tonic::Status,
+        >;
+        async fn search_alerts(
+            &self,
+            request: tonic::Request<super::SearchAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchAlertsResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the SubscribeAlerts method.
+        type SubscribeAlertsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::AlertRecord, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn subscribe_alerts(
+            &self,
+            request: tonic::Request<super::SubscribeAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeAlertsStream>,
+            tonic::Status,
+        >;
+        /// Only the node that published an alert can revoke it
+        async fn revoke_alert(
+            &self,
+            request: tonic::Request<super::RevokeAlertRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RevokeAlertResponse>,
+            tonic::Status,
+        >;
+        /// Records are published like submitted alerts, the ones failing to parse
+        /// or validate are reported and skipped
+        async fn import_alerts(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::ImportAlertsRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<super::ImportAlertsResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the ExportAlerts method.
+        type ExportAlertsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ExportAlertsResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        async fn export_alerts(
+            &self,
+            request: tonic::Request<super::ExportAlertsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::ExportAlertsStream>,
+            tonic::Status,
+        >;
     }
     /// Definition of service
     #[derive(Debug)]
@@ -301,29 +819,935 @@ pub mod alert_service_server {
                     };
                     Box::pin(fut)
                 }
-                _ => {
-                    Box::pin(async move {
-                        let mut response = http::Response::new(
-                            tonic::body::Body::default(),
-                        );
-                        let headers = response.headers_mut();
-                        headers
-                            .insert(
-                                tonic::Status::GRPC_STATUS,
-                                (tonic::Code::Unimplemented as i32).into(),
-                            );
-                        headers
-                            .insert(
-                                http::header::CONTENT_TYPE,
-                                tonic::metadata::GRPC_CONTENT_TYPE,
-                            );
-                        Ok(response)
-                    })
+                "/alert.AlertService/GetAlert" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetAlertSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::UnaryService<super::GetAlertRequest>
+                    for GetAlertSvc<T> {
+                        type Response = super::AlertRecord;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetAlertRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::get_alert(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetAlertSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.AlertService/ListAlerts" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListAlertsSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::UnaryService<super::ListAlertsRequest>
+                    for ListAlertsSvc<T> {
+                        type Response = super::ListAlertsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListAlertsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::list_alerts(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListAlertsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.AlertService/SearchAlerts" => {
+                    #[allow(non_camel_case_types)]
+                    struct SearchAlertsSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::UnaryService<super::SearchAlertsRequest>
+                    for SearchAlertsSvc<T> {
+                        type Response = super::SearchAlertsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SearchAlertsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::search_alerts(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SearchAlertsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.AlertService/SubscribeAlerts" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeAlertsSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::ServerStreamingService<
+                        super::SubscribeAlertsRequest,
+                    > for SubscribeAlertsSvc<T> {
+                        type Response = super::AlertRecord;
+                        type ResponseStream = T::SubscribeAlertsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeAlertsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::subscribe_alerts(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SubscribeAlertsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.AlertService/RevokeAlert" => {
+                    #[allow(non_camel_case_types)]
+                    struct RevokeAlertSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::UnaryService<super::RevokeAlertRequest>
+                    for RevokeAlertSvc<T> {
+                        type Response = super::RevokeAlertResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RevokeAlertRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::revoke_alert(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RevokeAlertSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.AlertService/ImportAlerts" => {
+                    #[allow(non_camel_case_types)]
+                    struct ImportAlertsSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::ClientStreamingService<super::ImportAlertsRequest>
+                    for ImportAlertsSvc<T> {
+                        type Response = super::ImportAlertsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::ImportAlertsRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::import_alerts(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ImportAlertsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.AlertService/ExportAlerts" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExportAlertsSvc<T: AlertService>(pub Arc<T>);
+                    impl<
+                        T: AlertService,
+                    > tonic::server::ServerStreamingService<super::ExportAlertsRequest>
+                    for ExportAlertsSvc<T> {
+                        type Response = super::ExportAlertsResponse;
+                        type ResponseStream = T::ExportAlertsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ExportAlertsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlertService>::export_alerts(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ExportAlertsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(
+                            tonic::body::Body::default(),
+                        );
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for AlertServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "alert.AlertService";
+    impl<T> tonic::server::NamedService for AlertServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
+/// Generated client implementations.
+pub mod node_admin_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Introspection of the running node and operator actions
+    #[derive(Debug, Clone)]
+    pub struct NodeAdminClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl NodeAdminClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> NodeAdminClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::Body>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> NodeAdminClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::Body>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            NodeAdminClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Bans are persisted and applied again when the node restarts
+        pub async fn set_peer_ban(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PeerBanRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PeerBanResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.NodeAdmin/SetPeerBan",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.NodeAdmin", "SetPeerBan"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_node_info(
+            &mut self,
+            request: impl tonic::IntoRequest<super::NodeInfoRequest>,
+        ) -> std::result::Result<tonic::Response<super::NodeInfo>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.NodeAdmin/GetNodeInfo",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.NodeAdmin", "GetNodeInfo"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_peers(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListPeersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListPeersResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.NodeAdmin/ListPeers",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("alert.NodeAdmin", "ListPeers"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Starts dialing, the connection shows in ListPeers once established
+        pub async fn dial(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DialRequest>,
+        ) -> std::result::Result<tonic::Response<super::DialResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/alert.NodeAdmin/Dial");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("alert.NodeAdmin", "Dial"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn disconnect_peer(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DisconnectPeerRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DisconnectPeerResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/alert.NodeAdmin/DisconnectPeer",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("alert.NodeAdmin", "DisconnectPeer"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod node_admin_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with NodeAdminServer.
+    #[async_trait]
+    pub trait NodeAdmin: std::marker::Send + std::marker::Sync + 'static {
+        /// Bans are persisted and applied again when the node restarts
+        async fn set_peer_ban(
+            &self,
+            request: tonic::Request<super::PeerBanRequest>,
+        ) -> std::result::Result<tonic::Response<super::PeerBanResponse>, tonic::Status>;
+        async fn get_node_info(
+            &self,
+            request: tonic::Request<super::NodeInfoRequest>,
+        ) -> std::result::Result<tonic::Response<super::NodeInfo>, tonic::Status>;
+        async fn list_peers(
+            &self,
+            request: tonic::Request<super::ListPeersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListPeersResponse>,
+            tonic::Status,
+        >;
+        /// Starts dialing, the connection shows in ListPeers once established
+        async fn dial(
+            &self,
+            request: tonic::Request<super::DialRequest>,
+        ) -> std::result::Result<tonic::Response<super::DialResponse>, tonic::Status>;
+        async fn disconnect_peer(
+            &self,
+            request: tonic::Request<super::DisconnectPeerRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DisconnectPeerResponse>,
+            tonic::Status,
+        >;
+    }
+    /// Introspection of the running node and operator actions
+    #[derive(Debug)]
+    pub struct NodeAdminServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> NodeAdminServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for NodeAdminServer<T>
+    where
+        T: NodeAdmin,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/alert.NodeAdmin/SetPeerBan" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetPeerBanSvc<T: NodeAdmin>(pub Arc<T>);
+                    impl<T: NodeAdmin> tonic::server::UnaryService<super::PeerBanRequest>
+                    for SetPeerBanSvc<T> {
+                        type Response = super::PeerBanResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PeerBanRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NodeAdmin>::set_peer_ban(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SetPeerBanSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.NodeAdmin/GetNodeInfo" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetNodeInfoSvc<T: NodeAdmin>(pub Arc<T>);
+                    impl<
+                        T: NodeAdmin,
+                    > tonic::server::UnaryService<super::NodeInfoRequest>
+                    for GetNodeInfoSvc<T> {
+                        type Response = super::NodeInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::NodeInfoRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NodeAdmin>::get_node_info(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetNodeInfoSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.NodeAdmin/ListPeers" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListPeersSvc<T: NodeAdmin>(pub Arc<T>);
+                    impl<
+                        T: NodeAdmin,
+                    > tonic::server::UnaryService<super::ListPeersRequest>
+                    for ListPeersSvc<T> {
+                        type Response = super::ListPeersResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListPeersRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NodeAdmin>::list_peers(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListPeersSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.NodeAdmin/Dial" => {
+                    #[allow(non_camel_case_types)]
+                    struct DialSvc<T: NodeAdmin>(pub Arc<T>);
+                    impl<T: NodeAdmin> tonic::server::UnaryService<super::DialRequest>
+                    for DialSvc<T> {
+                        type Response = super::DialResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DialRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NodeAdmin>::dial(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DialSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/alert.NodeAdmin/DisconnectPeer" => {
+                    #[allow(non_camel_case_types)]
+                    struct DisconnectPeerSvc<T: NodeAdmin>(pub Arc<T>);
+                    impl<
+                        T: NodeAdmin,
+                    > tonic::server::UnaryService<super::DisconnectPeerRequest>
+                    for DisconnectPeerSvc<T> {
+                        type Response = super::DisconnectPeerResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DisconnectPeerRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NodeAdmin>::disconnect_peer(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DisconnectPeerSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(
+                            tonic::body::Body::default(),
+                        );
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
                 }
             }
         }
     }
-    impl<T> Clone for AlertServiceServer<T> {
+    impl<T> Clone for NodeAdminServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -336,8 +1760,8 @@ pub mod alert_service_server {
         }
     }
     /// Generated gRPC service name
-    pub const SERVICE_NAME: &str = "alert.AlertService";
-    impl<T> tonic::server::NamedService for AlertServiceServer<T> {
+    pub const SERVICE_NAME: &str = "alert.NodeAdmin";
+    impl<T> tonic::server::NamedService for NodeAdminServer<T> {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
@@ -1,45 +1,429 @@
-use futures::{Stream, StreamExt, stream};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
 use std::pin::Pin;
-use tokio::sync::mpsc;
-use tonic::{Request, Response, Status};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::oneshot;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, error, info, warn};
 
+use crate::db::alert_store::{AlertFilter, AlertQuery, StoredAlert};
+use crate::db::search;
 use crate::grpc_daemon::alert::{
-    AlertConfirmation, AlertRequestData, alert_service_server::AlertService,
+    AlertConfirmation, AlertMatch, AlertRecord, AlertRequestData, Attachment, ExportAlertsRequest,
+    ExportAlertsResponse, GetAlertRequest, ImportAlertsRequest, ImportAlertsResponse, ImportResult,
+    ListAlertsRequest, ListAlertsResponse, RecordFormat, RevokeAlertRequest, RevokeAlertResponse,
+    SearchAlertsRequest, SearchAlertsResponse, SubscribeAlertsRequest,
+    alert_service_server::AlertService,
 };
+use crate::grpc_daemon::records::{self, RecordReader};
+use crate::metrics::Metrics;
+use crate::p2p_kad::alert_message::{self, AlertMessage};
+use crate::p2p_kad::command::{Command, CommandSender};
+use crate::p2p_kad::revocation::RevokeError;
+use crate::p2p_kad::validation::validate_alert;
+
+/// How long a submission waits for room in the node queue before it is
+/// refused, so clients can retry later
+const QUEUE_WAIT: Duration = Duration::from_secs(2);
+
+/// Alerts answered when the request leaves the limit at 0
+const DEFAULT_LIMIT: usize = 20;
+
+/// Most alerts answered by a single read
+const MAX_LIMIT: usize = 500;
 
 pub struct AlertStreamer {
-    sender: mpsc::UnboundedSender<String>,
+    sender: CommandSender,
+    metrics: Metrics,
 }
 
 impl AlertStreamer {
-    pub fn new(sender: mpsc::UnboundedSender<String>) -> Self {
-        Self { sender }
+    pub fn new(sender: CommandSender, metrics: Metrics) -> Self {
+        Self { sender, metrics }
+    }
+}
+
+/// Legacy urls become attachments and hold the first three attachments, so
+/// older clients and nodes keep seeing photos
+impl From<AlertRequestData> for AlertMessage {
+    fn from(data: AlertRequestData) -> Self {
+        let mut alert = Self {
+            first_name: data.first_name,
+            last_name: data.last_name,
+            description: data.description,
+            yob: data.yob,
+            url_1: data.url_1,
+            url_2: data.url_2,
+            url_3: data.url_3,
+            country: data.country,
+            type_alert: data.type_alert,
+            name_alert: data.name_alert,
+            attachments: data.attachments.into_iter().map(Into::into).collect(),
+            // Stamped by the node when it publishes the alert
+            ..Self::default()
+        };
+        alert.fill_legacy_urls();
+        alert
+    }
+}
+
+impl From<AlertMessage> for AlertRequestData {
+    fn from(mut alert: AlertMessage) -> Self {
+        // Alerts of older nodes only have the legacy urls
+        alert.merge_legacy_urls();
+        Self {
+            first_name: alert.first_name,
+            last_name: alert.last_name,
+            description: alert.description,
+            yob: alert.yob,
+            url_1: alert.url_1,
+            url_2: alert.url_2,
+            url_3: alert.url_3,
+            country: alert.country,
+            type_alert: alert.type_alert,
+            name_alert: alert.name_alert,
+            attachments: alert.attachments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<Attachment> for alert_message::Attachment {
+    fn from(attachment: Attachment) -> Self {
+        Self {
+            url: attachment.url,
+            mime_type: attachment.mime_type,
+            sha256: attachment.sha256,
+            caption: attachment.caption,
+        }
+    }
+}
+
+impl From<alert_message::Attachment> for Attachment {
+    fn from(attachment: alert_message::Attachment) -> Self {
+        Self {
+            url: attachment.url,
+            mime_type: attachment.mime_type,
+            sha256: attachment.sha256,
+            caption: attachment.caption,
+        }
+    }
+}
+
+impl From<StoredAlert> for AlertRecord {
+    fn from(stored: StoredAlert) -> Self {
+        Self {
+            alert_id: stored.alert_id,
+            origin: stored.alert.origin.clone(),
+            seq: stored.alert.seq,
+            created_at: stored.created_at,
+            revoked: stored.revoked,
+            data: Some(stored.alert.into()),
+        }
+    }
+}
+
+impl From<RevokeError> for Status {
+    fn from(e: RevokeError) -> Self {
+        match e {
+            RevokeError::NotFound => Status::not_found(e.to_string()),
+            RevokeError::NotOrigin => Status::permission_denied(e.to_string()),
+            RevokeError::AlreadyRevoked | RevokeError::IsRevocation => {
+                Status::failed_precondition(e.to_string())
+            }
+            RevokeError::Db(e) => {
+                error!(error = %e, "failed to revoke alert");
+                Status::internal("Failed to save revocation")
+            }
+        }
+    }
+}
+
+/// Filter of the request fields, empty fields matching every alert
+fn filter(country: String, type_alert: String) -> AlertFilter {
+    AlertFilter {
+        country: Some(country).filter(|country| !country.is_empty()),
+        type_alert: Some(type_alert).filter(|type_alert| !type_alert.is_empty()),
+    }
+}
+
+fn limit(limit: u32) -> usize {
+    match limit {
+        0 => DEFAULT_LIMIT,
+        limit => (limit as usize).min(MAX_LIMIT),
     }
 }
 
 type AlertStream = Pin<Box<dyn Stream<Item = Result<AlertConfirmation, Status>> + Send + 'static>>;
 
-#[tonic::async_trait]
-impl AlertService for AlertStreamer {
-    type ProcessAndStreamStream = AlertStream;
+type RecordStream = Pin<Box<dyn Stream<Item = Result<AlertRecord, Status>> + Send + 'static>>;
 
-    async fn process_and_stream(
+type ExportStream =
+    Pin<Box<dyn Stream<Item = Result<ExportAlertsResponse, Status>> + Send + 'static>>;
+
+/// Alerts of the feed passing `filter`, revocations left out. Subscribers
+/// too slow to keep up skip the alerts they missed.
+fn subscription(feed: broadcast::Receiver<AlertMessage>, filter: AlertFilter) -> RecordStream {
+    stream::unfold(feed, async |mut feed| {
+        loop {
+            match feed.recv().await {
+                Ok(alert) => return Some((alert, feed)),
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(missed, "alert subscriber lagging, alerts skipped");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter(move |alert| std::future::ready(alert.revokes.is_none() && filter.matches(alert)))
+    .map(|alert| {
+        Ok(AlertRecord::from(StoredAlert {
+            // Not read back from the database
+            id: 0,
+            alert_id: alert.content_id(),
+            alert,
+            created_at: String::new(),
+            revoked: false,
+        }))
+    })
+    .boxed()
+}
+
+/// Sends the command built around a reply channel and waits for the node to
+/// answer
+async fn ask<T>(
+    sender: &CommandSender,
+    command: impl FnOnce(oneshot::Sender<T>) -> Command,
+) -> Result<T, Status> {
+    let (reply, response) = oneshot::channel();
+    if sender.send(command(reply)).await.is_err() {
+        return Err(Status::unavailable("P2P node is not running"));
+    }
+    response
+        .await
+        .map_err(|_| Status::unavailable("P2P node stopped"))
+}
+
+async fn stored_alerts(
+    sender: &CommandSender,
+    query: AlertQuery,
+) -> Result<Vec<StoredAlert>, Status> {
+    ask(sender, |reply| Command::Alerts { query, reply })
+        .await?
+        .map_err(|e| {
+            error!(error = %e, "failed to read alerts");
+            Status::internal("Failed to read alerts")
+        })
+}
+
+/// Records of the alerts passing `filter`, oldest first, read from the node
+/// a page at a time
+fn export(
+    sender: CommandSender,
+    format: RecordFormat,
+    filter: AlertFilter,
+    include_revoked: bool,
+) -> ExportStream {
+    let header = records::header(format).map(|record| Ok(ExportAlertsResponse { record }));
+    // Row id the next page starts after, `None` once the last one was read
+    let pages = stream::try_unfold(Some(0), move |cursor| {
+        let sender = sender.clone();
+        let filter = filter.clone();
+        async move {
+            let Some(after) = cursor else {
+                return Ok::<_, Status>(None);
+            };
+            let alerts = stored_alerts(
+                &sender,
+                AlertQuery::Export {
+                    filter,
+                    after,
+                    limit: MAX_LIMIT,
+                },
+            )
+            .await?;
+            let next = alerts
+                .last()
+                .filter(|_| alerts.len() == MAX_LIMIT)
+                .map(|alert| alert.id);
+            Ok(Some((stream::iter(alerts.into_iter().map(Ok)), next)))
+        }
+    });
+    let alerts = pages
+        .try_flatten()
+        .try_filter(move |alert| std::future::ready(include_revoked || !alert.revoked))
+        .map_ok(move |alert| ExportAlertsResponse {
+            record: records::write(format, &alert.alert.into()),
+        });
+    stream::iter(header).chain(alerts).boxed()
+}
+
+impl AlertStreamer {
+    async fn alerts(&self, query: AlertQuery) -> Result<Vec<AlertRecord>, Status> {
+        let alerts = stored_alerts(&self.sender, query).await?;
+        Ok(alerts.into_iter().map(AlertRecord::from).collect())
+    }
+
+    async fn get(
         &self,
-        request: Request<AlertRequestData>,
-    ) -> Result<Response<Self::ProcessAndStreamStream>, Status> {
-        let req_data = request.into_inner();
+        request: Request<GetAlertRequest>,
+    ) -> Result<Response<AlertRecord>, Status> {
+        let alert_id = request.into_inner().alert_id;
+        self.alerts(AlertQuery::Get(alert_id.clone()))
+            .await?
+            .pop()
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found(format!("no alert {alert_id}")))
+    }
+
+    async fn list(
+        &self,
+        request: Request<ListAlertsRequest>,
+    ) -> Result<Response<ListAlertsResponse>, Status> {
+        let request = request.into_inner();
+        let alerts = self
+            .alerts(AlertQuery::List {
+                filter: filter(request.country, request.type_alert),
+                limit: limit(request.limit),
+                offset: request.offset as usize,
+            })
+            .await?;
+        Ok(Response::new(ListAlertsResponse { alerts }))
+    }
 
-        // Send data through channel
-        if let Err(e) = self.sender.send(req_data.type_alert.to_string()) {
-            return Err(Status::internal("Failed to process request"));
+    async fn search(
+        &self,
+        request: Request<SearchAlertsRequest>,
+    ) -> Result<Response<SearchAlertsResponse>, Status> {
+        let request = request.into_inner();
+        if search::match_expression(&request.query).is_none() {
+            return Err(Status::invalid_argument(format!(
+                "search for at least one word of {} characters",
+                search::MIN_WORD_LEN
+            )));
         }
+        let matches = ask(&self.sender, |reply| Command::Search {
+            text: request.query,
+            limit: limit(request.limit),
+            reply,
+        })
+        .await?
+        .map_err(|e| {
+            error!(error = %e, "failed to search alerts");
+            Status::internal("Failed to read alerts")
+        })?;
+        Ok(Response::new(SearchAlertsResponse {
+            matches: matches
+                .into_iter()
+                .map(|found| AlertMatch {
+                    alert: Some(found.alert.into()),
+                    score: found.score,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeAlertsRequest>,
+    ) -> Result<Response<RecordStream>, Status> {
+        let request = request.into_inner();
+        let feed = ask(&self.sender, |reply| Command::Subscribe { reply }).await?;
+        Ok(Response::new(subscription(
+            feed,
+            filter(request.country, request.type_alert),
+        )))
+    }
 
-        println!("------ gRPC Message -------");
-        println!(
-            "{} {} (Type: {})",
-            req_data.first_name, req_data.last_name, req_data.type_alert
+    async fn revoke(
+        &self,
+        request: Request<RevokeAlertRequest>,
+    ) -> Result<Response<RevokeAlertResponse>, Status> {
+        let alert_id = request.into_inner().alert_id;
+        let revocation_id =
+            ask(&self.sender, |reply| Command::Revoke { alert_id, reply }).await??;
+        Ok(Response::new(RevokeAlertResponse { revocation_id }))
+    }
+
+    async fn import(
+        &self,
+        request: Request<Streaming<ImportAlertsRequest>>,
+    ) -> Result<Response<ImportAlertsResponse>, Status> {
+        let mut requests = request.into_inner();
+        let mut response = ImportAlertsResponse::default();
+        let mut reader = None;
+        while let Some(request) = requests.message().await? {
+            if reader.is_none() {
+                let format = RecordFormat::try_from(request.format)
+                    .map_err(|_| Status::invalid_argument("unknown record format"))?;
+                reader = Some(RecordReader::new(format));
+                response.dry_run = request.dry_run;
+            }
+            let Some(data) = reader
+                .as_mut()
+                .and_then(|reader| reader.read(&request.record))
+            else {
+                continue;
+            };
+
+            let result = match data {
+                Ok(data) if response.dry_run => {
+                    validate_alert(&AlertMessage::from(data)).map_err(|e| e.to_string())
+                }
+                Ok(data) => self
+                    .publish(AlertMessage::from(data))
+                    .await
+                    .map(|_| ())
+                    .map_err(|status| status.message().to_string()),
+                Err(e) => Err(e),
+            };
+            let error = match result {
+                Ok(()) => {
+                    response.imported += 1;
+                    String::new()
+                }
+                Err(error) => {
+                    response.failed += 1;
+                    error
+                }
+            };
+            response.results.push(ImportResult {
+                record: response.results.len() as u64 + 1,
+                error,
+            });
+        }
+        info!(
+            imported = response.imported,
+            failed = response.failed,
+            dry_run = response.dry_run,
+            "alerts imported over gRPC"
         );
-        println!("------ gRPC Message end ---\n");
+        Ok(Response::new(response))
+    }
+
+    async fn export(
+        &self,
+        request: Request<ExportAlertsRequest>,
+    ) -> Result<Response<ExportStream>, Status> {
+        let request = request.into_inner();
+        let format = RecordFormat::try_from(request.format)
+            .map_err(|_| Status::invalid_argument("unknown record format"))?;
+        Ok(Response::new(export(
+            self.sender.clone(),
+            format,
+            filter(request.country, request.type_alert),
+            request.include_revoked,
+        )))
+    }
+
+    async fn process(
+        &self,
+        request: Request<AlertRequestData>,
+    ) -> Result<Response<AlertStream>, Status> {
+        let alert = AlertMessage::from(request.into_inner());
+        let (type_alert, country) = (alert.type_alert.clone(), alert.country.clone());
+        let alert_id = self.publish(alert).await?;
+        info!(%alert_id, %type_alert, %country, "alert received over gRPC");
 
         let confirmation_messages = vec![
             AlertConfirmation {
@@ -48,17 +432,256 @@ impl AlertService for AlertStreamer {
             },
             AlertConfirmation {
                 confirmation_id: "CONF-002".into(),
-                status_message: "Transmitted to Peers.".into(),
+                status_message: "Queued for transmission to Peers.".into(),
             },
         ];
 
         let output_stream = stream::iter(confirmation_messages.into_iter().map(Ok))
             .inspect(|result| {
                 if let Ok(conf) = result {
-                    println!("-> Sending confirmation: {}", conf.status_message);
+                    debug!(
+                        confirmation_id = %conf.confirmation_id,
+                        status = %conf.status_message,
+                        "sending confirmation"
+                    );
                 }
             })
             .boxed();
-        Ok(Response::new(output_stream as Self::ProcessAndStreamStream))
+        Ok(Response::new(output_stream as AlertStream))
+    }
+
+    /// Validates the alert, queues it for the node and waits until it is
+    /// stored, returns the content id the node stored it under. Peers reject
+    /// invalid alerts and graylist the node gossiping them.
+    async fn publish(&self, alert: AlertMessage) -> Result<String, Status> {
+        validate_alert(&alert).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let (reply, stored) = oneshot::channel();
+        match self
+            .sender
+            .send_timeout(
+                Command::Publish {
+                    alert: Box::new(alert),
+                    reply,
+                },
+                QUEUE_WAIT,
+            )
+            .await
+        {
+            Ok(()) => {}
+            Err(SendTimeoutError::Timeout(_)) => {
+                warn!("node queue is full, alert refused");
+                return Err(Status::resource_exhausted(
+                    "Node is busy, retry the request later",
+                ));
+            }
+            Err(SendTimeoutError::Closed(_)) => {
+                error!("P2P node is not running, alert dropped");
+                return Err(Status::internal("Failed to process request"));
+            }
+        }
+
+        // Confirmed once the alert is durable, the node gossips it from
+        // the outbox even after a restart
+        match stored.await {
+            Ok(Ok(alert_id)) => Ok(alert_id),
+            Ok(Err(e)) => {
+                error!(error = %e, "failed to store alert");
+                Err(Status::internal("Failed to save alert"))
+            }
+            Err(_) => Err(Status::unavailable("P2P node stopped")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AlertService for AlertStreamer {
+    type ProcessAndStreamStream = AlertStream;
+
+    async fn process_and_stream(
+        &self,
+        request: Request<AlertRequestData>,
+    ) -> Result<Response<Self::ProcessAndStreamStream>, Status> {
+        self.metrics
+            .time_grpc("ProcessAndStream", self.process(request))
+            .await
+    }
+
+    async fn get_alert(
+        &self,
+        request: Request<GetAlertRequest>,
+    ) -> Result<Response<AlertRecord>, Status> {
+        self.metrics.time_grpc("GetAlert", self.get(request)).await
+    }
+
+    async fn list_alerts(
+        &self,
+        request: Request<ListAlertsRequest>,
+    ) -> Result<Response<ListAlertsResponse>, Status> {
+        self.metrics
+            .time_grpc("ListAlerts", self.list(request))
+            .await
+    }
+
+    async fn search_alerts(
+        &self,
+        request: Request<SearchAlertsRequest>,
+    ) -> Result<Response<SearchAlertsResponse>, Status> {
+        self.metrics
+            .time_grpc("SearchAlerts", self.search(request))
+            .await
+    }
+
+    type SubscribeAlertsStream = RecordStream;
+
+    async fn subscribe_alerts(
+        &self,
+        request: Request<SubscribeAlertsRequest>,
+    ) -> Result<Response<Self::SubscribeAlertsStream>, Status> {
+        self.metrics
+            .time_grpc("SubscribeAlerts", self.subscribe(request))
+            .await
+    }
+
+    async fn revoke_alert(
+        &self,
+        request: Request<RevokeAlertRequest>,
+    ) -> Result<Response<RevokeAlertResponse>, Status> {
+        self.metrics
+            .time_grpc("RevokeAlert", self.revoke(request))
+            .await
+    }
+
+    async fn import_alerts(
+        &self,
+        request: Request<Streaming<ImportAlertsRequest>>,
+    ) -> Result<Response<ImportAlertsResponse>, Status> {
+        self.metrics
+            .time_grpc("ImportAlerts", self.import(request))
+            .await
+    }
+
+    type ExportAlertsStream = ExportStream;
+
+    async fn export_alerts(
+        &self,
+        request: Request<ExportAlertsRequest>,
+    ) -> Result<Response<Self::ExportAlertsStream>, Status> {
+        self.metrics
+            .time_grpc("ExportAlerts", self.export(request))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p_kad::command;
+
+    fn data() -> AlertRequestData {
+        AlertRequestData {
+            first_name: "Ana".into(),
+            country: "AR".into(),
+            type_alert: "amber".into(),
+            name_alert: "Ana missing".into(),
+            ..AlertRequestData::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alert_is_confirmed_once_stored() {
+        let (sender, mut receiver) = command::channel(1, Metrics::default());
+        let streamer = AlertStreamer::new(sender, Metrics::default());
+
+        let node = tokio::spawn(async move {
+            match receiver.recv().await {
+                Some(Command::Publish { alert, reply }) => {
+                    reply.send(Ok(alert.content_id())).unwrap()
+                }
+                other => panic!("unexpected command {other:?}"),
+            }
+        });
+
+        let confirmations: Vec<_> = streamer
+            .process_and_stream(Request::new(data()))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        node.await.unwrap();
+        assert_eq!(confirmations.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_alert_is_refused_before_the_node() {
+        let (sender, mut receiver) = command::channel(1, Metrics::default());
+        let streamer = AlertStreamer::new(sender, Metrics::default());
+
+        let invalid = AlertRequestData {
+            country: "Argentina".into(),
+            ..data()
+        };
+        for data in [AlertRequestData::default(), invalid] {
+            let status = streamer
+                .process_and_stream(Request::new(data))
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscription_filters_alerts() {
+        let (feed, receiver) = broadcast::channel(8);
+        let alert = |country: &str, revokes: Option<&str>| AlertMessage {
+            country: country.into(),
+            type_alert: "amber".into(),
+            revokes: revokes.map(Into::into),
+            ..AlertMessage::default()
+        };
+        feed.send(alert("UY", None)).unwrap();
+        feed.send(alert("AR", Some("abc"))).unwrap();
+        feed.send(alert("AR", None)).unwrap();
+        drop(feed);
+
+        let records: Vec<_> = subscription(receiver, filter("ar".into(), String::new()))
+            .collect()
+            .await;
+        assert_eq!(records.len(), 1);
+        let record = records[0].as_ref().unwrap();
+        assert_eq!(record.data.as_ref().unwrap().country, "AR");
+        assert_eq!(record.alert_id, alert("AR", None).content_id());
+    }
+
+    #[test]
+    fn test_limit_defaults_and_is_capped() {
+        assert_eq!(limit(0), DEFAULT_LIMIT);
+        assert_eq!(limit(7), 7);
+        assert_eq!(limit(10_000), MAX_LIMIT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_alert_is_refused_when_the_queue_is_full() {
+        let (sender, mut receiver) = command::channel(1, Metrics::default());
+        let (reply, _) = oneshot::channel();
+        sender
+            .send(Command::Publish {
+                alert: Box::default(),
+                reply,
+            })
+            .await
+            .unwrap();
+        let streamer = AlertStreamer::new(sender, Metrics::default());
+
+        let status = streamer
+            .process_and_stream(Request::new(data()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(receiver.recv().await.is_some());
     }
 }
@@ -0,0 +1,239 @@
+use csv::StringRecord;
+
+use crate::grpc_daemon::alert::{AlertRequestData, RecordFormat};
+
+/// Columns of exported CSV records, in the order of the message fields.
+/// Attachments only travel in JSON Lines, the url columns hold the first
+/// three.
+pub const CSV_HEADER: [&str; 10] = [
+    "first_name",
+    "last_name",
+    "description",
+    "yob",
+    "url_1",
+    "url_2",
+    "url_3",
+    "country",
+    "type_alert",
+    "name_alert",
+];
+
+/// Reads the records of an import, one at a time. CSV imports start with
+/// their header.
+pub struct RecordReader {
+    format: RecordFormat,
+    header: Option<StringRecord>,
+}
+
+impl RecordReader {
+    pub fn new(format: RecordFormat) -> Self {
+        Self {
+            format,
+            header: None,
+        }
+    }
+
+    /// Alert of `record`, or `None` for the CSV header
+    pub fn read(&mut self, record: &str) -> Option<Result<AlertRequestData, String>> {
+        match self.format {
+            RecordFormat::JsonLines => {
+                Some(serde_json::from_str(record).map_err(|e| format!("invalid JSON: {e}")))
+            }
+            RecordFormat::Csv => {
+                let row = match csv_record(record) {
+                    Ok(row) => row,
+                    Err(e) => return Some(Err(e)),
+                };
+                match &self.header {
+                    None => {
+                        self.header = Some(row);
+                        None
+                    }
+                    Some(header) => Some(
+                        row.deserialize(Some(header))
+                            .map_err(|e| format!("invalid CSV: {e}")),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// The single CSV record of `text`
+fn csv_record(text: &str) -> Result<StringRecord, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(text.as_bytes());
+    let mut records = reader.records();
+    match (records.next(), records.next()) {
+        (Some(Ok(record)), None) => Ok(record),
+        (Some(Err(e)), _) => Err(format!("invalid CSV: {e}")),
+        (None, _) => Err("empty record".into()),
+        (Some(Ok(_)), Some(_)) => Err("more than one CSV record".into()),
+    }
+}
+
+/// Records of a whole file, split for the messages of an import. CSV
+/// records may span lines when quoted fields hold line breaks.
+pub fn split(format: RecordFormat, text: &str) -> Result<Vec<String>, String> {
+    match format {
+        RecordFormat::JsonLines => Ok(text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect()),
+        RecordFormat::Csv => csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(text.as_bytes())
+            .records()
+            .map(|record| {
+                record
+                    .map(|record| csv_line(|writer| writer.write_record(&record)))
+                    .map_err(|e| format!("invalid CSV: {e}"))
+            })
+            .collect(),
+    }
+}
+
+/// First record of an export, the CSV header
+pub fn header(format: RecordFormat) -> Option<String> {
+    match format {
+        RecordFormat::JsonLines => None,
+        RecordFormat::Csv => Some(csv_line(|writer| writer.write_record(CSV_HEADER))),
+    }
+}
+
+/// `data` as a record of `format`, without line terminator
+pub fn write(format: RecordFormat, data: &AlertRequestData) -> String {
+    match format {
+        RecordFormat::JsonLines => serde_json::to_string(data).unwrap_or_default(),
+        RecordFormat::Csv => csv_line(|writer| {
+            writer.serialize(AlertRequestData {
+                attachments: Vec::new(),
+                ..data.clone()
+            })
+        }),
+    }
+}
+
+fn csv_line(write: impl FnOnce(&mut csv::Writer<Vec<u8>>) -> csv::Result<()>) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    // Writing to memory only fails on unserializable values, which alerts
+    // don't have
+    let _ = write(&mut writer);
+    let bytes = writer.into_inner().unwrap_or_default();
+    String::from_utf8_lossy(&bytes)
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_daemon::alert::Attachment;
+
+    fn data() -> AlertRequestData {
+        AlertRequestData {
+            first_name: "Ana".into(),
+            last_name: "Pérez, \"La Flaca\"".into(),
+            description: "two\nlines".into(),
+            yob: 1990,
+            country: "AR".into(),
+            type_alert: "amber".into(),
+            name_alert: "missing".into(),
+            ..AlertRequestData::default()
+        }
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        for format in [RecordFormat::JsonLines, RecordFormat::Csv] {
+            let mut reader = RecordReader::new(format);
+            if let Some(header) = header(format) {
+                assert!(reader.read(&header).is_none());
+            }
+            let record = write(format, &data());
+            assert_eq!(reader.read(&record), Some(Ok(data())), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_attachments_only_travel_in_json_lines() {
+        let data = AlertRequestData {
+            url_1: "https://example.org/ana.jpg".into(),
+            attachments: vec![Attachment {
+                url: "https://example.org/ana.jpg".into(),
+                caption: "at home".into(),
+                ..Attachment::default()
+            }],
+            ..data()
+        };
+        let mut reader = RecordReader::new(RecordFormat::JsonLines);
+        let record = write(RecordFormat::JsonLines, &data);
+        assert_eq!(reader.read(&record), Some(Ok(data.clone())));
+
+        let mut reader = RecordReader::new(RecordFormat::Csv);
+        reader.read(&header(RecordFormat::Csv).unwrap());
+        let read = reader
+            .read(&write(RecordFormat::Csv, &data))
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.url_1, data.url_1);
+        assert!(read.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_csv_columns_follow_the_header() {
+        let mut reader = RecordReader::new(RecordFormat::Csv);
+        assert!(reader.read("country,name_alert,type_alert").is_none());
+
+        let data = reader.read("UY,n,red").unwrap().unwrap();
+        assert_eq!(
+            (data.country.as_str(), data.type_alert.as_str()),
+            ("UY", "red")
+        );
+        assert!(data.first_name.is_empty());
+
+        assert!(reader.read("UY,n").unwrap().is_err());
+        assert!(reader.read("UY,n,red\nAR,n,red").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_split_keeps_multiline_csv_records() {
+        let text = format!(
+            "{}\n{}\n\n{}\n",
+            header(RecordFormat::Csv).unwrap(),
+            write(RecordFormat::Csv, &data()),
+            write(RecordFormat::Csv, &AlertRequestData::default()),
+        );
+        let records = split(RecordFormat::Csv, &text).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let mut reader = RecordReader::new(RecordFormat::Csv);
+        let read: Vec<_> = records.iter().filter_map(|r| reader.read(r)).collect();
+        assert_eq!(read, [Ok(data()), Ok(AlertRequestData::default())]);
+
+        let lines = split(RecordFormat::JsonLines, "{}\n\n  \n{}").unwrap();
+        assert_eq!(lines, ["{}", "{}"]);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_refused() {
+        let mut reader = RecordReader::new(RecordFormat::JsonLines);
+        assert!(
+            reader
+                .read(r#"{"country":"AR","colour":"red"}"#)
+                .unwrap()
+                .is_err()
+        );
+        assert!(reader.read(r#"{"yob":"old"}"#).unwrap().is_err());
+
+        let mut reader = RecordReader::new(RecordFormat::Csv);
+        assert!(reader.read("country,colour").is_none());
+        assert!(reader.read("AR,red").unwrap().is_err());
+    }
+}
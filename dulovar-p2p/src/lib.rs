@@ -1,2 +1,6 @@
+pub mod db;
 pub mod grpc_daemon;
+pub mod metrics;
 pub mod p2p_kad;
+pub mod schema;
+pub mod telemetry;
@@ -1,17 +1,13 @@
 use std::error::Error;
 
-// Declaration and inclusion of code generated by tonic-build
-pub mod alert {
-    include!("grpc_daemon/alert.rs");
-}
+use dulovar_p2p::telemetry::{self, LogFormat};
 
-mod grpc_daemon;
 mod orchestrator;
-mod p2p_kad;
 use orchestrator::run_concurrent_services;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    telemetry::init(LogFormat::from_env())?;
     run_concurrent_services().await?;
 
     Ok(())
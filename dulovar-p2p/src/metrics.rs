@@ -0,0 +1,310 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::Router;
+use axum::http::{HeaderName, StatusCode, header};
+use axum::routing::get;
+use libp2p::metrics::Recorder;
+use libp2p::swarm::SwarmEvent;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::encoding::text::encode;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{Histogram, exponential_buckets};
+use prometheus_client::registry::Registry;
+use tokio_util::sync::CancellationToken;
+use tonic::Status;
+
+use crate::p2p_kad::my_behaviour::{MyBehaviour, MyBehaviourEvent};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct SourceLabels {
+    /// `gossip` or `sync`
+    pub source: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ReasonLabels {
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct TopicLabels {
+    pub topic: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct GrpcLabels {
+    pub method: String,
+    pub code: String,
+}
+
+/// Metrics of the node and its gRPC services, cheap to clone and share
+#[derive(Clone)]
+pub struct Metrics {
+    /// Metrics of the libp2p behaviours and connections
+    pub libp2p: Arc<libp2p::metrics::Metrics>,
+    alerts_received: Family<SourceLabels, Counter>,
+    alerts_published: Counter,
+    alerts_publish_failed: Counter,
+    alerts_rejected: Family<ReasonLabels, Counter>,
+    alerts_stored: Counter,
+    connected_peers: Gauge,
+    mesh_peers: Family<TopicLabels, Gauge>,
+    command_queue_depth: Gauge,
+    outbox_pending: Gauge,
+    grpc_request_duration: Family<GrpcLabels, Histogram>,
+}
+
+impl Default for Metrics {
+    /// Metrics that are recorded but not exposed, for tests
+    fn default() -> Self {
+        Self::new(&mut Registry::default())
+    }
+}
+
+impl Metrics {
+    /// Registers the metrics in `registry`, under the `dulovar` and
+    /// `libp2p` prefixes
+    pub fn new(registry: &mut Registry) -> Self {
+        let libp2p = Arc::new(libp2p::metrics::Metrics::new(registry));
+        let registry = registry.sub_registry_with_prefix("dulovar");
+
+        let alerts_received = Family::default();
+        registry.register(
+            "alerts_received",
+            "Valid alerts received from peers",
+            alerts_received.clone(),
+        );
+        let alerts_published = Counter::default();
+        registry.register(
+            "alerts_published",
+            "Alerts submitted to this node and published",
+            alerts_published.clone(),
+        );
+        let alerts_publish_failed = Counter::default();
+        registry.register(
+            "alerts_publish_failed",
+            "Alerts submitted to this node and dropped without being published",
+            alerts_publish_failed.clone(),
+        );
+        let alerts_rejected = Family::default();
+        registry.register(
+            "alerts_rejected",
+            "Alerts from peers that failed validation",
+            alerts_rejected.clone(),
+        );
+        let alerts_stored = Counter::default();
+        registry.register(
+            "alerts_stored",
+            "Alerts written to the database",
+            alerts_stored.clone(),
+        );
+        let connected_peers = Gauge::default();
+        registry.register(
+            "connected_peers",
+            "Peers with at least one established connection",
+            connected_peers.clone(),
+        );
+        let mesh_peers = Family::default();
+        registry.register(
+            "gossipsub_mesh_peers",
+            "Peers in the gossipsub mesh of each subscribed topic",
+            mesh_peers.clone(),
+        );
+        let command_queue_depth = Gauge::default();
+        registry.register(
+            "command_queue_depth",
+            "Commands from the gRPC services waiting for the node",
+            command_queue_depth.clone(),
+        );
+        let outbox_pending = Gauge::default();
+        registry.register(
+            "outbox_pending",
+            "Alerts stored locally and not gossiped yet",
+            outbox_pending.clone(),
+        );
+        let grpc_request_duration = Family::<GrpcLabels, Histogram>::new_with_constructor(|| {
+            Histogram::new(exponential_buckets(0.001, 2.0, 14))
+        });
+        registry.register(
+            "grpc_request_duration_seconds",
+            "Time spent answering gRPC requests",
+            grpc_request_duration.clone(),
+        );
+
+        Self {
+            libp2p,
+            alerts_received,
+            alerts_published,
+            alerts_publish_failed,
+            alerts_rejected,
+            alerts_stored,
+            connected_peers,
+            mesh_peers,
+            command_queue_depth,
+            outbox_pending,
+            grpc_request_duration,
+        }
+    }
+
+    pub fn alert_received(&self, source: &str) {
+        self.alerts_received
+            .get_or_create(&SourceLabels {
+                source: source.to_string(),
+            })
+            .inc();
+    }
+
+    pub fn alert_published(&self) {
+        self.alerts_published.inc();
+    }
+
+    pub fn alert_publish_failed(&self) {
+        self.alerts_publish_failed.inc();
+    }
+
+    pub fn alert_rejected(&self, reason: &str) {
+        self.alerts_rejected
+            .get_or_create(&ReasonLabels {
+                reason: reason.to_string(),
+            })
+            .inc();
+    }
+
+    pub fn alert_stored(&self) {
+        self.alerts_stored.inc();
+    }
+
+    pub fn command_queue_depth(&self, depth: usize) {
+        self.command_queue_depth.set(depth as i64);
+    }
+
+    pub fn outbox_pending(&self, pending: i64) {
+        self.outbox_pending.set(pending);
+    }
+
+    /// Records a swarm event in the libp2p metrics, behaviour events included
+    pub fn record(&self, event: &SwarmEvent<MyBehaviourEvent>) {
+        match event {
+            SwarmEvent::Behaviour(MyBehaviourEvent::Dcutr(event)) => self.libp2p.record(event),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(event)) => self.libp2p.record(event),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Identify(event)) => self.libp2p.record(event),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Kad(event)) => self.libp2p.record(event),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Ping(event)) => self.libp2p.record(event),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Relay(event)) => self.libp2p.record(event),
+            _ => {}
+        }
+        self.libp2p.record(event);
+    }
+
+    /// Refreshes the gauges read from the swarm state
+    pub fn observe_swarm(&self, swarm: &libp2p::Swarm<MyBehaviour>) {
+        self.connected_peers
+            .set(swarm.connected_peers().count() as i64);
+        let gossipsub = &swarm.behaviour().gossipsub;
+        for topic in gossipsub.topics() {
+            self.mesh_peers
+                .get_or_create(&TopicLabels {
+                    topic: topic.to_string(),
+                })
+                .set(gossipsub.mesh_peers(topic).count() as i64);
+        }
+    }
+
+    /// Runs the handler of the gRPC `method`, recording how long it took
+    /// and the status it answered
+    pub async fn time_grpc<T>(
+        &self,
+        method: &str,
+        call: impl Future<Output = Result<T, Status>>,
+    ) -> Result<T, Status> {
+        let start = Instant::now();
+        let result = call.await;
+        let code = match &result {
+            Ok(_) => tonic::Code::Ok,
+            Err(status) => status.code(),
+        };
+        self.grpc_request_duration
+            .get_or_create(&GrpcLabels {
+                method: method.to_string(),
+                code: format!("{code:?}"),
+            })
+            .observe(start.elapsed().as_secs_f64());
+        result
+    }
+}
+
+/// Serves the metrics of `registry` on `GET /metrics` until `shutdown` is
+/// cancelled
+pub async fn serve(
+    addr: SocketAddr,
+    registry: Arc<Registry>,
+    shutdown: CancellationToken,
+) -> std::io::Result<()> {
+    let app = Router::new().route(
+        "/metrics",
+        get(move || {
+            let registry = registry.clone();
+            async move { render(&registry) }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "metrics endpoint running");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await
+}
+
+fn render(registry: &Registry) -> (StatusCode, [(HeaderName, &'static str); 1], String) {
+    let mut body = String::new();
+    match encode(&mut body, registry) {
+        Ok(()) => (StatusCode::OK, [(header::CONTENT_TYPE, CONTENT_TYPE)], body),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [(header::CONTENT_TYPE, "text/plain")],
+            e.to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_are_encoded() {
+        let mut registry = Registry::default();
+        let metrics = Metrics::new(&mut registry);
+
+        metrics.alert_received("gossip");
+        metrics.alert_rejected("wrong_topic");
+        metrics.alert_published();
+        metrics.alert_publish_failed();
+        metrics.command_queue_depth(3);
+        let result = metrics
+            .time_grpc("ProcessAndStream", async {
+                Err::<(), _>(Status::resource_exhausted("full"))
+            })
+            .await;
+        assert!(result.is_err());
+
+        let mut body = String::new();
+        encode(&mut body, &registry).unwrap();
+        assert!(body.contains("dulovar_alerts_received_total{source=\"gossip\"} 1"));
+        assert!(body.contains("dulovar_alerts_rejected_total{reason=\"wrong_topic\"} 1"));
+        assert!(body.contains("dulovar_alerts_published_total 1"));
+        assert!(body.contains("dulovar_alerts_publish_failed_total 1"));
+        assert!(body.contains("dulovar_command_queue_depth 3"));
+        assert!(body.contains(
+            "dulovar_grpc_request_duration_seconds_count{method=\"ProcessAndStream\",code=\"ResourceExhausted\"} 1"
+        ));
+        // The libp2p registry is exposed alongside
+        assert!(body.contains("libp2p_"));
+    }
+}
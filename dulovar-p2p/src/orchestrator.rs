@@ -1,31 +1,225 @@
 use dulovar_p2p::grpc_daemon::GrpcDaemon;
+use dulovar_p2p::metrics::{self, Metrics};
 use dulovar_p2p::p2p_kad::P2pKad;
+use dulovar_p2p::p2p_kad::command::{self, DEFAULT_QUEUE_CAPACITY, parse_queue_capacity};
+use prometheus_client::registry::Registry;
 use std::error::Error;
-use tokio;
-use tokio::sync::mpsc;
-use tokio::task::JoinHandle;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, error, info, info_span, warn};
 
-/// Runs the Kademlia P2P node and the gRPC server concurrently.
+/// Address of the Prometheus endpoint when `DULOVAR_METRICS_ADDR` is not set,
+/// only reachable from the host
+const DEFAULT_METRICS_ADDR: &str = "127.0.0.1:9464";
+
+/// Times a subsystem is restarted before the whole process stops
+const MAX_RESTARTS: u32 = 3;
+
+/// Delay before the first restart, doubled on each following one
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A subsystem that ran this long before stopping is considered healthy
+/// again, its restarts and backoff start over
+const HEALTHY_UPTIME: Duration = Duration::from_secs(300);
+
+/// Runs the Kademlia P2P node, the gRPC server and the metrics endpoint
+/// concurrently until SIGINT or SIGTERM, or until a subsystem keeps failing.
+///
+/// On shutdown the gRPC server stops first and lets its requests finish, then
+/// the node publishes the alerts still queued and leaves its topics.
 pub async fn run_concurrent_services() -> Result<(), Box<dyn Error>> {
+    let mut registry = Registry::default();
+    let metrics = Metrics::new(&mut registry);
+    let metrics_addr: SocketAddr = std::env::var("DULOVAR_METRICS_ADDR")
+        .unwrap_or_else(|_| DEFAULT_METRICS_ADDR.to_string())
+        .parse()?;
+    let queue_capacity = match std::env::var("DULOVAR_COMMAND_QUEUE_CAPACITY") {
+        Ok(value) => parse_queue_capacity(&value).map_err(|e| {
+            format!("DULOVAR_COMMAND_QUEUE_CAPACITY must be a number of at least 1: {e}")
+        })?,
+        Err(_) => DEFAULT_QUEUE_CAPACITY,
+    };
+
     // Create communication channel
-    let (sender, receiver) = mpsc::unbounded_channel();
+    let (sender, receiver) = command::channel(queue_capacity, metrics.clone());
 
     // Crete instances
-    let grpc_daemon = GrpcDaemon::new(sender);
-    let p2p_kad = P2pKad::new(receiver);
+    let grpc_daemon = GrpcDaemon::new(sender, metrics.clone());
+    let mut p2p_kad = P2pKad::new(receiver, metrics);
 
-    let grpc_handle: JoinHandle<()> = tokio::spawn(async move {
-        let addr = "[::1]:50051".parse().unwrap();
-        println!("Starting (gRPC Server) on {}...", addr);
-        let _ = grpc_daemon.run_server(addr).await;
-    });
+    // Cancelled on a signal or when a subsystem gives up
+    let shutdown = CancellationToken::new();
+    // Cancelled once the gRPC server no longer queues alerts
+    let node_shutdown = CancellationToken::new();
 
-    let p2p_handle = tokio::spawn(async move {
-        println!("Starting (P2P Kademlia)...");
-        let _ = p2p_kad.run().await;
+    let signal_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        match wait_for_signal().await {
+            Ok(()) => info!("shutdown requested"),
+            Err(e) => error!(error = %e, "cannot listen for signals, shutting down"),
+        }
+        signal_shutdown.cancel();
     });
 
-    tokio::try_join!(grpc_handle, p2p_handle)?;
+    // Losing the metrics endpoint does not stop the node
+    let metrics_shutdown = shutdown.clone();
+    let metrics_handle = tokio::spawn(
+        async move {
+            if let Err(e) = metrics::serve(metrics_addr, Arc::new(registry), metrics_shutdown).await
+            {
+                error!(addr = %metrics_addr, error = %e, "metrics endpoint stopped");
+            }
+        }
+        .instrument(info_span!("metrics")),
+    );
+
+    let grpc_shutdown = shutdown.clone();
+    let grpc_handle = tokio::spawn(
+        supervise("gRPC server", shutdown.clone(), async move || {
+            let addr = "[::1]:50051".parse()?;
+            info!(%addr, "starting gRPC server");
+            grpc_daemon.run_server(addr, grpc_shutdown.clone()).await
+        })
+        .instrument(info_span!("grpc")),
+    );
+
+    let p2p_shutdown = node_shutdown.clone();
+    let p2p_handle = tokio::spawn(
+        supervise("P2P node", shutdown.clone(), async move || {
+            info!("starting P2P node");
+            p2p_kad.run(p2p_shutdown.clone()).await
+        })
+        .instrument(info_span!("p2p")),
+    );
+
+    let grpc_result = grpc_handle.await?;
+    // The node drains the alerts accepted before the gRPC server stopped
+    node_shutdown.cancel();
+    let p2p_result = p2p_handle.await?;
+    shutdown.cancel();
+    metrics_handle.await?;
+
+    info!("shutdown complete");
+    grpc_result.and(p2p_result).map_err(Into::into)
+}
+
+/// Runs a subsystem until `shutdown` is cancelled. When it stops on its own
+/// it is restarted with an exponential backoff, up to [`MAX_RESTARTS`]
+/// times in a row, after which `shutdown` is cancelled to stop the other
+/// subsystems. Runs lasting [`HEALTHY_UPTIME`] reset the count.
+async fn supervise(
+    name: &'static str,
+    shutdown: CancellationToken,
+    mut run: impl AsyncFnMut() -> Result<(), Box<dyn Error>>,
+) -> Result<(), String> {
+    let mut restarts = 0;
+    loop {
+        let started = Instant::now();
+        let result = run().await.map_err(|e| e.to_string());
+        if shutdown.is_cancelled() {
+            return result;
+        }
+        if started.elapsed() >= HEALTHY_UPTIME {
+            restarts = 0;
+        }
+
+        let reason = result.err().unwrap_or_else(|| "exited".to_string());
+        if restarts == MAX_RESTARTS {
+            error!(subsystem = name, %reason, "giving up, shutting down");
+            shutdown.cancel();
+            return Err(format!("{name} failed: {reason}"));
+        }
+        let delay = RESTART_BACKOFF * 2u32.pow(restarts);
+        restarts += 1;
+        warn!(subsystem = name, %reason, restarts, ?delay, "restarting");
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.cancelled() => return Ok(()),
+        }
+    }
+}
+
+/// Waits for SIGINT, or SIGTERM on Unix
+async fn wait_for_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failing_subsystem_is_restarted_then_fails_fast() {
+        let shutdown = CancellationToken::new();
+        let mut runs = 0;
+
+        let result = supervise("test", shutdown.clone(), async || {
+            runs += 1;
+            Err("boom".into())
+        })
+        .await;
+
+        assert_eq!(runs, MAX_RESTARTS + 1);
+        assert!(result.unwrap_err().contains("boom"));
+        assert!(shutdown.is_cancelled());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_restarts_are_forgotten_after_healthy_uptime() {
+        let shutdown = CancellationToken::new();
+        let token = shutdown.clone();
+        let mut runs = 0;
+
+        let result = supervise("test", shutdown.clone(), async || {
+            runs += 1;
+            if runs == 2 * MAX_RESTARTS {
+                token.cancel();
+                return Ok(());
+            }
+            // Transient failures far apart
+            tokio::time::sleep(HEALTHY_UPTIME).await;
+            Err("boom".into())
+        })
+        .await;
+
+        assert_eq!(runs, 2 * MAX_RESTARTS);
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_subsystem_recovers_after_restart() {
+        let shutdown = CancellationToken::new();
+        let token = shutdown.clone();
+        let mut runs = 0;
+
+        let result = supervise("test", shutdown.clone(), async || {
+            runs += 1;
+            if runs == 1 {
+                return Err("boom".into());
+            }
+            // Runs until the shutdown
+            token.cancel();
+            Ok(())
+        })
+        .await;
 
-    Ok(())
+        assert_eq!(runs, 2);
+        assert!(result.is_ok());
+    }
 }
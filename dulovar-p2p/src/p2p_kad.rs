@@ -1,96 +1,178 @@
+pub mod alert_message;
+pub mod command;
+pub mod config;
+pub mod console;
 pub mod event_loop;
 pub mod events;
+pub mod external_addresses;
+pub mod http_client;
+pub mod identity;
+pub mod introspection;
 pub mod my_behaviour;
+pub mod node_state;
 pub mod p2p_kad_utils;
+pub mod peer_store;
 pub mod rest_request;
+pub mod revocation;
+pub mod scoring;
+pub mod swarm;
+pub mod sync;
+pub mod topics;
+pub mod validation;
 
-use tokio::sync::mpsc;
-
-use libp2p::{
-    Transport, core::transport::upgrade::Version, gossipsub, identify, noise, ping, tcp, yamux,
-};
+use libp2p::{Multiaddr, multiaddr::Protocol};
 
 use std::error::Error;
-use tokio::{io, io::AsyncBufReadExt};
-
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, info, info_span, warn};
+
+use crate::db::alert_store::AlertStore;
+use crate::db::ban_store::BanStore;
+use crate::db::establish_connection;
+use crate::db::seen_cache::SeenCache;
+use crate::metrics::Metrics;
+use crate::p2p_kad::command::CommandReceiver;
+use crate::p2p_kad::config::P2pConfig;
+use crate::p2p_kad::console::Console;
 use crate::p2p_kad::event_loop::event_loop;
-use crate::p2p_kad::my_behaviour::MyBehaviour;
+use crate::p2p_kad::http_client::HttpClientConfig;
+use crate::p2p_kad::identity::load_or_create;
+use crate::p2p_kad::node_state::NodeState;
 use crate::p2p_kad::p2p_kad_utils::*;
+use crate::p2p_kad::peer_store::PeerStore;
 use crate::p2p_kad::rest_request::RestRequest;
+use crate::p2p_kad::swarm::build_swarm;
+use crate::p2p_kad::topics::subscriptions;
 
 pub struct P2pKad {
-    receiver: mpsc::UnboundedReceiver<String>,
+    receiver: CommandReceiver,
+    config: P2pConfig,
+    metrics: Metrics,
 }
 
 impl P2pKad {
-    pub fn new(receiver: mpsc::UnboundedReceiver<String>) -> Self {
-        Self { receiver }
-    }
-
-    pub async fn run(mut self) -> Result<(), Box<dyn Error>> {
-        // Create a Gosspipsub topic
-        let _r = RestRequest::register_node().await?;
-        let nodes = RestRequest::get_nodes().await?;
-
-        let gossipsub_topic = gossipsub::IdentTopic::new("operations");
-
-        let mut swarm = libp2p::SwarmBuilder::with_new_identity()
-            .with_tokio()
-            .with_other_transport(|key| {
-                let noise_config = noise::Config::new(key).unwrap();
-                let yamux_config = yamux::Config::default();
-
-                let base_transport =
-                    tcp::tokio::Transport::new(tcp::Config::default().nodelay(true));
-                base_transport
-                    .upgrade(Version::V1Lazy)
-                    .authenticate(noise_config)
-                    .multiplex(yamux_config)
-            })?
-            .with_dns()?
-            .with_behaviour(|key| {
-                let gossipsub_config = gossipsub::ConfigBuilder::default()
-                    .max_transmit_size(262144)
-                    .build()
-                    .map_err(io::Error::other)?;
-                Ok(MyBehaviour {
-                    gossipsub: gossipsub::Behaviour::new(
-                        gossipsub::MessageAuthenticity::Signed(key.clone()),
-                        gossipsub_config,
-                    )
-                    .expect("Valid configuration"),
-                    identify: identify::Behaviour::new(identify::Config::new(
-                        "/ipfs/0.1.0".into(),
-                        key.public(),
-                    )),
-                    ping: ping::Behaviour::new(ping::Config::new()),
-                })
-            })?
-            .build();
-
-        println!("Subscribing to {gossipsub_topic:?}");
-        swarm
-            .behaviour_mut()
-            .gossipsub
-            .subscribe(&gossipsub_topic)
-            .unwrap();
-
-        let _ = add_new_nodes(&mut swarm, nodes);
-
-        // Read full lines from stdin
-        let mut stdin = io::BufReader::new(io::stdin()).lines();
-
-        // Listen on all interfaces and whatever port the OS assigns
-        swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
-
-        event_loop(&mut self.receiver, &mut swarm, gossipsub_topic).await
+    /// Cached addresses dialed on startup
+    const MAX_CACHED_DIALS: usize = 50;
+
+    /// Node configured from the `DULOVAR_*` environment variables
+    pub fn new(receiver: CommandReceiver, metrics: Metrics) -> Self {
+        Self::with_config(receiver, P2pConfig::from_env(), metrics)
+    }
+
+    pub fn with_config(receiver: CommandReceiver, config: P2pConfig, metrics: Metrics) -> Self {
+        Self {
+            receiver,
+            config,
+            metrics,
+        }
+    }
+
+    /// Runs the node until `shutdown` is cancelled. The node can be run
+    /// again after it failed, the queued commands are kept.
+    pub async fn run(&mut self, shutdown: CancellationToken) -> Result<(), Box<dyn Error>> {
+        let mut peer_store = PeerStore::load(&self.config.peer_store_path);
+        let mut relays = self.config.relays.clone();
+
+        let rest_request = RestRequest::new(HttpClientConfig::default())?;
+        match rest_request.get_nodes().await {
+            Ok(nodes) => {
+                relays.extend(
+                    nodes
+                        .iter()
+                        .filter(|node| node.is_master())
+                        .filter_map(|node| node.address.parse::<Multiaddr>().ok()),
+                );
+                peer_store.set_registry_nodes(nodes.into_iter().map(|n| n.address).collect());
+                if let Err(e) = peer_store.save() {
+                    warn!(error = %e, "failed to save peer store");
+                }
+            }
+            Err(e) => warn!(error = %e, "failed to get nodes, dialing cached peers"),
+        }
+
+        let keypair = load_or_create(&self.config.identity_path)?;
+        let mut swarm = build_swarm(&self.config, keypair.clone())?;
+
+        for topic in subscriptions(&self.config) {
+            info!(%topic, "subscribing");
+            swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
+        }
+
+        let _ = add_new_nodes(
+            &mut swarm,
+            peer_store
+                .dial_candidates_by(Self::MAX_CACHED_DIALS, |addr| self.config.dial_rank(addr)),
+        );
+        let seen = SeenCache::new(establish_connection(&self.config.database_path)?)?;
+        let alerts = AlertStore::new(establish_connection(&self.config.database_path)?);
+        let mut bans = BanStore::new(establish_connection(&self.config.database_path)?);
+        for peer in bans.banned()? {
+            swarm.behaviour_mut().blocklist.block_peer(peer);
+        }
+        let mut state = NodeState::new(
+            keypair,
+            peer_store,
+            Arc::new(rest_request),
+            seen,
+            alerts,
+            bans,
+            self.config.listen_port,
+            self.metrics.clone(),
+        );
+        state.blocked_peers = self.config.blocked_peers.iter().copied().collect();
+
+        let console = Console::stdin();
+        if console.is_some() {
+            info!("operator console enabled, type `help` for the commands");
+        }
+
+        // Listen on all interfaces on the port announced to the registry
+        for address in self.config.listen_addresses() {
+            swarm.listen_on(address)?;
+        }
+
+        // Master nodes are reachable, the others reserve a slot on the
+        // relays so peers behind NAT can still reach them
+        if !self.config.relay_server {
+            reserve_relays(&mut swarm, &relays);
+        }
+
+        let span = info_span!("node", peer_id = %swarm.local_peer_id());
+        event_loop(
+            &mut self.receiver,
+            &mut swarm,
+            &mut state,
+            console,
+            &shutdown,
+        )
+        .instrument(span)
+        .await
+    }
+}
+
+/// Listens on a `/p2p-circuit` address of each relay, which makes the relay
+/// client reserve a slot on it
+fn reserve_relays(swarm: &mut libp2p::Swarm<my_behaviour::MyBehaviour>, relays: &[Multiaddr]) {
+    let local_peer_id = *swarm.local_peer_id();
+    for relay in relays {
+        if relay.iter().last() == Some(Protocol::P2p(local_peer_id)) {
+            continue;
+        }
+        let Some(circuit) = relay_circuit_address(relay) else {
+            warn!(%relay, "relay address has no peer id, skipping it");
+            continue;
+        };
+        match swarm.listen_on(circuit.clone()) {
+            Ok(_) => info!(%relay, "reserving a slot on relay"),
+            Err(e) => warn!(%circuit, error = %e, "failed to listen on relay circuit"),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use libp2p::Multiaddr;
     use std::str::FromStr;
     use std::time::Duration;
     use tokio::time::timeout;
@@ -153,14 +235,55 @@ mod tests {
         assert_eq!(addr.to_string(), "/ip4/127.0.0.1/tcp/4001");
     }
 
+    #[test]
+    fn test_parse_dial_address_keeps_circuit_peer_id() {
+        let circuit = "/ip4/203.0.113.7/tcp/4001/p2p/12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA/p2p-circuit/p2p/12D3KooWLHuQ5VAVNzNmhHLS7CPvmztmyCdqbdjFJu3Ecr9NWnEr";
+        let addr = parse_dial_address(circuit).unwrap();
+        assert_eq!(addr.to_string(), circuit);
+
+        let direct =
+            "/ip4/203.0.113.7/tcp/4001/p2p/12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA";
+        let addr = parse_dial_address(direct).unwrap();
+        assert_eq!(addr.to_string(), "/ip4/203.0.113.7/tcp/4001");
+    }
+
+    #[test]
+    fn test_relay_circuit_address() {
+        let relay = Multiaddr::from_str(
+            "/ip4/203.0.113.7/tcp/4001/p2p/12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA",
+        )
+        .unwrap();
+        let circuit = relay_circuit_address(&relay).unwrap();
+        assert_eq!(circuit, relay.with(Protocol::P2pCircuit));
+
+        let no_peer_id = Multiaddr::from_str("/ip4/203.0.113.7/tcp/4001").unwrap();
+        assert_eq!(relay_circuit_address(&no_peer_id), None);
+    }
+
+    #[test]
+    fn test_with_peer_id() {
+        let peer_id = libp2p::PeerId::random();
+        let addr = Multiaddr::from_str("/ip4/203.0.113.7/tcp/4001").unwrap();
+
+        let with_id = with_peer_id(addr.clone(), peer_id);
+        assert_eq!(with_id, addr.with(Protocol::P2p(peer_id)));
+        // Not appended twice
+        assert_eq!(with_peer_id(with_id.clone(), peer_id), with_id);
+    }
+
     #[tokio::test]
     async fn test_init_kad_basic_setup() {
         // This test verifies that init_kad can be called without panicking
         // We'll timeout quickly since init_kad runs indefinitely
-        let (sender, receiver) = mpsc::unbounded_channel();
-
-        let p2p_kad = P2pKad::new(receiver);
-        let result = timeout(Duration::from_millis(100), p2p_kad.run()).await;
+        // The sender is kept alive, the node stops once the queue is closed
+        let (_sender, receiver) = command::channel(16, Metrics::default());
+
+        let mut p2p_kad = P2pKad::new(receiver, Metrics::default());
+        let result = timeout(
+            Duration::from_millis(100),
+            p2p_kad.run(CancellationToken::new()),
+        )
+        .await;
 
         // Should timeout (not panic) since init_kad runs in a loop
         assert!(result.is_err());
@@ -0,0 +1,373 @@
+use libp2p::PeerId;
+use libp2p::gossipsub::{Message, MessageId};
+use libp2p::identity::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Alert as it travels between nodes, serialized as JSON in gossip messages
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AlertMessage {
+    pub first_name: String,
+    pub last_name: String,
+    pub description: String,
+    pub yob: i32,
+    /// First three attachment urls, for nodes that predate attachments
+    pub url_1: String,
+    pub url_2: String,
+    pub url_3: String,
+    /// ISO 3166-1 alpha-2 code of the country the alert applies to
+    pub country: String,
+    pub type_alert: String,
+    pub name_alert: String,
+    /// Peer id of the node that published the alert, persisted across its
+    /// restarts
+    #[serde(default)]
+    pub origin: String,
+    /// Position of the alert among the alerts published by `origin`,
+    /// starting at 1
+    #[serde(default)]
+    pub seq: u64,
+    /// Content id of an earlier alert of the same origin that this one
+    /// revokes. A revocation carries the country and type of the revoked
+    /// alert so it travels on the same topics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revokes: Option<String>,
+    /// Left out of the encoding when empty, so alerts without attachments
+    /// keep their content id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Hex signature by `origin` of the content id, origin and sequence
+    /// number, so any node can pass the alert on in a sync. Empty for alerts
+    /// of nodes that predate signatures.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub signature: String,
+}
+
+/// What the content id covers: the alert without what its publisher stamps on
+/// it. A revocation only counts from the origin of the alert it revokes, so
+/// its origin stays in: a forged one cannot take the id of the genuine one.
+#[derive(Serialize)]
+struct Content<'a> {
+    first_name: &'a str,
+    last_name: &'a str,
+    description: &'a str,
+    yob: i32,
+    url_1: &'a str,
+    url_2: &'a str,
+    url_3: &'a str,
+    country: &'a str,
+    type_alert: &'a str,
+    name_alert: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revokes: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: &'a Vec<Attachment>,
+}
+
+/// Photo or document of an alert, fetched by clients from its url
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Attachment {
+    pub url: String,
+    #[serde(default)]
+    pub mime_type: String,
+    /// Hex SHA-256 of the content, empty when unknown
+    #[serde(default)]
+    pub sha256: String,
+    #[serde(default)]
+    pub caption: String,
+}
+
+impl AlertMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("AlertMessage serializes to JSON")
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+
+    /// Content address of the alert: hex SHA-256 of the canonical encoding of
+    /// its content, so the same alert has the same id whoever publishes it
+    pub fn content_id(&self) -> String {
+        let content = Content {
+            first_name: &self.first_name,
+            last_name: &self.last_name,
+            description: &self.description,
+            yob: self.yob,
+            url_1: &self.url_1,
+            url_2: &self.url_2,
+            url_3: &self.url_3,
+            country: &self.country,
+            type_alert: &self.type_alert,
+            name_alert: &self.name_alert,
+            origin: self.revokes.as_ref().map(|_| self.origin.as_str()),
+            revokes: self.revokes.as_deref(),
+            attachments: &self.attachments,
+        };
+        let bytes = serde_json::to_vec(&content).expect("alert content serializes to JSON");
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    /// Stamps the alert with the peer id of `keypair` as origin, at `seq`,
+    /// and signs it
+    pub fn sign(&mut self, keypair: &Keypair, seq: u64) {
+        self.origin = keypair.public().to_peer_id().to_string();
+        self.seq = seq;
+        let signature = keypair
+            .sign(&self.signed_bytes())
+            .expect("node keypairs are ed25519, whose signing cannot fail");
+        self.signature = hex::encode(signature);
+    }
+
+    /// True when the alert is signed by its origin. The public key is read
+    /// from the origin peer id, which embeds ed25519 keys.
+    pub fn verify_signature(&self) -> bool {
+        let Ok(origin) = self.origin.parse::<PeerId>() else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(&self.signature) else {
+            return false;
+        };
+        let multihash = origin.as_ref();
+        // Identity multihash, the digest is the encoded public key
+        if multihash.code() != 0 {
+            return false;
+        }
+        PublicKey::try_decode_protobuf(multihash.digest())
+            .is_ok_and(|key| key.verify(&self.signed_bytes(), &signature))
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&(self.content_id(), &self.origin, self.seq))
+            .expect("signed fields serialize to JSON")
+    }
+
+    /// Appends the legacy urls missing from the attachments, for alerts of
+    /// clients and nodes that only know `url_1` to `url_3`
+    pub fn merge_legacy_urls(&mut self) {
+        for url in [&self.url_1, &self.url_2, &self.url_3] {
+            if !url.is_empty() && !self.attachments.iter().any(|a| a.url == *url) {
+                self.attachments.push(Attachment {
+                    url: url.clone(),
+                    ..Attachment::default()
+                });
+            }
+        }
+    }
+
+    /// Sets the legacy urls to the first three attachments, after merging
+    /// them in
+    pub fn fill_legacy_urls(&mut self) {
+        self.merge_legacy_urls();
+        let mut urls = self.attachments.iter().map(|a| a.url.clone());
+        self.url_1 = urls.next().unwrap_or_default();
+        self.url_2 = urls.next().unwrap_or_default();
+        self.url_3 = urls.next().unwrap_or_default();
+    }
+}
+
+/// Gossipsub message id: hash of the topic and of the alert content id, or of
+/// the raw data when it is not an alert. The topic is part of the id since the
+/// same alert is published on several topics.
+pub fn message_id(message: &Message) -> MessageId {
+    let mut hasher = Sha256::new();
+    hasher.update(message.topic.as_str().as_bytes());
+    hasher.update([0]);
+    match AlertMessage::from_bytes(&message.data) {
+        Ok(alert) => hasher.update(alert.content_id()),
+        Err(_) => hasher.update(&message.data),
+    }
+    MessageId::from(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let alert = AlertMessage {
+            first_name: "Ana".into(),
+            country: "AR".into(),
+            type_alert: "missing-person".into(),
+            ..AlertMessage::default()
+        };
+
+        assert_eq!(AlertMessage::from_bytes(&alert.to_bytes()).unwrap(), alert);
+        assert!(AlertMessage::from_bytes(b"operations").is_err());
+    }
+
+    fn message(data: Vec<u8>, topic: &str) -> Message {
+        Message {
+            source: None,
+            data,
+            sequence_number: None,
+            topic: libp2p::gossipsub::IdentTopic::new(topic).hash(),
+        }
+    }
+
+    #[test]
+    fn test_content_id_ignores_encoding() {
+        let alert = AlertMessage {
+            first_name: "Ana".into(),
+            type_alert: "amber".into(),
+            ..AlertMessage::default()
+        };
+        let mut value = serde_json::to_value(&alert).unwrap();
+        value["first_name"] = "Ana".into();
+        let reencoded = serde_json::to_vec_pretty(&value).unwrap();
+
+        assert_eq!(
+            message_id(&message(alert.to_bytes(), "alerts/v1/global")),
+            message_id(&message(reencoded, "alerts/v1/global"))
+        );
+        assert_ne!(
+            message_id(&message(alert.to_bytes(), "alerts/v1/global")),
+            message_id(&message(alert.to_bytes(), "alerts/v1/type/amber"))
+        );
+
+        let other = AlertMessage {
+            first_name: "Eva".into(),
+            ..alert.clone()
+        };
+        assert_eq!(alert.content_id().len(), 64);
+        assert_ne!(alert.content_id(), other.content_id());
+    }
+
+    #[test]
+    fn test_content_id_ignores_publisher() {
+        let alert = AlertMessage {
+            first_name: "Ana".into(),
+            origin: "origin-a".into(),
+            seq: 1,
+            ..AlertMessage::default()
+        };
+        let republished = AlertMessage {
+            origin: "origin-b".into(),
+            seq: 7,
+            ..alert.clone()
+        };
+        assert_eq!(alert.content_id(), republished.content_id());
+        assert_eq!(
+            message_id(&message(alert.to_bytes(), "alerts/v1/global")),
+            message_id(&message(republished.to_bytes(), "alerts/v1/global"))
+        );
+
+        // Revocations stay bound to their origin
+        let revocation = AlertMessage {
+            revokes: Some(alert.content_id()),
+            ..alert.clone()
+        };
+        let forged = AlertMessage {
+            origin: "origin-b".into(),
+            ..revocation.clone()
+        };
+        assert_ne!(revocation.content_id(), forged.content_id());
+    }
+
+    #[test]
+    fn test_signature_binds_origin_and_seq() {
+        let keypair = Keypair::generate_ed25519();
+        let mut alert = AlertMessage {
+            first_name: "Ana".into(),
+            ..AlertMessage::default()
+        };
+        let content_id = alert.content_id();
+        alert.sign(&keypair, 3);
+
+        assert_eq!(alert.origin, keypair.public().to_peer_id().to_string());
+        assert_eq!(alert.seq, 3);
+        assert_eq!(alert.content_id(), content_id);
+        assert!(
+            AlertMessage::from_bytes(&alert.to_bytes())
+                .unwrap()
+                .verify_signature()
+        );
+
+        let tampered = [
+            AlertMessage {
+                first_name: "Eva".into(),
+                ..alert.clone()
+            },
+            AlertMessage {
+                seq: 4,
+                ..alert.clone()
+            },
+            AlertMessage {
+                origin: Keypair::generate_ed25519()
+                    .public()
+                    .to_peer_id()
+                    .to_string(),
+                ..alert.clone()
+            },
+            AlertMessage {
+                signature: String::new(),
+                ..alert.clone()
+            },
+        ];
+        for alert in tampered {
+            assert!(!alert.verify_signature(), "{alert:?}");
+        }
+    }
+
+    #[test]
+    fn test_attachments_keep_older_content_ids() {
+        let alert = AlertMessage {
+            first_name: "Ana".into(),
+            ..AlertMessage::default()
+        };
+        assert!(
+            !String::from_utf8(alert.to_bytes())
+                .unwrap()
+                .contains("attachments")
+        );
+
+        let older = br#"{"first_name":"Ana","last_name":"","description":"","yob":0,
+            "url_1":"","url_2":"","url_3":"","country":"","type_alert":"","name_alert":""}"#;
+        assert_eq!(AlertMessage::from_bytes(older).unwrap(), alert);
+    }
+
+    #[test]
+    fn test_legacy_urls_map_to_attachments() {
+        let photo = |url: &str| Attachment {
+            url: url.into(),
+            mime_type: "image/jpeg".into(),
+            ..Attachment::default()
+        };
+        let mut alert = AlertMessage {
+            url_1: "b".into(),
+            url_3: "e".into(),
+            attachments: vec![photo("a"), photo("b"), photo("c"), photo("d")],
+            ..AlertMessage::default()
+        };
+        alert.fill_legacy_urls();
+
+        let urls: Vec<&str> = alert.attachments.iter().map(|a| a.url.as_str()).collect();
+        assert_eq!(urls, ["a", "b", "c", "d", "e"]);
+        assert_eq!(alert.attachments[4].mime_type, "");
+        assert_eq!(
+            (
+                alert.url_1.as_str(),
+                alert.url_2.as_str(),
+                alert.url_3.as_str()
+            ),
+            ("a", "b", "c")
+        );
+
+        let mut older = AlertMessage {
+            url_2: "x".into(),
+            ..AlertMessage::default()
+        };
+        older.merge_legacy_urls();
+        assert_eq!(
+            older.attachments,
+            [Attachment {
+                url: "x".into(),
+                ..Attachment::default()
+            }]
+        );
+        assert_eq!((older.url_1.as_str(), older.url_2.as_str()), ("", "x"));
+    }
+}
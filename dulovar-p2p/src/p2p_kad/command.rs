@@ -0,0 +1,198 @@
+use std::num::{NonZeroUsize, ParseIntError};
+use std::time::Duration;
+
+use libp2p::swarm::DialError;
+use libp2p::{Multiaddr, PeerId};
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, SendTimeoutError, TryRecvError},
+};
+use tokio::sync::{broadcast, oneshot};
+
+use crate::db::DbError;
+use crate::db::alert_store::{AlertQuery, SearchMatch, StoredAlert};
+use crate::metrics::Metrics;
+use crate::p2p_kad::alert_message::AlertMessage;
+use crate::p2p_kad::introspection::{ConnectedPeer, NodeStatus};
+use crate::p2p_kad::revocation::RevokeError;
+
+/// Commands queued for the node before senders have to wait
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Requests sent by the gRPC services to the node event loop
+#[derive(Debug)]
+pub enum Command {
+    /// Stores the alert with its outbox entry, replies with its content id
+    /// once both are committed, then gossips it to the matching topics
+    Publish {
+        alert: Box<AlertMessage>,
+        reply: oneshot::Sender<Result<String, DbError>>,
+    },
+    /// Blocks the peer, closes its connections and persists the ban. The
+    /// reply tells whether the peer was not banned yet.
+    Ban {
+        peer: PeerId,
+        reply: oneshot::Sender<Result<bool, DbError>>,
+    },
+    /// Lifts a ban, replying whether the peer was banned. Peers of the
+    /// configured blocklist are left banned and the reply is false.
+    Unban {
+        peer: PeerId,
+        reply: oneshot::Sender<Result<bool, DbError>>,
+    },
+    /// Snapshot of the addresses, topics, routing table and database
+    Status {
+        reply: oneshot::Sender<Result<NodeStatus, DbError>>,
+    },
+    /// Connected peers with their ping and identify details
+    Peers {
+        reply: oneshot::Sender<Vec<(PeerId, ConnectedPeer)>>,
+    },
+    /// Starts dialing `address`, the reply tells whether the dial started
+    Dial {
+        address: Multiaddr,
+        reply: oneshot::Sender<Result<(), DialError>>,
+    },
+    /// Closes every connection to the peer, replying whether it was
+    /// connected
+    Disconnect {
+        peer: PeerId,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Reads stored alerts
+    Alerts {
+        query: AlertQuery,
+        reply: oneshot::Sender<Result<Vec<StoredAlert>, DbError>>,
+    },
+    /// Alerts similar to `text`, best matches first
+    Search {
+        text: String,
+        limit: usize,
+        reply: oneshot::Sender<Result<Vec<SearchMatch>, DbError>>,
+    },
+    /// Publishes a revocation of an alert this node published, replying
+    /// with the content id of the revocation
+    Revoke {
+        alert_id: String,
+        reply: oneshot::Sender<Result<String, RevokeError>>,
+    },
+    /// Receiver of the alerts stored from now on, local or from peers
+    Subscribe {
+        reply: oneshot::Sender<broadcast::Receiver<AlertMessage>>,
+    },
+}
+
+/// Parses a queue capacity, which must be at least 1
+pub fn parse_queue_capacity(value: &str) -> Result<usize, ParseIntError> {
+    value.trim().parse().map(NonZeroUsize::get)
+}
+
+/// Bounded queue of commands to the node, its depth is exported as the
+/// `dulovar_command_queue_depth` gauge
+pub fn channel(capacity: usize, metrics: Metrics) -> (CommandSender, CommandReceiver) {
+    let (sender, receiver) = mpsc::channel(capacity);
+    (
+        CommandSender {
+            inner: sender,
+            metrics: metrics.clone(),
+        },
+        CommandReceiver {
+            inner: receiver,
+            metrics,
+        },
+    )
+}
+
+#[derive(Clone)]
+pub struct CommandSender {
+    inner: mpsc::Sender<Command>,
+    metrics: Metrics,
+}
+
+impl CommandSender {
+    /// Queues `command`, waiting while the queue is full
+    pub async fn send(&self, command: Command) -> Result<(), SendError<Command>> {
+        self.inner.send(command).await?;
+        self.observe_depth();
+        Ok(())
+    }
+
+    /// Queues `command`, giving up when the queue stays full for `timeout`
+    pub async fn send_timeout(
+        &self,
+        command: Command,
+        timeout: Duration,
+    ) -> Result<(), SendTimeoutError<Command>> {
+        self.inner.send_timeout(command, timeout).await?;
+        self.observe_depth();
+        Ok(())
+    }
+
+    fn observe_depth(&self) {
+        self.metrics
+            .command_queue_depth(self.inner.max_capacity() - self.inner.capacity());
+    }
+}
+
+pub struct CommandReceiver {
+    inner: mpsc::Receiver<Command>,
+    metrics: Metrics,
+}
+
+impl CommandReceiver {
+    pub async fn recv(&mut self) -> Option<Command> {
+        let command = self.inner.recv().await;
+        self.metrics.command_queue_depth(self.inner.len());
+        command
+    }
+
+    pub fn try_recv(&mut self) -> Result<Command, TryRecvError> {
+        let command = self.inner.try_recv();
+        self.metrics.command_queue_depth(self.inner.len());
+        command
+    }
+
+    /// Refuses new commands, the queued ones can still be received
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publish() -> Command {
+        let (reply, _) = oneshot::channel();
+        Command::Publish {
+            alert: Box::default(),
+            reply,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_times_out() {
+        let (sender, mut receiver) = channel(1, Metrics::default());
+
+        sender.send(publish()).await.unwrap();
+        let result = sender
+            .send_timeout(publish(), Duration::from_millis(10))
+            .await;
+        assert!(matches!(result, Err(SendTimeoutError::Timeout(_))));
+
+        // Room is made as soon as the node receives
+        assert!(receiver.recv().await.is_some());
+        sender
+            .send_timeout(publish(), Duration::from_millis(10))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_queue_capacity() {
+        assert_eq!(parse_queue_capacity(" 16 ").unwrap(), 16);
+        assert!(parse_queue_capacity("0").is_err());
+        assert!(parse_queue_capacity("-1").is_err());
+        assert!(parse_queue_capacity("").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use libp2p::{Multiaddr, PeerId, multiaddr::Protocol};
+use tracing::warn;
+
+/// Settings of the P2P node, read from `DULOVAR_*` environment variables
+#[derive(Debug, Clone)]
+pub struct P2pConfig {
+    /// Port the node listens on, over TCP and UDP for QUIC
+    /// (`DULOVAR_LISTEN_PORT`)
+    pub listen_port: u16,
+    /// Listen and dial over TCP (`DULOVAR_TCP`)
+    pub tcp: bool,
+    /// Listen and dial over QUIC, preferred to TCP when a peer advertises
+    /// both (`DULOVAR_QUIC`)
+    pub quic: bool,
+    /// Port of the `/tcp/PORT/ws` listener for browsers and networks that
+    /// only let websockets through, disabled when unset (`DULOVAR_WS_PORT`)
+    pub websocket_port: Option<u16>,
+    /// File caching the known peers between restarts (`DULOVAR_PEER_STORE`)
+    pub peer_store_path: PathBuf,
+    /// SQLite database of the node (`DULOVAR_DATABASE`)
+    pub database_path: PathBuf,
+    /// Keypair of the node, created on the first run (`DULOVAR_IDENTITY`)
+    pub identity_path: PathBuf,
+    /// Serve circuit relay v2 reservations, meant for master nodes
+    /// (`DULOVAR_RELAY_SERVER`)
+    pub relay_server: bool,
+    /// Relays to reserve a slot on, in addition to the master nodes of the
+    /// registry. Each address must end with the relay peer id
+    /// (`DULOVAR_RELAYS`, comma separated)
+    pub relays: Vec<Multiaddr>,
+    /// ISO 3166-1 alpha-2 codes of the countries whose alerts this node
+    /// follows (`DULOVAR_JURISDICTIONS`, comma separated)
+    pub jurisdictions: Vec<String>,
+    /// Alert types this node follows (`DULOVAR_ALERT_TYPES`, comma separated),
+    /// ignored when `jurisdictions` is set. The node follows every alert when
+    /// neither is set.
+    pub alert_types: Vec<String>,
+    /// Established connections across all peers (`DULOVAR_MAX_CONNECTIONS`)
+    pub max_connections: u32,
+    /// Established connections to a single peer
+    /// (`DULOVAR_MAX_CONNECTIONS_PER_PEER`)
+    pub max_connections_per_peer: u32,
+    /// Only these peers may connect when set (`DULOVAR_ALLOWED_PEERS`, comma
+    /// separated)
+    pub allowed_peers: Vec<PeerId>,
+    /// Peers refused in addition to the ones banned at runtime
+    /// (`DULOVAR_BLOCKED_PEERS`, comma separated)
+    pub blocked_peers: Vec<PeerId>,
+}
+
+impl Default for P2pConfig {
+    fn default() -> Self {
+        Self {
+            listen_port: 4001,
+            tcp: true,
+            quic: true,
+            websocket_port: None,
+            peer_store_path: PathBuf::from("data/peers.json"),
+            database_path: PathBuf::from("sqlite/database.db"),
+            identity_path: PathBuf::from("data/identity.key"),
+            relay_server: false,
+            relays: Vec::new(),
+            jurisdictions: Vec::new(),
+            alert_types: Vec::new(),
+            max_connections: 200,
+            max_connections_per_peer: 4,
+            allowed_peers: Vec::new(),
+            blocked_peers: Vec::new(),
+        }
+    }
+}
+
+impl P2pConfig {
+    /// Reads the configuration from the process environment
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the configuration through `lookup`, keeping the default of any
+    /// missing or invalid variable
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut config = Self::default();
+
+        if let Some(port) = lookup("DULOVAR_LISTEN_PORT").and_then(|v| v.trim().parse().ok()) {
+            config.listen_port = port;
+        }
+        if let Some(enabled) = lookup("DULOVAR_TCP").and_then(|v| parse_bool(&v)) {
+            config.tcp = enabled;
+        }
+        if let Some(enabled) = lookup("DULOVAR_QUIC").and_then(|v| parse_bool(&v)) {
+            config.quic = enabled;
+        }
+        if let Some(port) = lookup("DULOVAR_WS_PORT").and_then(|v| v.trim().parse().ok()) {
+            config.websocket_port = Some(port);
+        }
+        if let Some(path) = lookup("DULOVAR_PEER_STORE") {
+            config.peer_store_path = PathBuf::from(path);
+        }
+        if let Some(path) = lookup("DULOVAR_DATABASE") {
+            config.database_path = PathBuf::from(path);
+        }
+        if let Some(path) = lookup("DULOVAR_IDENTITY") {
+            config.identity_path = PathBuf::from(path);
+        }
+        if let Some(enabled) = lookup("DULOVAR_RELAY_SERVER").and_then(|v| parse_bool(&v)) {
+            config.relay_server = enabled;
+        }
+        if let Some(relays) = lookup("DULOVAR_RELAYS") {
+            config.relays = parse_multiaddrs(&relays);
+        }
+        if let Some(jurisdictions) = lookup("DULOVAR_JURISDICTIONS") {
+            config.jurisdictions = parse_list(&jurisdictions);
+        }
+        if let Some(alert_types) = lookup("DULOVAR_ALERT_TYPES") {
+            config.alert_types = parse_list(&alert_types);
+        }
+        if let Some(max) = lookup("DULOVAR_MAX_CONNECTIONS").and_then(|v| v.trim().parse().ok()) {
+            config.max_connections = max;
+        }
+        if let Some(max) =
+            lookup("DULOVAR_MAX_CONNECTIONS_PER_PEER").and_then(|v| v.trim().parse().ok())
+        {
+            config.max_connections_per_peer = max;
+        }
+        if let Some(peers) = lookup("DULOVAR_ALLOWED_PEERS") {
+            config.allowed_peers = parse_peer_ids(&peers);
+        }
+        if let Some(peers) = lookup("DULOVAR_BLOCKED_PEERS") {
+            config.blocked_peers = parse_peer_ids(&peers);
+        }
+
+        config
+    }
+
+    /// Addresses to listen on for the enabled transports
+    pub fn listen_addresses(&self) -> Vec<Multiaddr> {
+        let any = Multiaddr::empty().with(Protocol::Ip4([0, 0, 0, 0].into()));
+        let mut addresses = Vec::new();
+        if self.quic {
+            addresses.push(
+                any.clone()
+                    .with(Protocol::Udp(self.listen_port))
+                    .with(Protocol::QuicV1),
+            );
+        }
+        if self.tcp {
+            addresses.push(any.clone().with(Protocol::Tcp(self.listen_port)));
+        }
+        if let Some(port) = self.websocket_port {
+            addresses.push(any.with(Protocol::Tcp(port)).with(Protocol::Ws("/".into())));
+        }
+        addresses
+    }
+
+    /// Rank of `addr` when choosing which address of a peer to dial, lower is
+    /// preferred. `None` when no enabled transport can dial it.
+    pub fn dial_rank(&self, addr: &Multiaddr) -> Option<u8> {
+        if addr.iter().any(|p| p == Protocol::QuicV1) {
+            self.quic.then_some(0)
+        } else if addr
+            .iter()
+            .any(|p| matches!(p, Protocol::Ws(_) | Protocol::Wss(_)))
+        {
+            Some(2)
+        } else {
+            self.tcp.then_some(1)
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn parse_multiaddrs(value: &str) -> Vec<Multiaddr> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .filter_map(|addr| match Multiaddr::from_str(addr) {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                warn!(addr, error = %e, "ignoring invalid multiaddr");
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_peer_ids(value: &str) -> Vec<PeerId> {
+    parse_list(value)
+        .into_iter()
+        .filter_map(|id| match PeerId::from_str(&id) {
+            Ok(peer) => Some(peer),
+            Err(e) => {
+                warn!(peer_id = id, error = %e, "ignoring invalid peer id");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config_from(vars: &[(&str, &str)]) -> P2pConfig {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        P2pConfig::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = config_from(&[]);
+
+        assert_eq!(config.listen_port, 4001);
+        assert!(config.tcp);
+        assert!(config.quic);
+        assert_eq!(config.websocket_port, None);
+        assert_eq!(config.peer_store_path, PathBuf::from("data/peers.json"));
+        assert_eq!(config.database_path, PathBuf::from("sqlite/database.db"));
+        assert_eq!(config.identity_path, PathBuf::from("data/identity.key"));
+        assert!(!config.relay_server);
+        assert!(config.relays.is_empty());
+        assert_eq!(config.max_connections, 200);
+        assert_eq!(config.max_connections_per_peer, 4);
+        assert!(config.allowed_peers.is_empty());
+        assert!(config.blocked_peers.is_empty());
+    }
+
+    #[test]
+    fn test_from_lookup() {
+        let config = config_from(&[
+            ("DULOVAR_LISTEN_PORT", "4101"),
+            ("DULOVAR_QUIC", "off"),
+            ("DULOVAR_WS_PORT", "443"),
+            ("DULOVAR_JURISDICTIONS", "AR, UY,"),
+            ("DULOVAR_ALERT_TYPES", "amber"),
+            ("DULOVAR_MAX_CONNECTIONS", "50"),
+            (
+                "DULOVAR_BLOCKED_PEERS",
+                "12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA, nope",
+            ),
+            ("DULOVAR_PEER_STORE", "/var/lib/dulovar/peers.json"),
+            ("DULOVAR_IDENTITY", "/var/lib/dulovar/identity.key"),
+            ("DULOVAR_RELAY_SERVER", "true"),
+            (
+                "DULOVAR_RELAYS",
+                "/ip4/203.0.113.7/tcp/4001/p2p/12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA, not-an-addr,",
+            ),
+        ]);
+
+        assert_eq!(config.listen_port, 4101);
+        assert!(config.tcp);
+        assert!(!config.quic);
+        assert_eq!(config.websocket_port, Some(443));
+        assert_eq!(
+            config.peer_store_path,
+            PathBuf::from("/var/lib/dulovar/peers.json")
+        );
+        assert_eq!(
+            config.identity_path,
+            PathBuf::from("/var/lib/dulovar/identity.key")
+        );
+        assert!(config.relay_server);
+        assert_eq!(config.relays.len(), 1);
+        assert_eq!(config.jurisdictions, ["AR", "UY"]);
+        assert_eq!(config.alert_types, ["amber"]);
+        assert_eq!(config.max_connections, 50);
+        assert_eq!(config.blocked_peers.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_values_keep_defaults() {
+        let config = config_from(&[
+            ("DULOVAR_LISTEN_PORT", "99999"),
+            ("DULOVAR_RELAY_SERVER", "maybe"),
+        ]);
+
+        assert_eq!(config.listen_port, 4001);
+        assert!(!config.relay_server);
+    }
+
+    #[test]
+    fn test_listen_addresses() {
+        let config = P2pConfig::default();
+        let addresses: Vec<String> = config
+            .listen_addresses()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            addresses,
+            ["/ip4/0.0.0.0/udp/4001/quic-v1", "/ip4/0.0.0.0/tcp/4001"]
+        );
+
+        let tcp_only = P2pConfig {
+            quic: false,
+            ..P2pConfig::default()
+        };
+        assert_eq!(tcp_only.listen_addresses().len(), 1);
+
+        let websocket = P2pConfig {
+            websocket_port: Some(8443),
+            ..P2pConfig::default()
+        };
+        assert_eq!(
+            websocket.listen_addresses().last().unwrap().to_string(),
+            "/ip4/0.0.0.0/tcp/8443/ws"
+        );
+    }
+
+    #[test]
+    fn test_dial_rank_prefers_quic() {
+        let quic = Multiaddr::from_str("/ip4/203.0.113.7/udp/4001/quic-v1").unwrap();
+        let tcp = Multiaddr::from_str("/ip4/203.0.113.7/tcp/4001").unwrap();
+        let ws = Multiaddr::from_str("/ip4/203.0.113.7/tcp/443/ws").unwrap();
+
+        let config = P2pConfig::default();
+        assert!(config.dial_rank(&quic) < config.dial_rank(&tcp));
+        assert!(config.dial_rank(&tcp) < config.dial_rank(&ws));
+
+        let no_quic = P2pConfig {
+            quic: false,
+            ..P2pConfig::default()
+        };
+        assert_eq!(no_quic.dial_rank(&quic), None);
+        assert!(no_quic.dial_rank(&tcp).is_some());
+    }
+}
@@ -0,0 +1,148 @@
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+use libp2p::{Multiaddr, PeerId};
+use tokio::io::{self, AsyncBufReadExt, BufReader, Lines, Stdin};
+
+use crate::p2p_kad::alert_message::AlertMessage;
+use crate::p2p_kad::validation::validate_alert;
+
+/// Alerts listed by `alerts recent`
+pub const RECENT_ALERTS: usize = 10;
+
+pub const HELP: &str = "\
+commands:
+  peers              connected peers with ping RTT and agent version
+  topics             subscribed topics and their mesh peers
+  dial <multiaddr>   dial an address
+  publish <json>     store and gossip an alert given as JSON
+  alerts recent      last stored alerts
+  ban <peer id>      ban a peer, persisted across restarts
+  dht get <key>      look up a record in the DHT
+  help               this help";
+
+/// Operator command typed on the console
+#[derive(Debug, PartialEq)]
+pub enum ConsoleCommand {
+    Help,
+    Peers,
+    Topics,
+    Dial(Multiaddr),
+    Publish(Box<AlertMessage>),
+    RecentAlerts,
+    Ban(PeerId),
+    DhtGet(String),
+}
+
+impl FromStr for ConsoleCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        match (command, args) {
+            ("help", _) => Ok(Self::Help),
+            ("peers", "") => Ok(Self::Peers),
+            ("topics", "") => Ok(Self::Topics),
+            ("dial", address) if !address.is_empty() => address
+                .parse()
+                .map(Self::Dial)
+                .map_err(|e| format!("invalid multiaddr: {e}")),
+            ("publish", json) if !json.is_empty() => {
+                let alert = AlertMessage::from_bytes(json.as_bytes())
+                    .map_err(|e| format!("invalid alert: {e}"))?;
+                validate_alert(&alert).map_err(|e| format!("invalid alert: {e}"))?;
+                Ok(Self::Publish(Box::new(alert)))
+            }
+            ("alerts", "recent") => Ok(Self::RecentAlerts),
+            ("ban", peer) if !peer.is_empty() => peer
+                .parse()
+                .map(Self::Ban)
+                .map_err(|e| format!("invalid peer id: {e}")),
+            ("dht", args) => match args.split_once(char::is_whitespace) {
+                Some(("get", key)) if !key.trim().is_empty() => {
+                    Ok(Self::DhtGet(key.trim().to_string()))
+                }
+                _ => Err("usage: dht get <key>".into()),
+            },
+            _ => Err(format!("unknown command `{line}`, type `help`")),
+        }
+    }
+}
+
+/// Lines typed by the operator on stdin
+pub struct Console {
+    lines: Lines<BufReader<Stdin>>,
+}
+
+impl Console {
+    /// Console on stdin, or `None` when stdin is not a terminal, like when
+    /// the node runs as a service
+    pub fn stdin() -> Option<Self> {
+        std::io::stdin().is_terminal().then(|| Self {
+            lines: BufReader::new(io::stdin()).lines(),
+        })
+    }
+
+    /// Next non empty line, `None` once stdin is closed
+    pub async fn next_line(&mut self) -> Option<String> {
+        loop {
+            match self.lines.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => return Some(line),
+                Ok(None) | Err(_) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!("peers".parse(), Ok(ConsoleCommand::Peers));
+        assert_eq!(" topics ".parse(), Ok(ConsoleCommand::Topics));
+        assert_eq!("alerts recent".parse(), Ok(ConsoleCommand::RecentAlerts));
+        assert_eq!(
+            "dial /ip4/127.0.0.1/tcp/4001".parse(),
+            Ok(ConsoleCommand::Dial(
+                "/ip4/127.0.0.1/tcp/4001".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            "dht get  some key ".parse(),
+            Ok(ConsoleCommand::DhtGet("some key".into()))
+        );
+
+        let peer = PeerId::random();
+        assert_eq!(format!("ban {peer}").parse(), Ok(ConsoleCommand::Ban(peer)));
+
+        let json = r#"publish {"first_name":"","last_name":"","description":"",
+            "yob":0,"url_1":"","url_2":"","url_3":"","country":"AR",
+            "type_alert":"amber","name_alert":"test"}"#;
+        match json.parse() {
+            Ok(ConsoleCommand::Publish(alert)) => assert_eq!(alert.country, "AR"),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for line in [
+            "dial",
+            "dial nope",
+            "ban nope",
+            "publish {",
+            r#"publish {"first_name":"","last_name":"","description":"","yob":0,"url_1":"",
+                "url_2":"","url_3":"","country":"AR","type_alert":" ","name_alert":"test"}"#,
+            "dht put k",
+            "alerts",
+            "nope",
+        ] {
+            assert!(line.parse::<ConsoleCommand>().is_err(), "{line}");
+        }
+    }
+}
@@ -1,36 +1,430 @@
+use crate::db::DbError;
+use crate::db::alert_store::{AlertQuery, OutboxEntry};
+use crate::p2p_kad::alert_message::AlertMessage;
+use crate::p2p_kad::command::{Command, CommandReceiver};
+use crate::p2p_kad::console::{Console, ConsoleCommand, HELP, RECENT_ALERTS};
 use crate::p2p_kad::events::handle_swarm_event;
+use crate::p2p_kad::introspection::node_status;
 use crate::p2p_kad::my_behaviour::MyBehaviour;
+use crate::p2p_kad::node_state::NodeState;
+use crate::p2p_kad::revocation::{RevokeError, revocation};
+use crate::p2p_kad::topics::topics_for;
 use futures::StreamExt;
-use libp2p::gossipsub;
+use libp2p::gossipsub::{self, PublishError};
+use libp2p::kad;
 use std::error::Error;
+use std::time::Duration;
 use tokio::select;
-use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::time::{interval, timeout};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
+/// How often the gauges read from the swarm are refreshed
+const METRICS_REFRESH: Duration = Duration::from_secs(15);
+
+/// How often the outbox is retried while alerts wait for peers
+const OUTBOX_RETRY: Duration = Duration::from_secs(5);
+
+/// How often the peer store is saved when peers changed
+const PEER_STORE_FLUSH: Duration = Duration::from_secs(30);
+
+/// Outbox entries published per attempt
+const OUTBOX_BATCH: usize = 100;
+
+/// How long the swarm keeps running after leaving the topics, so the last
+/// messages reach the peers
+const SHUTDOWN_LINGER: Duration = Duration::from_secs(1);
+
+/// Runs the node until `shutdown` is cancelled or every sender is dropped,
+/// then shuts it down cleanly. Lines of the `console` are run as operator
+/// commands.
 pub async fn event_loop(
-    receiver: &mut mpsc::UnboundedReceiver<String>,
+    receiver: &mut CommandReceiver,
     swarm: &mut libp2p::Swarm<MyBehaviour>,
-    gossipsub_topic: gossipsub::IdentTopic,
+    state: &mut NodeState,
+    mut console: Option<Console>,
+    shutdown: &CancellationToken,
 ) -> Result<(), Box<dyn Error>> {
+    let mut metrics_refresh = interval(METRICS_REFRESH);
+    // The first tick publishes what a previous run left in the outbox
+    let mut outbox_retry = interval(OUTBOX_RETRY);
+    let mut peer_store_flush = interval(PEER_STORE_FLUSH);
     loop {
         select! {
-            // Receiving message from channel
-            message = receiver.recv() => {
-                if let Some(msg) = message {
-                  // Sending message to peers
-                    if let Err(e) = swarm
-                        .behaviour_mut()
-                        .gossipsub
-                        .publish(gossipsub_topic.clone(), msg.as_bytes())
-                    {
-                        println!("Publish error: {e:?}");
-                    }
+            _ = shutdown.cancelled() => break,
+
+            // Receiving command from channel
+            command = receiver.recv() => match command {
+                Some(command) => handle_command(command, swarm, state),
+                None => break,
+            },
+
+            _ = outbox_retry.tick() => publish_outbox(swarm, state),
+
+            // Written in batches, not on every connection
+            _ = peer_store_flush.tick() => {
+                if let Err(e) = state.peer_store.flush() {
+                    warn!(error = %e, "failed to save peer store");
                 }
             },
 
+            line = next_console_line(&mut console) => match line {
+                Some(line) => handle_console_line(&line, swarm, state),
+                None => console = None,
+            },
+
+            // Gauges that no event tells about, like the mesh size
+            _ = metrics_refresh.tick() => {
+                state.metrics.observe_swarm(swarm);
+            },
+
             // Swarm network event
             event = swarm.select_next_some() => {
-                handle_swarm_event(event).await;
+                handle_swarm_event(event, swarm, state).await;
             },
         }
     }
+
+    shut_down(receiver, swarm, state).await;
+    Ok(())
+}
+
+/// Handles the commands still queued, leaves the gossipsub topics and
+/// saves the peer store. The database needs no flush, every statement is
+/// committed as it runs and the connections close with `state`. Alerts
+/// that found no peer stay in the outbox for the next run.
+async fn shut_down(
+    receiver: &mut CommandReceiver,
+    swarm: &mut libp2p::Swarm<MyBehaviour>,
+    state: &mut NodeState,
+) {
+    receiver.close();
+    let mut drained = 0;
+    while let Ok(command) = receiver.try_recv() {
+        handle_command(command, swarm, state);
+        drained += 1;
+    }
+    info!(drained, "shutting down, queued commands handled");
+
+    let topics: Vec<_> = swarm.behaviour().gossipsub.topics().cloned().collect();
+    for topic in topics {
+        // Topics are identity hashed, the hash is the topic name
+        let topic = gossipsub::IdentTopic::new(topic.into_string());
+        if let Err(e) = swarm.behaviour_mut().gossipsub.unsubscribe(&topic) {
+            warn!(%topic, error = ?e, "failed to unsubscribe");
+        }
+    }
+    let _ = timeout(SHUTDOWN_LINGER, async {
+        loop {
+            let event = swarm.select_next_some().await;
+            handle_swarm_event(event, swarm, state).await;
+        }
+    })
+    .await;
+
+    if let Err(e) = state.peer_store.flush() {
+        warn!(error = %e, "failed to save peer store");
+    }
+}
+
+/// Next line typed on the console, pending forever without a console
+async fn next_console_line(console: &mut Option<Console>) -> Option<String> {
+    match console {
+        Some(console) => console.next_line().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Runs an operator command and prints its outcome. Commands that exist for
+/// the gRPC services go through [`handle_command`], which answers at once.
+fn handle_console_line(line: &str, swarm: &mut libp2p::Swarm<MyBehaviour>, state: &mut NodeState) {
+    let command = match line.parse::<ConsoleCommand>() {
+        Ok(command) => command,
+        Err(e) => {
+            println!("{e}");
+            return;
+        }
+    };
+
+    match command {
+        ConsoleCommand::Help => println!("{HELP}"),
+        ConsoleCommand::Peers => {
+            let peers = state.peers.list();
+            println!("{} connected peers", peers.len());
+            for (peer, connected) in peers {
+                let rtt = connected
+                    .rtt
+                    .map(|rtt| format!("{}ms", rtt.as_millis()))
+                    .unwrap_or_else(|| "-".into());
+                let agent = connected.agent_version.as_deref().unwrap_or("-");
+                println!("  {peer}  rtt {rtt}  agent {agent}");
+                for address in &connected.addresses {
+                    println!("    {address}");
+                }
+            }
+        }
+        ConsoleCommand::Topics => {
+            let gossipsub = &swarm.behaviour().gossipsub;
+            for topic in gossipsub.topics() {
+                let mesh: Vec<String> = gossipsub
+                    .mesh_peers(topic)
+                    .map(ToString::to_string)
+                    .collect();
+                println!("  {topic}  {} mesh peers {}", mesh.len(), mesh.join(" "));
+            }
+        }
+        ConsoleCommand::Dial(address) => {
+            let (reply, mut response) = oneshot::channel();
+            handle_command(Command::Dial { address, reply }, swarm, state);
+            match response.try_recv() {
+                Ok(Ok(())) => println!("dialing"),
+                Ok(Err(e)) => println!("cannot dial: {e}"),
+                Err(_) => {}
+            }
+        }
+        ConsoleCommand::Publish(alert) => {
+            let (reply, mut response) = oneshot::channel();
+            handle_command(Command::Publish { alert, reply }, swarm, state);
+            match response.try_recv() {
+                Ok(Ok(alert_id)) => println!("stored alert {alert_id}"),
+                Ok(Err(e)) => println!("failed to store alert: {e}"),
+                Err(_) => {}
+            }
+        }
+        ConsoleCommand::RecentAlerts => match state.alerts.recent(RECENT_ALERTS) {
+            Ok(alerts) => {
+                for alert in alerts {
+                    println!(
+                        "  {}  {} #{}  {} {}",
+                        alert.content_id(),
+                        alert.origin,
+                        alert.seq,
+                        alert.type_alert,
+                        alert.country
+                    );
+                }
+            }
+            Err(e) => println!("failed to read alerts: {e}"),
+        },
+        ConsoleCommand::Ban(peer) => {
+            let (reply, mut response) = oneshot::channel();
+            handle_command(Command::Ban { peer, reply }, swarm, state);
+            match response.try_recv() {
+                Ok(Ok(true)) => println!("banned {peer}"),
+                Ok(Ok(false)) => println!("{peer} already banned"),
+                Ok(Err(e)) => println!("banned until restart, failed to persist: {e}"),
+                Err(_) => {}
+            }
+        }
+        ConsoleCommand::DhtGet(key) => {
+            let query = swarm
+                .behaviour_mut()
+                .kad
+                .get_record(kad::RecordKey::new(&key));
+            println!("query {query:?} started, the result is logged");
+        }
+    }
+}
+
+fn handle_command(command: Command, swarm: &mut libp2p::Swarm<MyBehaviour>, state: &mut NodeState) {
+    match command {
+        Command::Publish { alert, reply } => store_alert(*alert, reply, swarm, state),
+        Command::Ban { peer, reply } => {
+            let result = state.bans.ban(&peer);
+            // Blocked even if persisting failed, the ban then lasts until restart
+            swarm.behaviour_mut().blocklist.block_peer(peer);
+            info!(peer_id = %peer, "banned peer");
+            let _ = reply.send(result);
+        }
+        Command::Unban { peer, reply } if state.blocked_peers.contains(&peer) => {
+            warn!(peer_id = %peer, "peer is blocked by the configuration, not unbanned");
+            let _ = reply.send(Ok(false));
+        }
+        Command::Unban { peer, reply } => {
+            let result = state.bans.unban(&peer);
+            if result.is_ok() {
+                swarm.behaviour_mut().blocklist.unblock_peer(peer);
+                info!(peer_id = %peer, "unbanned peer");
+            }
+            let _ = reply.send(result);
+        }
+        Command::Status { reply } => {
+            let _ = reply.send(node_status(swarm, state));
+        }
+        Command::Peers { reply } => {
+            let _ = reply.send(state.peers.list());
+        }
+        Command::Dial { address, reply } => {
+            info!(%address, "dialing on operator request");
+            let _ = reply.send(swarm.dial(address));
+        }
+        Command::Disconnect { peer, reply } => {
+            info!(peer_id = %peer, "disconnecting on operator request");
+            let _ = reply.send(swarm.disconnect_peer_id(peer).is_ok());
+        }
+        Command::Alerts { query, reply } => {
+            let _ = reply.send(state.alerts.query(&query));
+        }
+        Command::Search { text, limit, reply } => {
+            let _ = reply.send(state.alerts.search(&text, limit));
+        }
+        Command::Revoke { alert_id, reply } => revoke_alert(alert_id, reply, swarm, state),
+        Command::Subscribe { reply } => {
+            let _ = reply.send(state.feed.subscribe());
+        }
+    }
+}
+
+/// Stores a local alert and its outbox entry, then tries to gossip it. An
+/// alert with the same content as a known one is not published again.
+fn store_alert(
+    mut alert: AlertMessage,
+    reply: oneshot::Sender<Result<String, DbError>>,
+    swarm: &mut libp2p::Swarm<MyBehaviour>,
+    state: &mut NodeState,
+) {
+    let result = state.alerts.insert_local(&mut alert, &state.keypair);
+    match &result {
+        Ok(true) => state.metrics.alert_stored(),
+        Ok(false) => info!(alert_id = %alert.content_id(), "alert already known"),
+        Err(e) => error!(error = %e, "failed to store alert"),
+    }
+    let stored = matches!(result, Ok(true));
+    let _ = reply.send(result.map(|_| alert.content_id()));
+    if stored {
+        let _ = state.feed.send(alert);
+        publish_outbox(swarm, state);
+    }
+}
+
+/// Stores and gossips the revocation of an alert published by this node
+fn revoke_alert(
+    alert_id: String,
+    reply: oneshot::Sender<Result<String, RevokeError>>,
+    swarm: &mut libp2p::Swarm<MyBehaviour>,
+    state: &mut NodeState,
+) {
+    let origin = state.keypair.public().to_peer_id().to_string();
+    let result = state
+        .alerts
+        .query(&AlertQuery::Get(alert_id))
+        .map_err(RevokeError::from)
+        .and_then(|found| found.into_iter().next().ok_or(RevokeError::NotFound))
+        .and_then(|target| revocation(&target, &origin))
+        .and_then(|mut alert| {
+            state.alerts.insert_local(&mut alert, &state.keypair)?;
+            Ok(alert)
+        });
+    match result {
+        Ok(alert) => {
+            state.metrics.alert_stored();
+            info!(alert_id = %alert.revokes.as_deref().unwrap_or_default(), "revoked alert");
+            let _ = reply.send(Ok(alert.content_id()));
+            let _ = state.feed.send(alert);
+            publish_outbox(swarm, state);
+        }
+        Err(e) => {
+            let _ = reply.send(Err(e));
+        }
+    }
+}
+
+/// Gossips the alerts of the outbox to their topics. Entries are removed
+/// once published or when they can never be, and are otherwise retried on
+/// the next tick.
+fn publish_outbox(swarm: &mut libp2p::Swarm<MyBehaviour>, state: &mut NodeState) {
+    let entries = match state.alerts.outbox(OUTBOX_BATCH) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!(error = %e, "failed to read outbox");
+            return;
+        }
+    };
+
+    for entry in entries {
+        let outcome = match AlertMessage::from_bytes(&entry.payload) {
+            Ok(alert) => publish_alert(&alert, &entry, swarm),
+            Err(e) => {
+                error!(alert_id = %entry.alert_id, error = %e, "dropping unreadable outbox entry");
+                Outcome::Dropped
+            }
+        };
+        let result = match outcome {
+            Outcome::Published => {
+                state.metrics.alert_published();
+                state.alerts.remove_from_outbox(entry.id)
+            }
+            Outcome::Retry => state.alerts.outbox_attempted(entry.id),
+            Outcome::Dropped => {
+                state.metrics.alert_publish_failed();
+                state.alerts.remove_from_outbox(entry.id)
+            }
+        };
+        if let Err(e) = result {
+            error!(alert_id = %entry.alert_id, error = %e, "failed to update outbox");
+        }
+    }
+
+    match state.alerts.outbox_len() {
+        Ok(pending) => state.metrics.outbox_pending(pending),
+        Err(e) => error!(error = %e, "failed to count outbox"),
+    }
+}
+
+/// What became of an outbox entry
+enum Outcome {
+    Published,
+    /// Kept for the next tick
+    Retry,
+    /// Can never be published
+    Dropped,
+}
+
+/// Publishes the alert on each matching topic. Reaching one topic is enough:
+/// no connected peer follows the others, and their subscribers get the alert
+/// from this node through the alert sync when they connect.
+fn publish_alert(
+    alert: &AlertMessage,
+    entry: &OutboxEntry,
+    swarm: &mut libp2p::Swarm<MyBehaviour>,
+) -> Outcome {
+    let mut published = false;
+    for topic in topics_for(alert) {
+        match swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic.clone(), entry.payload.clone())
+        {
+            // Already sent by an earlier attempt
+            Ok(_) | Err(PublishError::Duplicate) => {
+                debug!(alert_id = %entry.alert_id, seq = alert.seq, %topic, "published alert");
+                published = true;
+            }
+            Err(PublishError::InsufficientPeers) => {
+                debug!(alert_id = %entry.alert_id, %topic, "no peer to publish alert to");
+            }
+            // The payload is the same on every topic
+            Err(PublishError::MessageTooLarge) => {
+                error!(
+                    alert_id = %entry.alert_id,
+                    size = entry.payload.len(),
+                    "dropping alert too large to publish"
+                );
+                return Outcome::Dropped;
+            }
+            Err(e) => {
+                warn!(alert_id = %entry.alert_id, %topic, error = ?e, "failed to publish alert");
+            }
+        }
+    }
+    if published {
+        return Outcome::Published;
+    }
+    debug!(
+        alert_id = %entry.alert_id,
+        attempts = entry.attempts,
+        "alert not published, retrying later"
+    );
+    Outcome::Retry
 }
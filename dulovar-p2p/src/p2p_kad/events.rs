@@ -1,25 +1,221 @@
-use crate::p2p_kad::my_behaviour::MyBehaviourEvent;
-use libp2p::{gossipsub, ping, swarm::SwarmEvent};
+use crate::p2p_kad::alert_message::AlertMessage;
+use crate::p2p_kad::my_behaviour::{MyBehaviour, MyBehaviourEvent};
+use crate::p2p_kad::node_state::NodeState;
+use crate::p2p_kad::p2p_kad_utils::with_peer_id;
+use crate::p2p_kad::swarm::KAD_PROTOCOL;
+use crate::p2p_kad::sync::{SYNC_BATCH, SyncRequest, SyncResponse, next_request, respond};
+use crate::p2p_kad::validation::{
+    ValidationError, validate_message, validate_revocation, validate_synced,
+};
+use libp2p::{
+    Multiaddr, PeerId, Swarm, autonat, dcutr, gossipsub, identify, kad, ping, relay,
+    request_response, swarm::SwarmEvent,
+};
+use tracing::{debug, error, info, warn};
 
-pub async fn handle_swarm_event(event: SwarmEvent<MyBehaviourEvent>) {
+pub async fn handle_swarm_event(
+    event: SwarmEvent<MyBehaviourEvent>,
+    swarm: &mut Swarm<MyBehaviour>,
+    state: &mut NodeState,
+) {
+    state.metrics.record(&event);
     match event {
         SwarmEvent::NewListenAddr { address, .. } => {
-            println!("Listening on {address:?}");
+            info!(%address, "listening");
+        }
+        SwarmEvent::ConnectionEstablished {
+            peer_id,
+            endpoint,
+            num_established,
+            ..
+        } => {
+            if endpoint.is_dialer() {
+                state
+                    .peer_store
+                    .record_connection(&peer_id, [endpoint.get_remote_address()]);
+            }
+            state
+                .peers
+                .connected(peer_id, endpoint.get_remote_address().clone());
+            // Catch up on the alerts missed while disconnected
+            if num_established.get() == 1 {
+                match state.alerts.watermarks() {
+                    Ok(watermarks) => {
+                        swarm
+                            .behaviour_mut()
+                            .sync
+                            .send_request(&peer_id, SyncRequest { watermarks });
+                    }
+                    Err(e) => error!(error = %e, "failed to read sync watermarks"),
+                }
+            }
+            state.metrics.observe_swarm(swarm);
+        }
+        SwarmEvent::ConnectionClosed {
+            peer_id, endpoint, ..
+        } => {
+            state
+                .peers
+                .disconnected(&peer_id, endpoint.get_remote_address());
+            state.metrics.observe_swarm(swarm);
+        }
+        SwarmEvent::ExternalAddrConfirmed { address } => {
+            info!(%address, "external address confirmed");
+            on_external_addr_confirmed(address, *swarm.local_peer_id(), state);
+        }
+        SwarmEvent::ExternalAddrExpired { address } => {
+            info!(%address, "external address expired");
+            state.external_addresses.expire(&address);
         }
         SwarmEvent::Behaviour(MyBehaviourEvent::Identify(event)) => {
-            println!("identify: {event:?}");
+            debug!(?event, "identify");
+            if let identify::Event::Received { peer_id, info, .. } = event {
+                state.peers.identified(&peer_id, info.agent_version.clone());
+                if info.protocols.contains(&KAD_PROTOCOL) {
+                    for address in &info.listen_addrs {
+                        swarm
+                            .behaviour_mut()
+                            .kad
+                            .add_address(&peer_id, address.clone());
+                    }
+                }
+                state
+                    .peer_store
+                    .record_connection(&peer_id, &info.listen_addrs);
+
+                if let Some(candidate) = state.external_addresses.observe(
+                    peer_id,
+                    &info.observed_addr,
+                    state.listen_port,
+                ) {
+                    info!(%candidate, "probing external address candidate");
+                    swarm.behaviour_mut().autonat.probe_address(candidate);
+                }
+            }
+        }
+        SwarmEvent::Behaviour(MyBehaviourEvent::Autonat(autonat::Event::StatusChanged {
+            old,
+            new,
+        })) => {
+            info!(?old, ?new, "autonat: NAT status changed");
+            if let autonat::NatStatus::Public(address) = new {
+                swarm.add_external_address(address.clone());
+                on_external_addr_confirmed(address, *swarm.local_peer_id(), state);
+            }
         }
+        SwarmEvent::Behaviour(MyBehaviourEvent::RelayClient(event)) => match event {
+            relay::client::Event::ReservationReqAccepted {
+                relay_peer_id,
+                renewal,
+                ..
+            } => {
+                let action = if renewal { "renewed" } else { "accepted" };
+                info!(peer_id = %relay_peer_id, "relay: reservation {action}");
+            }
+            relay::client::Event::OutboundCircuitEstablished { relay_peer_id, .. } => {
+                info!(peer_id = %relay_peer_id, "relay: outbound circuit established");
+            }
+            relay::client::Event::InboundCircuitEstablished { src_peer_id, .. } => {
+                info!(peer_id = %src_peer_id, "relay: inbound circuit established");
+            }
+        },
+        SwarmEvent::Behaviour(MyBehaviourEvent::Relay(event)) => {
+            debug!(?event, "relay server");
+        }
+        SwarmEvent::Behaviour(MyBehaviourEvent::Dcutr(dcutr::Event {
+            remote_peer_id,
+            result,
+        })) => match result {
+            Ok(connection_id) => {
+                info!(peer_id = %remote_peer_id, %connection_id, "dcutr: direct connection upgraded")
+            }
+            Err(e) => info!(peer_id = %remote_peer_id, error = %e, "dcutr: hole punching failed"),
+        },
         SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(gossipsub::Event::Message {
             propagation_source: peer_id,
             message_id: id,
             message,
         })) => {
-            println!(
-                "Got message: {} with id: {} from peer: {:?}",
-                String::from_utf8_lossy(&message.data),
-                id,
-                peer_id
-            );
+            let acceptance = match validate_message(&message).and_then(|alert| {
+                validate_known_revocation(&alert, state)?;
+                Ok(alert)
+            }) {
+                Ok(alert) => {
+                    // Keyed on the content so the copies of other topics and
+                    // alerts already synced are handled once
+                    let alert_id = alert.content_id();
+                    match state.seen.insert(&alert_id) {
+                        Ok(true) => {
+                            state.metrics.alert_received("gossip");
+                            match state.alerts.insert(&alert) {
+                                Ok(true) => {
+                                    state.metrics.alert_stored();
+                                    let _ = state.feed.send(alert.clone());
+                                }
+                                Ok(false) => {}
+                                Err(e) => {
+                                    error!(%alert_id, error = %e, "failed to store alert")
+                                }
+                            }
+                            info!(
+                                %peer_id,
+                                message_id = %id,
+                                %alert_id,
+                                topic = %message.topic,
+                                type_alert = %alert.type_alert,
+                                country = %alert.country,
+                                "got alert"
+                            );
+                            gossipsub::MessageAcceptance::Accept
+                        }
+                        // Already handled, possibly before a restart. Still
+                        // forwarded, the peers of this topic may not have it.
+                        Ok(false) => gossipsub::MessageAcceptance::Accept,
+                        Err(e) => {
+                            error!(%alert_id, error = %e, "failed to record alert");
+                            gossipsub::MessageAcceptance::Accept
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(%peer_id, message_id = %id, error = %e, "invalid message");
+                    state.metrics.alert_rejected(e.reason());
+                    e.acceptance()
+                }
+            };
+            if let Err(e) = swarm
+                .behaviour_mut()
+                .gossipsub
+                .report_message_validation_result(&id, &peer_id, acceptance)
+            {
+                warn!(message_id = %id, error = ?e, "failed to forward message");
+            }
+        }
+        // Lookups started from the operator console
+        SwarmEvent::Behaviour(MyBehaviourEvent::Kad(kad::Event::OutboundQueryProgressed {
+            id,
+            result: kad::QueryResult::GetRecord(result),
+            ..
+        })) => match result {
+            Ok(kad::GetRecordOk::FoundRecord(kad::PeerRecord { peer, record })) => {
+                info!(
+                    query = ?id,
+                    key = %String::from_utf8_lossy(record.key.as_ref()),
+                    value = %String::from_utf8_lossy(&record.value),
+                    from = ?peer,
+                    "dht: record found"
+                );
+            }
+            Ok(kad::GetRecordOk::FinishedWithNoAdditionalRecord { .. }) => {
+                debug!(query = ?id, "dht: lookup finished");
+            }
+            Err(e) => info!(query = ?id, error = %e, "dht: record not found"),
+        },
+        SwarmEvent::Behaviour(MyBehaviourEvent::Kad(event)) => {
+            debug!(?event, "kademlia");
+        }
+        SwarmEvent::Behaviour(MyBehaviourEvent::Sync(event)) => {
+            handle_sync_event(event, swarm, state);
         }
         SwarmEvent::Behaviour(MyBehaviourEvent::Ping(event)) => match event {
             ping::Event {
@@ -27,34 +223,148 @@ pub async fn handle_swarm_event(event: SwarmEvent<MyBehaviourEvent>) {
                 result: Result::Ok(rtt),
                 ..
             } => {
-                println!(
-                    "ping: rtt to {} is {} ms",
-                    peer.to_base58(),
-                    rtt.as_millis()
-                );
+                debug!(peer_id = %peer, rtt_ms = rtt.as_millis(), "ping");
+                state.peers.pinged(&peer, rtt);
             }
             ping::Event {
                 peer,
                 result: Result::Err(ping::Failure::Timeout),
                 ..
             } => {
-                println!("ping: timeout to {}", peer.to_base58());
+                debug!(peer_id = %peer, "ping: timeout");
             }
             ping::Event {
                 peer,
                 result: Result::Err(ping::Failure::Unsupported),
                 ..
             } => {
-                println!("ping: {} does not support ping protocol", peer.to_base58());
+                debug!(peer_id = %peer, "ping: protocol not supported");
             }
             ping::Event {
                 peer,
                 result: Result::Err(ping::Failure::Other { error }),
                 ..
             } => {
-                println!("ping: ping::Failure with {}: {error}", peer.to_base58());
+                debug!(peer_id = %peer, %error, "ping: failure");
             }
         },
         _ => {}
     }
 }
+
+fn handle_sync_event(
+    event: request_response::Event<SyncRequest, SyncResponse>,
+    swarm: &mut Swarm<MyBehaviour>,
+    state: &mut NodeState,
+) {
+    match event {
+        request_response::Event::Message {
+            peer,
+            message:
+                request_response::Message::Request {
+                    request, channel, ..
+                },
+        } => match respond(&mut state.alerts, &request, SYNC_BATCH) {
+            Ok(response) => {
+                info!(peer_id = %peer, count = response.alerts.len(), "sync: sending alerts");
+                if swarm
+                    .behaviour_mut()
+                    .sync
+                    .send_response(channel, response)
+                    .is_err()
+                {
+                    warn!(peer_id = %peer, "sync: request closed before the response");
+                }
+            }
+            // Dropping the channel tells the peer the request failed
+            Err(e) => error!(peer_id = %peer, error = %e, "sync: failed to answer"),
+        },
+        request_response::Event::Message {
+            peer,
+            message: request_response::Message::Response { response, .. },
+        } => {
+            let mut received = 0;
+            for alert in &response.alerts {
+                if let Err(e) =
+                    validate_synced(alert).and_then(|()| validate_known_revocation(alert, state))
+                {
+                    state.metrics.alert_rejected(e.reason());
+                    warn!(peer_id = %peer, alert_id = %alert.content_id(), error = %e, "sync: invalid alert");
+                    continue;
+                }
+                match state.alerts.insert(alert) {
+                    Ok(true) => {
+                        received += 1;
+                        state.metrics.alert_received("sync");
+                        state.metrics.alert_stored();
+                        let _ = state.feed.send(alert.clone());
+                        // Gossip copies arriving later are then ignored
+                        if let Err(e) = state.seen.insert(&alert.content_id()) {
+                            error!(alert_id = %alert.content_id(), error = %e, "sync: failed to record alert");
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        error!(peer_id = %peer, alert_id = %alert.content_id(), error = %e, "sync: failed to store alert")
+                    }
+                }
+            }
+            info!(peer_id = %peer, received, "sync: received missed alerts");
+
+            if response.more {
+                match next_request(&mut state.alerts, &response) {
+                    Ok(request) => {
+                        swarm.behaviour_mut().sync.send_request(&peer, request);
+                    }
+                    Err(e) => error!(error = %e, "sync: failed to read sync watermarks"),
+                }
+            }
+        }
+        request_response::Event::OutboundFailure { peer, error, .. } => {
+            debug!(peer_id = %peer, %error, "sync: outbound request failed");
+        }
+        request_response::Event::InboundFailure { peer, error, .. } => {
+            debug!(peer_id = %peer, %error, "sync: inbound request failed");
+        }
+        request_response::Event::ResponseSent { .. } => {}
+    }
+}
+
+/// Rejects the revocation of a known alert of another origin, it would
+/// never be honoured
+fn validate_known_revocation(
+    alert: &AlertMessage,
+    state: &mut NodeState,
+) -> Result<(), ValidationError> {
+    let Some(target) = &alert.revokes else {
+        return Ok(());
+    };
+    match state.alerts.origin_of(target) {
+        Ok(origin) => validate_revocation(alert, origin.as_deref()),
+        // Stored anyway, revocations are only honoured for their origin
+        Err(e) => {
+            error!(alert_id = %target, error = %e, "failed to read revoked alert");
+            Ok(())
+        }
+    }
+}
+
+/// Registers a newly confirmed external address with the registry. Relayed
+/// `/p2p-circuit` addresses are confirmed as soon as a reservation is accepted
+/// and are registered like direct ones. The local peer id is appended so
+/// relayed addresses can be dialed. It is read from the identity file, so a
+/// restart registers the same address and the registry refreshes its row
+/// instead of adding one.
+fn on_external_addr_confirmed(address: Multiaddr, local_peer_id: PeerId, state: &mut NodeState) {
+    if !state.external_addresses.confirm(address.clone()) {
+        return;
+    }
+
+    let address = with_peer_id(address, local_peer_id);
+    let rest_request = state.rest_request.clone();
+    tokio::spawn(async move {
+        if let Err(e) = rest_request.register_node(&address).await {
+            warn!(%address, error = %e, "failed to register node address");
+        }
+    });
+}
@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use libp2p::{Multiaddr, PeerId, multiaddr::Protocol};
+
+/// Distinct peers that must observe the same IP before it is probed
+const MIN_OBSERVERS: usize = 2;
+
+/// Tracks the addresses other peers observe this node at (reported by
+/// identify) and which of them AutoNAT confirmed as reachable.
+#[derive(Debug, Default)]
+pub struct ExternalAddresses {
+    observers: HashMap<IpAddr, HashSet<PeerId>>,
+    probed: HashSet<Multiaddr>,
+    confirmed: Vec<Multiaddr>,
+}
+
+impl ExternalAddresses {
+    /// Records that `peer` observed us at `observed`. Returns the candidate
+    /// address to probe with AutoNAT once enough distinct peers agree on the
+    /// same public IP. The ephemeral port of the observed address is replaced
+    /// by `listen_port`, keeping its transport (TCP or QUIC).
+    pub fn observe(
+        &mut self,
+        peer: PeerId,
+        observed: &Multiaddr,
+        listen_port: u16,
+    ) -> Option<Multiaddr> {
+        let ip = public_ip(observed)?;
+
+        let observers = self.observers.entry(ip).or_default();
+        observers.insert(peer);
+
+        if observers.len() < MIN_OBSERVERS {
+            return None;
+        }
+
+        let candidate = if observed.iter().any(|p| p == Protocol::QuicV1) {
+            Multiaddr::from(ip)
+                .with(Protocol::Udp(listen_port))
+                .with(Protocol::QuicV1)
+        } else {
+            Multiaddr::from(ip).with(Protocol::Tcp(listen_port))
+        };
+        self.probed.insert(candidate.clone()).then_some(candidate)
+    }
+
+    /// Marks `address` as confirmed, returns true the first time
+    pub fn confirm(&mut self, address: Multiaddr) -> bool {
+        if self.confirmed.contains(&address) {
+            return false;
+        }
+        self.confirmed.push(address);
+        true
+    }
+
+    /// Forgets an address that is no longer reachable, so it can be probed
+    /// again when peers keep observing it
+    pub fn expire(&mut self, address: &Multiaddr) {
+        self.confirmed.retain(|known| known != address);
+        self.probed.remove(address);
+    }
+
+    /// Addresses confirmed as reachable from the outside
+    pub fn confirmed(&self) -> &[Multiaddr] {
+        &self.confirmed
+    }
+}
+
+/// Global IP at the start of `address`, if any
+pub fn public_ip(address: &Multiaddr) -> Option<IpAddr> {
+    let ip = match address.iter().next()? {
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+    is_global(&ip).then_some(ip)
+}
+
+fn is_global(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || ip.segments()[0] == 0x2001 && ip.segments()[1] == 0xdb8)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn addr(text: &str) -> Multiaddr {
+        Multiaddr::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_candidate_after_enough_observers() {
+        let mut addresses = ExternalAddresses::default();
+        let observed = addr("/ip4/8.8.4.4/tcp/53122");
+        let peer = PeerId::random();
+
+        assert_eq!(addresses.observe(peer, &observed, 4001), None);
+        // Same peer again does not count twice
+        assert_eq!(addresses.observe(peer, &observed, 4001), None);
+
+        let candidate = addresses.observe(PeerId::random(), &addr("/ip4/8.8.4.4/tcp/41000"), 4001);
+        assert_eq!(candidate, Some(addr("/ip4/8.8.4.4/tcp/4001")));
+
+        // Only probed once
+        assert_eq!(addresses.observe(PeerId::random(), &observed, 4001), None);
+    }
+
+    #[test]
+    fn test_quic_observation_gives_quic_candidate() {
+        let mut addresses = ExternalAddresses::default();
+        let observed = addr("/ip4/8.8.4.4/udp/53122/quic-v1");
+
+        addresses.observe(PeerId::random(), &observed, 4001);
+        let candidate = addresses.observe(PeerId::random(), &observed, 4001);
+        assert_eq!(candidate, Some(addr("/ip4/8.8.4.4/udp/4001/quic-v1")));
+
+        // The TCP address of the same IP is still probed
+        let candidate = addresses.observe(PeerId::random(), &addr("/ip4/8.8.4.4/tcp/1"), 4001);
+        assert_eq!(candidate, Some(addr("/ip4/8.8.4.4/tcp/4001")));
+    }
+
+    #[test]
+    fn test_private_observations_are_ignored() {
+        let mut addresses = ExternalAddresses::default();
+
+        for observed in [
+            "/ip4/127.0.0.1/tcp/1",
+            "/ip4/192.168.1.20/tcp/1",
+            "/ip4/10.0.0.1/tcp/1",
+            "/ip4/100.64.0.1/tcp/1",
+            "/ip6/::1/tcp/1",
+            "/ip6/fd00::1/tcp/1",
+            "/dns4/example.com/tcp/1",
+        ] {
+            for _ in 0..MIN_OBSERVERS {
+                assert_eq!(
+                    addresses.observe(PeerId::random(), &addr(observed), 4001),
+                    None,
+                    "{observed} should be ignored"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_confirm_and_expire() {
+        let mut addresses = ExternalAddresses::default();
+        let confirmed = addr("/ip4/8.8.4.4/tcp/4001");
+
+        assert!(addresses.confirm(confirmed.clone()));
+        assert!(!addresses.confirm(confirmed.clone()));
+        assert_eq!(addresses.confirmed(), std::slice::from_ref(&confirmed));
+
+        addresses.expire(&confirmed);
+        assert!(addresses.confirmed().is_empty());
+    }
+
+    #[test]
+    fn test_expired_address_can_be_probed_again() {
+        let mut addresses = ExternalAddresses::default();
+        let observed = addr("/ip4/8.8.4.4/tcp/53122");
+
+        addresses.observe(PeerId::random(), &observed, 4001);
+        assert!(
+            addresses
+                .observe(PeerId::random(), &observed, 4001)
+                .is_some()
+        );
+
+        addresses.expire(&addr("/ip4/8.8.4.4/tcp/4001"));
+        assert!(
+            addresses
+                .observe(PeerId::random(), &observed, 4001)
+                .is_some()
+        );
+    }
+}
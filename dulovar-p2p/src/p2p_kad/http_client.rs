@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use tracing::warn;
+
+/// Errors of the calls made to the registry
+#[derive(Debug)]
+pub enum RegistryError {
+    /// Transport failure, timeout or undecodable body
+    Http(reqwest::Error),
+    /// The server answered with a non-success status
+    Status(StatusCode),
+    /// Too many consecutive failures, calls to this host are paused
+    CircuitOpen { host: String },
+    /// The address to register is not a confirmed public address
+    UnknownPublicAddress,
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Http(e) => write!(f, "HTTP request failed: {e}"),
+            RegistryError::Status(status) => write!(f, "unexpected HTTP status {status}"),
+            RegistryError::CircuitOpen { host } => {
+                write!(f, "circuit breaker open for {host}, request not sent")
+            }
+            RegistryError::UnknownPublicAddress => write!(f, "public address is unknown"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RegistryError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for RegistryError {
+    fn from(e: reqwest::Error) -> Self {
+        RegistryError::Http(e)
+    }
+}
+
+impl RegistryError {
+    /// Whether sending the same request again may succeed
+    fn is_retryable(&self) -> bool {
+        match self {
+            RegistryError::Http(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            RegistryError::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            RegistryError::CircuitOpen { .. } | RegistryError::UnknownPublicAddress => false,
+        }
+    }
+}
+
+/// Timeouts, retry and circuit breaker settings of [`HttpClient`]
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    /// Whole request timeout, body included
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Attempts after the first one
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failed calls before the breaker opens
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before letting a trial call through
+    pub open_duration: Duration,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(5),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// Delay before the retry number `attempt` (starting at 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Per-host circuit breaker state
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Whether a call may be sent at `now`. Once the open period is over a
+    /// trial call is let through and the breaker closes if it succeeds.
+    fn allows(&self, now: Instant) -> bool {
+        self.open_until.is_none_or(|until| now >= until)
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    fn record_failure(&mut self, now: Instant, config: &HttpClientConfig) {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= config.failure_threshold {
+            self.open_until = Some(now + config.open_duration);
+        }
+    }
+}
+
+/// HTTP client shared by the registry calls, with timeouts, exponential
+/// backoff retries and a circuit breaker per host
+pub struct HttpClient {
+    client: Client,
+    config: HttpClientConfig,
+    breakers: Mutex<HashMap<String, CircuitBreaker>>,
+}
+
+impl HttpClient {
+    pub fn new(config: HttpClientConfig) -> Result<Self, RegistryError> {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .build()?;
+
+        Ok(Self {
+            client,
+            config,
+            breakers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Sends the request built by `build`, retrying transient failures.
+    /// Non-success statuses are returned as [`RegistryError::Status`].
+    pub async fn send(
+        &self,
+        host: &str,
+        build: impl Fn(&Client) -> RequestBuilder,
+    ) -> Result<Response, RegistryError> {
+        if !self.breaker_allows(host) {
+            return Err(RegistryError::CircuitOpen {
+                host: host.to_string(),
+            });
+        }
+
+        let mut attempt = 0;
+        loop {
+            let result = match build(&self.client).send().await {
+                Ok(response) if response.status().is_success() => Ok(response),
+                Ok(response) => Err(RegistryError::Status(response.status())),
+                Err(e) => Err(RegistryError::Http(e)),
+            };
+
+            match result {
+                Ok(response) => {
+                    self.record(host, true);
+                    return Ok(response);
+                }
+                Err(e) if e.is_retryable() && attempt < self.config.max_retries => {
+                    attempt += 1;
+                    let delay = self.config.backoff(attempt);
+                    warn!(host, error = %e, attempt, ?delay, "request failed, retrying");
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    self.record(host, false);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn breaker_allows(&self, host: &str) -> bool {
+        let breakers = self.breakers.lock().unwrap();
+        breakers
+            .get(host)
+            .is_none_or(|breaker| breaker.allows(Instant::now()))
+    }
+
+    fn record(&self, host: &str, success: bool) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(host.to_string()).or_default();
+        if success {
+            breaker.record_success();
+        } else {
+            breaker.record_failure(Instant::now(), &self.config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let config = HttpClientConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(700),
+            ..HttpClientConfig::default()
+        };
+
+        assert_eq!(config.backoff(1), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(200));
+        assert_eq!(config.backoff(3), Duration::from_millis(400));
+        assert_eq!(config.backoff(4), Duration::from_millis(700));
+        assert_eq!(config.backoff(40), Duration::from_millis(700));
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_after_threshold() {
+        let config = HttpClientConfig {
+            failure_threshold: 2,
+            open_duration: Duration::from_secs(30),
+            ..HttpClientConfig::default()
+        };
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::default();
+
+        breaker.record_failure(now, &config);
+        assert!(breaker.allows(now));
+
+        breaker.record_failure(now, &config);
+        assert!(!breaker.allows(now));
+        assert!(!breaker.allows(now + Duration::from_secs(29)));
+        assert!(breaker.allows(now + Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_circuit_breaker_closes_on_success() {
+        let config = HttpClientConfig {
+            failure_threshold: 1,
+            ..HttpClientConfig::default()
+        };
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::default();
+
+        breaker.record_failure(now, &config);
+        assert!(!breaker.allows(now));
+
+        breaker.record_success();
+        assert!(breaker.allows(now));
+        assert_eq!(breaker.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_status_retryability() {
+        assert!(RegistryError::Status(StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(RegistryError::Status(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert!(!RegistryError::Status(StatusCode::BAD_REQUEST).is_retryable());
+        assert!(!RegistryError::UnknownPublicAddress.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_fails_fast() {
+        let client = HttpClient::new(HttpClientConfig::default()).unwrap();
+        client.breakers.lock().unwrap().insert(
+            "registry".to_string(),
+            CircuitBreaker {
+                consecutive_failures: 5,
+                open_until: Some(Instant::now() + Duration::from_secs(60)),
+            },
+        );
+
+        let result = client
+            .send("registry", |c| c.get("http://127.0.0.1:9/nodes"))
+            .await;
+        assert!(matches!(result, Err(RegistryError::CircuitOpen { .. })));
+    }
+}
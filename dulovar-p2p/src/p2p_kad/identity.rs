@@ -0,0 +1,91 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use libp2p::identity::Keypair;
+
+/// Keypair of the node stored at `path`, generated and saved on the first
+/// run. The peer id derived from it is the origin of the alerts the node
+/// publishes, so it must outlive restarts for the node to revoke them.
+pub fn load_or_create(path: &Path) -> io::Result<Keypair> {
+    match fs::read(path) {
+        Ok(bytes) => Keypair::from_protobuf_encoding(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = Keypair::generate_ed25519();
+            save(path, &keypair)?;
+            Ok(keypair)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes the keypair atomically, only readable by the owner on Unix
+fn save(path: &Path, keypair: &Keypair) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    io::Write::write_all(&mut options.open(&tmp)?, &bytes)?;
+    fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p_kad::p2p_kad_utils::with_peer_id;
+    use libp2p::Multiaddr;
+
+    #[test]
+    fn test_identity_is_kept_across_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/identity.key");
+
+        let created = load_or_create(&path).unwrap();
+        let loaded = load_or_create(&path).unwrap();
+        assert_eq!(created.public().to_peer_id(), loaded.public().to_peer_id());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_registered_address_is_kept_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        let address: Multiaddr = "/ip4/203.0.113.7/tcp/4001".parse().unwrap();
+
+        let registered =
+            |keypair: Keypair| with_peer_id(address.clone(), keypair.public().to_peer_id());
+        assert_eq!(
+            registered(load_or_create(&path).unwrap()),
+            registered(load_or_create(&path).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_corrupt_identity_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        fs::write(&path, b"not a key").unwrap();
+
+        let err = load_or_create(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read(&path).unwrap(), b"not a key");
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libp2p::{Multiaddr, PeerId, Swarm};
+
+use crate::db::DbError;
+use crate::p2p_kad::my_behaviour::MyBehaviour;
+use crate::p2p_kad::node_state::NodeState;
+
+/// What the node knows about a connected peer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectedPeer {
+    /// Remote address of each open connection
+    pub addresses: Vec<Multiaddr>,
+    /// Last ping round trip
+    pub rtt: Option<Duration>,
+    /// Agent version announced through identify
+    pub agent_version: Option<String>,
+}
+
+/// Connected peers, with what ping and identify reported about them
+#[derive(Debug, Default)]
+pub struct ConnectedPeers {
+    peers: HashMap<PeerId, ConnectedPeer>,
+}
+
+impl ConnectedPeers {
+    pub fn connected(&mut self, peer: PeerId, address: Multiaddr) {
+        self.peers.entry(peer).or_default().addresses.push(address);
+    }
+
+    /// Forgets the connection at `address`, and the peer once none is left
+    pub fn disconnected(&mut self, peer: &PeerId, address: &Multiaddr) {
+        if let Some(connected) = self.peers.get_mut(peer) {
+            if let Some(i) = connected.addresses.iter().position(|a| a == address) {
+                connected.addresses.swap_remove(i);
+            }
+            if connected.addresses.is_empty() {
+                self.peers.remove(peer);
+            }
+        }
+    }
+
+    pub fn pinged(&mut self, peer: &PeerId, rtt: Duration) {
+        if let Some(connected) = self.peers.get_mut(peer) {
+            connected.rtt = Some(rtt);
+        }
+    }
+
+    pub fn identified(&mut self, peer: &PeerId, agent_version: String) {
+        if let Some(connected) = self.peers.get_mut(peer) {
+            connected.agent_version = Some(agent_version);
+        }
+    }
+
+    /// Connected peers sorted by peer id
+    pub fn list(&self) -> Vec<(PeerId, ConnectedPeer)> {
+        let mut peers: Vec<_> = self
+            .peers
+            .iter()
+            .map(|(peer, connected)| (*peer, connected.clone()))
+            .collect();
+        peers.sort_by_key(|(peer, _)| peer.to_base58());
+        peers
+    }
+}
+
+/// Rows of the local database tables
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DbStats {
+    pub alerts: i64,
+    pub outbox: i64,
+    pub seen_messages: i64,
+    pub banned_peers: i64,
+}
+
+/// Snapshot of the node answered to the admin RPC
+#[derive(Debug, Clone)]
+pub struct NodeStatus {
+    pub peer_id: PeerId,
+    pub listen_addresses: Vec<Multiaddr>,
+    pub external_addresses: Vec<Multiaddr>,
+    /// Subscribed topics with the peers of their mesh
+    pub topics: Vec<(String, Vec<PeerId>)>,
+    /// Peers in the Kademlia routing table
+    pub kademlia_peers: usize,
+    pub db: DbStats,
+}
+
+pub fn node_status(
+    swarm: &mut Swarm<MyBehaviour>,
+    state: &mut NodeState,
+) -> Result<NodeStatus, DbError> {
+    let db = DbStats {
+        alerts: state.alerts.count()?,
+        outbox: state.alerts.outbox_len()?,
+        seen_messages: state.seen.count()?,
+        banned_peers: state.bans.count()?,
+    };
+
+    let gossipsub = &swarm.behaviour().gossipsub;
+    let mut topics: Vec<_> = gossipsub
+        .topics()
+        .map(|topic| {
+            (
+                topic.to_string(),
+                gossipsub.mesh_peers(topic).copied().collect(),
+            )
+        })
+        .collect();
+    topics.sort();
+
+    Ok(NodeStatus {
+        peer_id: *swarm.local_peer_id(),
+        listen_addresses: swarm.listeners().cloned().collect(),
+        external_addresses: swarm.external_addresses().cloned().collect(),
+        topics,
+        kademlia_peers: swarm
+            .behaviour_mut()
+            .kad
+            .kbuckets()
+            .map(|bucket| bucket.num_entries())
+            .sum(),
+        db,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_is_forgotten_with_its_last_connection() {
+        let mut peers = ConnectedPeers::default();
+        let peer = PeerId::random();
+        let tcp: Multiaddr = "/ip4/192.0.2.1/tcp/4001".parse().unwrap();
+        let quic: Multiaddr = "/ip4/192.0.2.1/udp/4001/quic-v1".parse().unwrap();
+
+        peers.connected(peer, tcp.clone());
+        peers.connected(peer, quic.clone());
+        peers.pinged(&peer, Duration::from_millis(12));
+        peers.identified(&peer, "dulovar/1.0".into());
+        // Not connected, ignored
+        peers.pinged(&PeerId::random(), Duration::from_millis(1));
+
+        let listed = peers.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].1.rtt, Some(Duration::from_millis(12)));
+        assert_eq!(listed[0].1.agent_version.as_deref(), Some("dulovar/1.0"));
+
+        peers.disconnected(&peer, &tcp);
+        assert_eq!(peers.list()[0].1.addresses, std::slice::from_ref(&quic));
+        peers.disconnected(&peer, &quic);
+        assert!(peers.list().is_empty());
+    }
+}
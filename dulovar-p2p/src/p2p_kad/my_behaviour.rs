@@ -1,9 +1,26 @@
-use libp2p::swarm::NetworkBehaviour;
-use libp2p::{gossipsub, identify, ping};
+use libp2p::swarm::{NetworkBehaviour, behaviour::toggle::Toggle};
+use libp2p::{
+    allow_block_list, autonat, connection_limits, dcutr, gossipsub, identify, kad, ping, relay,
+};
+
+use crate::p2p_kad::sync::SyncBehaviour;
 
 #[derive(NetworkBehaviour)]
 pub struct MyBehaviour {
+    /// Only enabled when an allowlist is configured
+    pub allowlist: Toggle<allow_block_list::Behaviour<allow_block_list::AllowedPeers>>,
+    pub blocklist: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
+    pub connection_limits: connection_limits::Behaviour,
+    pub autonat: autonat::Behaviour,
+    pub dcutr: dcutr::Behaviour,
     pub gossipsub: gossipsub::Behaviour,
     pub identify: identify::Behaviour,
+    /// DHT of the dulovar network, peers are added as identify reports them
+    pub kad: kad::Behaviour<kad::store::MemoryStore>,
     pub ping: ping::Behaviour,
+    /// Relay server, only enabled on master nodes
+    pub relay: Toggle<relay::Behaviour>,
+    pub relay_client: relay::client::Behaviour,
+    /// Anti-entropy sync of missed alerts
+    pub sync: SyncBehaviour,
 }
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use libp2p::PeerId;
+use libp2p::identity::Keypair;
+use tokio::sync::broadcast;
+
+use crate::db::alert_store::AlertStore;
+use crate::db::ban_store::BanStore;
+use crate::db::seen_cache::SeenCache;
+use crate::metrics::Metrics;
+use crate::p2p_kad::alert_message::AlertMessage;
+use crate::p2p_kad::external_addresses::ExternalAddresses;
+use crate::p2p_kad::introspection::ConnectedPeers;
+use crate::p2p_kad::peer_store::PeerStore;
+use crate::p2p_kad::rest_request::RestRequest;
+
+/// Alerts buffered for each subscriber before the slowest ones miss some
+const FEED_CAPACITY: usize = 256;
+
+/// State of the node shared by the event loop and the swarm event handlers
+pub struct NodeState {
+    /// Identity of the node, signing the alerts it publishes
+    pub keypair: Keypair,
+    pub peer_store: PeerStore,
+    pub external_addresses: ExternalAddresses,
+    /// Connected peers with their ping and identify details
+    pub peers: ConnectedPeers,
+    pub rest_request: Arc<RestRequest>,
+    /// Gossip messages already processed
+    pub seen: SeenCache,
+    /// Alerts published or received, served to peers syncing
+    pub alerts: AlertStore,
+    /// Peers banned through the admin RPC
+    pub bans: BanStore,
+    /// Peers of the configured blocklist, which cannot be unbanned
+    pub blocked_peers: HashSet<PeerId>,
+    /// TCP port the node listens on
+    pub listen_port: u16,
+    pub metrics: Metrics,
+    /// New alerts, local or received, sent to the gRPC subscribers
+    pub feed: broadcast::Sender<AlertMessage>,
+}
+
+impl NodeState {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        keypair: Keypair,
+        peer_store: PeerStore,
+        rest_request: Arc<RestRequest>,
+        seen: SeenCache,
+        alerts: AlertStore,
+        bans: BanStore,
+        listen_port: u16,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            keypair,
+            peer_store,
+            external_addresses: ExternalAddresses::default(),
+            peers: ConnectedPeers::default(),
+            rest_request,
+            seen,
+            alerts,
+            bans,
+            blocked_peers: HashSet::new(),
+            listen_port,
+            metrics,
+            feed: broadcast::channel(FEED_CAPACITY).0,
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use libp2p::Swarm;
-use libp2p::{Multiaddr, multiaddr::Protocol};
+use libp2p::{Multiaddr, PeerId, multiaddr::Protocol};
 
 use std::{error::Error, str::FromStr};
+use tracing::{debug, warn};
 
 /// for a multiaddr that ends with a peer id, this strips this suffix. Rust-libp2p
 /// only supports dialing to an address without providing the peer id.
@@ -11,7 +12,7 @@ pub fn strip_peer_id(addr: &mut Multiaddr) {
         Some(Protocol::P2p(peer_id)) => {
             let mut addr = Multiaddr::empty();
             addr.push(Protocol::P2p(peer_id));
-            println!("removing peer id {addr} so this address can be dialed by rust-libp2p");
+            debug!(%addr, "removing peer id so this address can be dialed by rust-libp2p");
         }
         Some(other) => addr.push(other),
         _ => {}
@@ -31,14 +32,42 @@ pub fn parse_legacy_multiaddr(text: &str) -> Result<Multiaddr, Box<dyn Error>> {
     Ok(res)
 }
 
+/// parse an address to dial. Relayed addresses keep their trailing peer id,
+/// the relay needs it to know which peer to connect to
+pub fn parse_dial_address(text: &str) -> Result<Multiaddr, Box<dyn Error>> {
+    match Multiaddr::from_str(text) {
+        Ok(addr) if addr.iter().any(|p| p == Protocol::P2pCircuit) => Ok(addr),
+        _ => parse_legacy_multiaddr(text),
+    }
+}
+
+/// appends `/p2p/<peer_id>` to `addr` unless it already ends with a peer id
+pub fn with_peer_id(mut addr: Multiaddr, peer_id: PeerId) -> Multiaddr {
+    if !matches!(addr.iter().last(), Some(Protocol::P2p(_))) {
+        addr.push(Protocol::P2p(peer_id));
+    }
+    addr
+}
+
+/// address to listen on to reserve a slot on `relay`, which must end with
+/// the relay peer id
+pub fn relay_circuit_address(relay: &Multiaddr) -> Option<Multiaddr> {
+    match relay.iter().last() {
+        Some(Protocol::P2p(_)) => Some(relay.clone().with(Protocol::P2pCircuit)),
+        _ => None,
+    }
+}
+
 pub fn add_new_nodes(
     swarm: &mut Swarm<impl libp2p::swarm::NetworkBehaviour>,
     nodes: Vec<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     for to_dial in nodes {
-        println!("Dialed {to_dial:?}");
-        if let Ok(addr) = parse_legacy_multiaddr(&to_dial) {
-            swarm.dial(addr)?;
+        debug!(address = %to_dial, "dialing");
+        if let Ok(addr) = parse_dial_address(&to_dial)
+            && let Err(e) = swarm.dial(addr)
+        {
+            warn!(address = %to_dial, error = %e, "failed to dial");
         }
     }
     Ok(())
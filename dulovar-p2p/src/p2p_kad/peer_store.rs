@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libp2p::{Multiaddr, PeerId, multiaddr::Protocol};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Peers are forgotten when no connection succeeded for this long (30 days)
+const MAX_PEER_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Addresses kept per peer
+const MAX_ADDRS_PER_PEER: usize = 8;
+
+/// Known addresses of a peer and the last time a connection succeeded
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PeerRecord {
+    pub addrs: Vec<String>,
+    pub last_connected: u64,
+}
+
+/// On-disk cache of the peers this node managed to connect to, plus the last
+/// list returned by the registry, so a restart can rejoin the mesh when the
+/// registry is unreachable.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PeerStore {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Changed since the last save
+    #[serde(skip)]
+    dirty: bool,
+    peers: HashMap<String, PeerRecord>,
+    registry_nodes: Vec<String>,
+}
+
+impl PeerStore {
+    /// Loads the store from `path`, starting empty when the file is missing
+    /// or cannot be parsed
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut store = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<PeerStore>(&bytes).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "ignoring corrupt peer store");
+                PeerStore::default()
+            }),
+            Err(_) => PeerStore::default(),
+        };
+        store.path = path;
+        store.prune(now_secs());
+        store
+    }
+
+    /// Writes the store to disk, replacing the previous file atomically
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    /// Saves the store if it changed since it was last saved
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.dirty {
+            self.save()?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Records a successful connection to `peer_id` through `addrs`
+    pub fn record_connection<'a>(
+        &mut self,
+        peer_id: &PeerId,
+        addrs: impl IntoIterator<Item = &'a Multiaddr>,
+    ) {
+        self.record_connection_at(peer_id, addrs, now_secs());
+    }
+
+    fn record_connection_at<'a>(
+        &mut self,
+        peer_id: &PeerId,
+        addrs: impl IntoIterator<Item = &'a Multiaddr>,
+        now: u64,
+    ) {
+        let record = self
+            .peers
+            .entry(peer_id.to_string())
+            .or_insert_with(|| PeerRecord {
+                addrs: Vec::new(),
+                last_connected: now,
+            });
+        record.last_connected = now;
+        self.dirty = true;
+
+        for addr in addrs.into_iter().filter(|addr| is_cacheable(addr)) {
+            let mut addr = addr.clone();
+            if !matches!(addr.iter().last(), Some(Protocol::P2p(_))) {
+                addr.push(Protocol::P2p(*peer_id));
+            }
+            let addr = addr.to_string();
+
+            // Most recent first
+            record.addrs.retain(|known| known != &addr);
+            record.addrs.insert(0, addr);
+        }
+        record.addrs.truncate(MAX_ADDRS_PER_PEER);
+    }
+
+    /// Remembers the last successful response of the registry
+    pub fn set_registry_nodes(&mut self, nodes: Vec<String>) {
+        self.registry_nodes = nodes;
+        self.dirty = true;
+    }
+
+    /// Last successful response of the registry
+    pub fn registry_nodes(&self) -> &[String] {
+        &self.registry_nodes
+    }
+
+    /// Addresses worth dialing on startup, most recently connected peers
+    /// first, followed by the cached registry nodes
+    pub fn dial_candidates(&self, limit: usize) -> Vec<String> {
+        self.dial_candidates_by(limit, |_| Some(0))
+    }
+
+    /// Like [`PeerStore::dial_candidates`], picking for each peer the address
+    /// with the lowest `rank`. Addresses ranked `None` cannot be dialed and
+    /// are skipped.
+    pub fn dial_candidates_by(
+        &self,
+        limit: usize,
+        rank: impl Fn(&Multiaddr) -> Option<u8>,
+    ) -> Vec<String> {
+        let rank = |addr: &String| addr.parse().ok().and_then(|addr| rank(&addr));
+
+        let mut peers: Vec<&PeerRecord> = self.peers.values().collect();
+        peers.sort_by_key(|record| std::cmp::Reverse(record.last_connected));
+
+        let mut candidates: Vec<String> = Vec::new();
+        let addrs = peers
+            .into_iter()
+            .filter_map(|record| {
+                // min_by_key keeps the first, most recent, address on ties
+                record
+                    .addrs
+                    .iter()
+                    .filter_map(|addr| rank(addr).map(|r| (r, addr)))
+                    .min_by_key(|(r, _)| *r)
+                    .map(|(_, addr)| addr)
+            })
+            .chain(
+                self.registry_nodes
+                    .iter()
+                    .filter(|addr| rank(addr).is_some()),
+            );
+
+        for addr in addrs {
+            if candidates.len() == limit {
+                break;
+            }
+            if !candidates.contains(addr) {
+                candidates.push(addr.clone());
+            }
+        }
+        candidates
+    }
+
+    /// Number of peers in the store
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Returns true when no peer has been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    fn prune(&mut self, now: u64) {
+        self.peers
+            .retain(|_, record| now.saturating_sub(record.last_connected) < MAX_PEER_AGE_SECS);
+    }
+}
+
+/// Loopback and unspecified addresses are useless to other hosts
+fn is_cacheable(addr: &Multiaddr) -> bool {
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => !ip.is_loopback() && !ip.is_unspecified(),
+        Some(Protocol::Ip6(ip)) => !ip.is_loopback() && !ip.is_unspecified(),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn addr(text: &str) -> Multiaddr {
+        Multiaddr::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_load_missing_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PeerStore::load(dir.path().join("peers.json"));
+
+        assert!(store.is_empty());
+        assert!(store.dial_candidates(10).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/peers.json");
+        let peer_id = PeerId::random();
+
+        let mut store = PeerStore::load(&path);
+        store.record_connection(&peer_id, [&addr("/ip4/203.0.113.7/tcp/4001")]);
+        store.set_registry_nodes(vec!["/ip4/198.51.100.2/tcp/4001".to_string()]);
+        store.save().unwrap();
+
+        let loaded = PeerStore::load(&path);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.registry_nodes(), ["/ip4/198.51.100.2/tcp/4001"]);
+        assert_eq!(
+            loaded.dial_candidates(10)[0],
+            format!("/ip4/203.0.113.7/tcp/4001/p2p/{peer_id}")
+        );
+    }
+
+    #[test]
+    fn test_flush_saves_changes_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("peers.json");
+
+        let mut store = PeerStore::load(&path);
+        store.flush().unwrap();
+        assert!(!path.exists());
+
+        store.record_connection(&PeerId::random(), [&addr("/ip4/203.0.113.7/tcp/4001")]);
+        store.flush().unwrap();
+        assert_eq!(PeerStore::load(&path).len(), 1);
+
+        fs::remove_file(&path).unwrap();
+        store.flush().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_corrupt_file_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("peers.json");
+        fs::write(&path, b"{ not json").unwrap();
+
+        assert!(PeerStore::load(&path).is_empty());
+    }
+
+    #[test]
+    fn test_dial_candidates_ordered_by_last_connection() {
+        let mut store = PeerStore::default();
+        let (old, recent) = (PeerId::random(), PeerId::random());
+
+        store.record_connection_at(&old, [&addr("/ip4/203.0.113.1/tcp/4001")], 100);
+        store.record_connection_at(&recent, [&addr("/ip4/203.0.113.2/tcp/4001")], 200);
+        store.set_registry_nodes(vec!["/ip4/198.51.100.2/tcp/4001".to_string()]);
+
+        let candidates = store.dial_candidates(10);
+        assert_eq!(candidates.len(), 3);
+        assert!(candidates[0].starts_with("/ip4/203.0.113.2/"));
+        assert!(candidates[1].starts_with("/ip4/203.0.113.1/"));
+        assert_eq!(candidates[2], "/ip4/198.51.100.2/tcp/4001");
+
+        assert_eq!(store.dial_candidates(1).len(), 1);
+    }
+
+    #[test]
+    fn test_dial_candidates_by_rank() {
+        let mut store = PeerStore::default();
+        let peer_id = PeerId::random();
+
+        store.record_connection_at(
+            &peer_id,
+            [
+                &addr("/ip4/203.0.113.1/udp/4001/quic-v1"),
+                &addr("/ip4/203.0.113.1/tcp/4001"),
+            ],
+            100,
+        );
+        store.set_registry_nodes(vec![
+            "/ip4/198.51.100.2/tcp/4001".to_string(),
+            "/ip4/198.51.100.3/udp/4001/quic-v1".to_string(),
+        ]);
+
+        let is_quic = |addr: &Multiaddr| addr.iter().any(|p| p == Protocol::QuicV1);
+
+        // Prefer QUIC over the most recent TCP address
+        let candidates =
+            store.dial_candidates_by(10, |addr| Some(if is_quic(addr) { 0 } else { 1 }));
+        assert!(candidates[0].starts_with("/ip4/203.0.113.1/udp/4001/quic-v1/"));
+        assert_eq!(candidates.len(), 3);
+
+        // QUIC disabled
+        let candidates = store.dial_candidates_by(10, |addr| (!is_quic(addr)).then_some(0));
+        assert!(candidates[0].starts_with("/ip4/203.0.113.1/tcp/4001/"));
+        assert_eq!(candidates[1], "/ip4/198.51.100.2/tcp/4001");
+        assert_eq!(candidates.len(), 2);
+    }
+
+    #[test]
+    fn test_loopback_addresses_are_not_cached() {
+        let mut store = PeerStore::default();
+        let peer_id = PeerId::random();
+
+        store.record_connection(
+            &peer_id,
+            [
+                &addr("/ip4/127.0.0.1/tcp/4001"),
+                &addr("/ip4/0.0.0.0/tcp/4001"),
+                &addr("/ip6/::1/tcp/4001"),
+            ],
+        );
+
+        assert_eq!(store.len(), 1);
+        assert!(store.dial_candidates(10).is_empty());
+    }
+
+    #[test]
+    fn test_stale_peers_are_pruned() {
+        let mut store = PeerStore::default();
+        let peer_id = PeerId::random();
+
+        store.record_connection_at(&peer_id, [&addr("/ip4/203.0.113.1/tcp/4001")], 0);
+        store.prune(MAX_PEER_AGE_SECS + 1);
+
+        assert!(store.is_empty());
+    }
+}
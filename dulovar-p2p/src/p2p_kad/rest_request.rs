@@ -1,72 +1,83 @@
 use std::collections::HashMap;
 
-use reqwest::Client;
+use libp2p::Multiaddr;
 use serde::Deserialize;
 
-pub struct RestRequest;
+use crate::p2p_kad::external_addresses::public_ip;
+use crate::p2p_kad::http_client::{HttpClient, HttpClientConfig, RegistryError};
 
-#[derive(Deserialize)]
-struct Node {
-    address: String,
+pub struct RestRequest {
+    client: HttpClient,
+}
+
+/// Node listed by the registry
+#[derive(Debug, Deserialize)]
+pub struct Node {
+    pub address: String,
+    #[allow(dead_code)]
     valid: i32,
     master: i32,
 }
 
+impl Node {
+    /// Master nodes run a relay server the other nodes reserve slots on
+    pub fn is_master(&self) -> bool {
+        self.master != 0
+    }
+}
+
 impl RestRequest {
     const DOMAIN: &str = "https://api.dulovar.com/nodes";
+    const REGISTRY_HOST: &str = "api.dulovar.com";
 
-    pub async fn get_nodes() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let url = Self::DOMAIN;
-        let response = reqwest::get(url).await?;
-        let nodes: Vec<Node> = response.json().await?;
-        Ok(nodes.into_iter().map(|n| n.address).collect())
+    pub fn new(config: HttpClientConfig) -> Result<Self, RegistryError> {
+        Ok(Self {
+            client: HttpClient::new(config)?,
+        })
     }
 
-    pub async fn register_node() -> Result<(), reqwest::Error> {
-        let ip = Self::get_public_ip().await;
-        let mut map = HashMap::new();
-        map.insert("address", &ip);
-
-        let url = Self::DOMAIN;
-        let client = Client::new();
-
-        let res = client.post(url).json(&map).send().await?;
+    pub async fn get_nodes(&self) -> Result<Vec<Node>, RegistryError> {
+        let response = self
+            .client
+            .send(Self::REGISTRY_HOST, |c| c.get(Self::DOMAIN))
+            .await?;
+        Ok(response.json().await?)
+    }
 
-        if res.status().is_success() {
-            println!("Node registered successfully with IP: {}", ip);
-        } else {
-            eprintln!("Error to register node. Code: {}", res.status());
+    /// Registers `address` as a dialable address of this node. Only public
+    /// addresses are sent, anything else is refused.
+    pub async fn register_node(&self, address: &Multiaddr) -> Result<(), RegistryError> {
+        if public_ip(address).is_none() {
+            return Err(RegistryError::UnknownPublicAddress);
         }
+        let address = address.to_string();
+        let mut map = HashMap::new();
+        map.insert("address", &address);
 
+        self.client
+            .send(Self::REGISTRY_HOST, |c| c.post(Self::DOMAIN).json(&map))
+            .await?;
+
+        tracing::info!(%address, "node registered");
         Ok(())
     }
+}
 
-    pub async fn get_public_ip() -> String {
-        let public_ip = match reqwest::get("https://api.ipify.org?format=text").await {
-            Ok(resp) if resp.status().is_success() => match resp.text().await {
-                Ok(t) => t.trim().to_string(),
-                Err(e) => {
-                    eprintln!("Failed to read ip body from api.ipify.org: {e}");
-                    String::from("unknown")
-                }
-            },
-            _ => match reqwest::get("https://ifconfig.co/ip").await {
-                Ok(resp) if resp.status().is_success() => {
-                    resp.text().await.unwrap_or_default().trim().to_string()
-                }
-                Ok(resp) => {
-                    eprintln!(
-                        "Fallback service returned non-success status: {}",
-                        resp.status()
-                    );
-                    String::from("unknown")
-                }
-                Err(e) => {
-                    eprintln!("Failed to contact public IP services: {e}");
-                    String::from("unknown")
-                }
-            },
-        };
-        public_ip
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_register_refuses_non_public_address() {
+        let rest_request = RestRequest::new(HttpClientConfig::default()).unwrap();
+
+        for address in ["/ip4/127.0.0.1/tcp/4001", "/ip4/192.168.0.10/tcp/4001"] {
+            let address = Multiaddr::from_str(address).unwrap();
+            assert!(matches!(
+                rest_request.register_node(&address).await,
+                Err(RegistryError::UnknownPublicAddress)
+            ));
+        }
     }
 }
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use crate::db::DbError;
+use crate::db::alert_store::StoredAlert;
+use crate::p2p_kad::alert_message::AlertMessage;
+
+/// Reasons an alert cannot be revoked
+#[derive(Debug)]
+pub enum RevokeError {
+    /// No alert has this content id
+    NotFound,
+    /// The alert was published by another node
+    NotOrigin,
+    AlreadyRevoked,
+    /// The alert is itself a revocation
+    IsRevocation,
+    Db(DbError),
+}
+
+impl fmt::Display for RevokeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevokeError::NotFound => write!(f, "alert not found"),
+            RevokeError::NotOrigin => {
+                write!(f, "only the node that published the alert can revoke it")
+            }
+            RevokeError::AlreadyRevoked => write!(f, "alert already revoked"),
+            RevokeError::IsRevocation => write!(f, "a revocation cannot be revoked"),
+            RevokeError::Db(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for RevokeError {}
+
+impl From<DbError> for RevokeError {
+    fn from(e: DbError) -> Self {
+        RevokeError::Db(e)
+    }
+}
+
+/// Revocation of `target` published by `origin`. It only carries what peers
+/// need to route it, the topics being derived from the country and type.
+pub fn revocation(target: &StoredAlert, origin: &str) -> Result<AlertMessage, RevokeError> {
+    if target.alert.origin != origin {
+        return Err(RevokeError::NotOrigin);
+    }
+    if target.alert.revokes.is_some() {
+        return Err(RevokeError::IsRevocation);
+    }
+    if target.revoked {
+        return Err(RevokeError::AlreadyRevoked);
+    }
+    Ok(AlertMessage {
+        country: target.alert.country.clone(),
+        type_alert: target.alert.type_alert.clone(),
+        revokes: Some(target.alert_id.clone()),
+        ..AlertMessage::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(origin: &str) -> StoredAlert {
+        let alert = AlertMessage {
+            name_alert: "target".into(),
+            country: "AR".into(),
+            type_alert: "amber".into(),
+            origin: origin.into(),
+            seq: 1,
+            ..AlertMessage::default()
+        };
+        StoredAlert {
+            id: 1,
+            alert_id: alert.content_id(),
+            alert,
+            created_at: String::new(),
+            revoked: false,
+        }
+    }
+
+    #[test]
+    fn test_revocation_keeps_the_topics() {
+        let target = stored("origin-a");
+        let revocation = revocation(&target, "origin-a").unwrap();
+
+        assert_eq!(
+            revocation.revokes.as_deref(),
+            Some(target.alert_id.as_str())
+        );
+        assert_eq!(
+            (revocation.country.as_str(), revocation.type_alert.as_str()),
+            ("AR", "amber")
+        );
+        assert!(revocation.name_alert.is_empty());
+    }
+
+    #[test]
+    fn test_only_the_origin_revokes_once() {
+        let target = stored("origin-a");
+        assert!(matches!(
+            revocation(&target, "origin-b"),
+            Err(RevokeError::NotOrigin)
+        ));
+
+        let revoked = StoredAlert {
+            revoked: true,
+            ..target.clone()
+        };
+        assert!(matches!(
+            revocation(&revoked, "origin-a"),
+            Err(RevokeError::AlreadyRevoked)
+        ));
+
+        let mut nested = target;
+        nested.alert.revokes = Some("abc".into());
+        assert!(matches!(
+            revocation(&nested, "origin-a"),
+            Err(RevokeError::IsRevocation)
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use libp2p::gossipsub::{IdentTopic, PeerScoreParams, PeerScoreThresholds, TopicScoreParams};
+
+/// Score of the peer is below this once it sent three invalid alerts in a
+/// short time, its messages and control traffic are then ignored
+const GRAYLIST_THRESHOLD: f64 = -80.0;
+
+/// Penalty of the invalid alerts of a peer, squared: -20, -80, -180, ...
+const INVALID_MESSAGE_WEIGHT: f64 = -20.0;
+
+/// Kept per decay interval (1s) of the invalid message counter, which gives
+/// a half-life of about 70 seconds
+const INVALID_MESSAGE_DECAY: f64 = 0.99;
+
+/// Gossipsub scoring parameters for `topics`. Only invalid messages and a
+/// small bonus for time in mesh and first deliveries are scored: alerts are
+/// rare so missing mesh deliveries are not penalized.
+pub fn peer_score_params(topics: &[IdentTopic]) -> PeerScoreParams {
+    let topic_params = TopicScoreParams {
+        topic_weight: 1.0,
+        time_in_mesh_weight: 0.01,
+        time_in_mesh_quantum: Duration::from_secs(1),
+        time_in_mesh_cap: 100.0,
+        first_message_deliveries_weight: 1.0,
+        first_message_deliveries_decay: 0.5,
+        first_message_deliveries_cap: 10.0,
+        mesh_message_deliveries_weight: 0.0,
+        mesh_failure_penalty_weight: 0.0,
+        invalid_message_deliveries_weight: INVALID_MESSAGE_WEIGHT,
+        invalid_message_deliveries_decay: INVALID_MESSAGE_DECAY,
+        ..TopicScoreParams::default()
+    };
+
+    PeerScoreParams {
+        topics: topics
+            .iter()
+            .map(|topic| (topic.hash(), topic_params.clone()))
+            .collect(),
+        ..PeerScoreParams::default()
+    }
+}
+
+pub fn peer_score_thresholds() -> PeerScoreThresholds {
+    PeerScoreThresholds {
+        graylist_threshold: GRAYLIST_THRESHOLD,
+        ..PeerScoreThresholds::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_are_valid() {
+        let params = peer_score_params(&[IdentTopic::new("alerts/v1/global")]);
+        assert_eq!(params.topics.len(), 1);
+        assert!(params.validate().is_ok());
+        assert!(peer_score_thresholds().validate().is_ok());
+    }
+
+    #[test]
+    fn test_three_invalid_messages_graylist() {
+        let bonus = 100.0 * 0.01 + 10.0;
+        let penalty = |invalid: f64| invalid * invalid * INVALID_MESSAGE_WEIGHT;
+
+        assert!(bonus + penalty(2.0) > GRAYLIST_THRESHOLD);
+        assert!(penalty(3.0) + bonus < GRAYLIST_THRESHOLD);
+    }
+}
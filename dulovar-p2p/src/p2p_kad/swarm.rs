@@ -0,0 +1,151 @@
+use libp2p::{
+    StreamProtocol, Swarm, Transport, allow_block_list, autonat, connection_limits,
+    core::{
+        muxing::StreamMuxerBox,
+        transport::{OptionalTransport, upgrade::Version},
+    },
+    dcutr, gossipsub, identify,
+    identity::Keypair,
+    kad, noise, ping, quic, relay,
+    swarm::behaviour::toggle::Toggle,
+    tcp, websocket, yamux,
+};
+use std::error::Error;
+use std::time::Duration;
+use tokio::io;
+
+use crate::p2p_kad::alert_message::message_id;
+use crate::p2p_kad::config::P2pConfig;
+use crate::p2p_kad::my_behaviour::MyBehaviour;
+use crate::p2p_kad::scoring::{peer_score_params, peer_score_thresholds};
+use crate::p2p_kad::sync::sync_behaviour;
+use crate::p2p_kad::topics::subscriptions;
+
+/// Kademlia protocol of the dulovar network, kept apart from the IPFS DHT
+pub const KAD_PROTOCOL: StreamProtocol = StreamProtocol::new("/dulovar/kad/1");
+
+/// How long a connection without active streams stays open
+const IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Builds the swarm of a node identified by `keypair`: QUIC and
+/// TCP+noise+yamux as enabled by `config`, websocket, DNS and the relay
+/// client transport, and the behaviours enabled by `config`. Peers banned at
+/// runtime are not known here, the caller blocks them.
+pub fn build_swarm(
+    config: &P2pConfig,
+    keypair: Keypair,
+) -> Result<Swarm<MyBehaviour>, Box<dyn Error>> {
+    if !config.tcp && !config.quic && config.websocket_port.is_none() {
+        return Err("at least one of TCP, QUIC and websocket must be enabled".into());
+    }
+
+    let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_other_transport(|key| {
+            let quic_transport = if config.quic {
+                OptionalTransport::some(
+                    quic::tokio::Transport::new(quic::Config::new(key))
+                        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection))),
+                )
+            } else {
+                OptionalTransport::none()
+            };
+            let tcp_transport = if config.tcp {
+                OptionalTransport::some(
+                    tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
+                        .upgrade(Version::V1Lazy)
+                        .authenticate(noise::Config::new(key).unwrap())
+                        .multiplex(yamux::Config::default())
+                        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer))),
+                )
+            } else {
+                OptionalTransport::none()
+            };
+
+            // Always composed so websocket addresses of other peers can be
+            // dialed, listening on one depends on `config.websocket_port`
+            let websocket_transport = websocket::WsConfig::new(tcp::tokio::Transport::new(
+                tcp::Config::default().nodelay(true),
+            ))
+            .upgrade(Version::V1Lazy)
+            .authenticate(noise::Config::new(key).unwrap())
+            .multiplex(yamux::Config::default())
+            .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)));
+
+            quic_transport
+                .or_transport(websocket_transport)
+                .map(|either, _| either.into_inner())
+                .or_transport(tcp_transport)
+                .map(|either, _| either.into_inner())
+        })?
+        .with_dns()?
+        .with_relay_client(noise::Config::new, yamux::Config::default)?
+        .with_behaviour(|key, relay_client| {
+            let local_peer_id = key.public().to_peer_id();
+            let gossipsub_config = gossipsub::ConfigBuilder::default()
+                .max_transmit_size(262144)
+                // Alerts are only forwarded once `handle_swarm_event` validated them
+                .validate_messages()
+                // Republishing the same alert gives the same message id
+                .message_id_fn(message_id)
+                .build()
+                .map_err(io::Error::other)?;
+            let mut gossipsub = gossipsub::Behaviour::new(
+                gossipsub::MessageAuthenticity::Signed(key.clone()),
+                gossipsub_config,
+            )
+            .expect("Valid configuration");
+            gossipsub
+                .with_peer_score(
+                    peer_score_params(&subscriptions(config)),
+                    peer_score_thresholds(),
+                )
+                .map_err(io::Error::other)?;
+
+            let allowlist = (!config.allowed_peers.is_empty()).then(|| {
+                let mut allowlist = allow_block_list::Behaviour::default();
+                for peer in &config.allowed_peers {
+                    allowlist.allow_peer(*peer);
+                }
+                allowlist
+            });
+            let mut blocklist = allow_block_list::Behaviour::default();
+            for peer in &config.blocked_peers {
+                blocklist.block_peer(*peer);
+            }
+            let limits = connection_limits::ConnectionLimits::default()
+                .with_max_established(Some(config.max_connections))
+                .with_max_established_per_peer(Some(config.max_connections_per_peer));
+
+            Ok(MyBehaviour {
+                allowlist: Toggle::from(allowlist),
+                blocklist,
+                connection_limits: connection_limits::Behaviour::new(limits),
+                autonat: autonat::Behaviour::new(local_peer_id, autonat::Config::default()),
+                dcutr: dcutr::Behaviour::new(local_peer_id),
+                gossipsub,
+                identify: identify::Behaviour::new(identify::Config::new(
+                    "/ipfs/0.1.0".into(),
+                    key.public(),
+                )),
+                kad: kad::Behaviour::with_config(
+                    local_peer_id,
+                    kad::store::MemoryStore::new(local_peer_id),
+                    kad::Config::new(KAD_PROTOCOL),
+                ),
+                ping: ping::Behaviour::new(ping::Config::new()),
+                relay: Toggle::from(
+                    config
+                        .relay_server
+                        .then(|| relay::Behaviour::new(local_peer_id, relay::Config::default())),
+                ),
+                relay_client,
+                sync: sync_behaviour(),
+            })
+        })?
+        // Keep idle connections open between sync rounds and gossip bursts
+        .with_swarm_config(|c| c.with_idle_connection_timeout(IDLE_CONNECTION_TIMEOUT))
+        .build();
+
+    Ok(swarm)
+}
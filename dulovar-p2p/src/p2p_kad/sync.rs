@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use libp2p::{
+    StreamProtocol,
+    request_response::{self, ProtocolSupport},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbError;
+use crate::db::alert_store::AlertStore;
+use crate::p2p_kad::alert_message::AlertMessage;
+
+/// Anti-entropy protocol: peers exchange their watermarks on connection and
+/// send back the alerts the other side is missing, whoever published them.
+/// Alerts are signed by their origin, so they can be relayed by any peer.
+pub const SYNC_PROTOCOL: StreamProtocol = StreamProtocol::new("/dulovar/sync/1");
+
+/// Alerts sent per response, the requester asks again while `more` is set
+pub const SYNC_BATCH: usize = 100;
+
+/// Highest sequence number the requester has for each origin. Alerts below a
+/// watermark are assumed known, gaps below it are left to gossip.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SyncRequest {
+    pub watermarks: HashMap<String, u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SyncResponse {
+    pub alerts: Vec<AlertMessage>,
+    /// More alerts are available above the returned ones
+    pub more: bool,
+}
+
+pub type SyncBehaviour = request_response::json::Behaviour<SyncRequest, SyncResponse>;
+
+pub fn sync_behaviour() -> SyncBehaviour {
+    request_response::json::Behaviour::new(
+        [(SYNC_PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+/// Answers `request` with up to `batch` alerts above the requested
+/// watermarks, of every origin known locally. The alerts of an origin come
+/// in sequence order and origins one after the other, so the requester
+/// resumes from its raised watermarks.
+pub fn respond(
+    store: &mut AlertStore,
+    request: &SyncRequest,
+    batch: usize,
+) -> Result<SyncResponse, DbError> {
+    let mut known: Vec<(String, u64)> = store.watermarks()?.into_iter().collect();
+    known.sort();
+
+    let mut alerts = Vec::new();
+    for (origin, watermark) in known {
+        let after = request.watermarks.get(&origin).copied().unwrap_or_default();
+        if watermark <= after {
+            continue;
+        }
+        alerts.extend(store.missing(&origin, after, batch + 1 - alerts.len())?);
+        if alerts.len() > batch {
+            break;
+        }
+    }
+    let more = alerts.len() > batch;
+    alerts.truncate(batch);
+    Ok(SyncResponse { alerts, more })
+}
+
+/// Request following `response`: the stored watermarks, raised to the alerts
+/// of the response so rejected alerts are not asked for again
+pub fn next_request(
+    store: &mut AlertStore,
+    response: &SyncResponse,
+) -> Result<SyncRequest, DbError> {
+    let mut watermarks = store.watermarks()?;
+    for alert in &response.alerts {
+        let watermark = watermarks.entry(alert.origin.clone()).or_default();
+        *watermark = (*watermark).max(alert.seq);
+    }
+    Ok(SyncRequest { watermarks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_connection;
+    use libp2p::identity::Keypair;
+
+    fn store_with_alerts(publishers: &[(&Keypair, usize)]) -> (tempfile::TempDir, AlertStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = AlertStore::new(establish_connection(dir.path().join("db")).unwrap());
+        for (keypair, count) in publishers {
+            for i in 0..*count {
+                let mut alert = AlertMessage {
+                    name_alert: format!("alert {i} of {}", origin(keypair)),
+                    type_alert: "amber".into(),
+                    ..AlertMessage::default()
+                };
+                store.insert_local(&mut alert, keypair).unwrap();
+            }
+        }
+        (dir, store)
+    }
+
+    fn origin(keypair: &Keypair) -> String {
+        keypair.public().to_peer_id().to_string()
+    }
+
+    #[test]
+    fn test_respond_in_batches() {
+        let a = Keypair::generate_ed25519();
+        let (_dir, mut store) = store_with_alerts(&[(&a, 5)]);
+
+        let response = respond(&mut store, &SyncRequest::default(), 3).unwrap();
+        assert_eq!(response.alerts.len(), 3);
+        assert!(response.more);
+
+        let request = SyncRequest {
+            watermarks: HashMap::from([(origin(&a), 3)]),
+        };
+        let response = respond(&mut store, &request, 3).unwrap();
+        assert_eq!(response.alerts.len(), 2);
+        assert!(!response.more);
+
+        let request = SyncRequest {
+            watermarks: HashMap::from([(origin(&a), 5)]),
+        };
+        assert!(respond(&mut store, &request, 3).unwrap().alerts.is_empty());
+    }
+
+    #[test]
+    fn test_respond_serves_every_origin() {
+        let (a, b) = (Keypair::generate_ed25519(), Keypair::generate_ed25519());
+        let (_dir, mut store) = store_with_alerts(&[(&a, 2), (&b, 2)]);
+        let mut request = SyncRequest {
+            watermarks: HashMap::from([(origin(&a), 1)]),
+        };
+
+        let mut received = Vec::new();
+        loop {
+            let response = respond(&mut store, &request, 2).unwrap();
+            for alert in &response.alerts {
+                assert!(alert.verify_signature());
+                received.push((alert.origin.clone(), alert.seq));
+                request.watermarks.insert(alert.origin.clone(), alert.seq);
+            }
+            if !response.more {
+                break;
+            }
+        }
+        received.sort();
+
+        let mut expected = vec![(origin(&a), 2), (origin(&b), 1), (origin(&b), 2)];
+        expected.sort();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_next_request_skips_received_alerts() {
+        let a = Keypair::generate_ed25519();
+        let (_dir, mut requester) = store_with_alerts(&[(&a, 1)]);
+        let response = SyncResponse {
+            alerts: vec![AlertMessage {
+                origin: "origin-b".into(),
+                seq: 4,
+                ..AlertMessage::default()
+            }],
+            more: true,
+        };
+
+        let request = next_request(&mut requester, &response).unwrap();
+        assert_eq!(
+            request.watermarks,
+            HashMap::from([(origin(&a), 1), ("origin-b".to_string(), 4)])
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use libp2p::gossipsub::IdentTopic;
+
+use crate::p2p_kad::alert_message::AlertMessage;
+use crate::p2p_kad::config::P2pConfig;
+
+/// Topic every alert is published on
+pub const GLOBAL_TOPIC: &str = "alerts/v1/global";
+
+/// Topic of the alerts of a country, `iso` being an ISO 3166-1 alpha-2 code
+pub fn country_topic(iso: &str) -> Option<IdentTopic> {
+    let iso = iso.trim();
+    if iso.len() != 2 || !iso.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(IdentTopic::new(format!(
+        "alerts/v1/country/{}",
+        iso.to_ascii_uppercase()
+    )))
+}
+
+/// Topic of the alerts of a type, e.g. `Missing Person` gives
+/// `alerts/v1/type/missing-person`
+pub fn type_topic(alert_type: &str) -> Option<IdentTopic> {
+    let slug = alert_type
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if slug.is_empty() || slug.contains('/') {
+        return None;
+    }
+    Some(IdentTopic::new(format!("alerts/v1/type/{slug}")))
+}
+
+/// Topics `alert` is published on: the global topic, plus its country and
+/// type topics when they are valid
+pub fn topics_for(alert: &AlertMessage) -> Vec<IdentTopic> {
+    std::iter::once(IdentTopic::new(GLOBAL_TOPIC))
+        .chain(country_topic(&alert.country))
+        .chain(type_topic(&alert.type_alert))
+        .collect()
+}
+
+/// Topics a node subscribes to: the topics of its jurisdictions, else of its
+/// alert types, else the global topic. Alerts are published on a topic of
+/// each kind, so following a single kind receives each alert once.
+pub fn subscriptions(config: &P2pConfig) -> Vec<IdentTopic> {
+    let countries: Vec<IdentTopic> = config
+        .jurisdictions
+        .iter()
+        .filter_map(|iso| country_topic(iso))
+        .collect();
+    if !countries.is_empty() {
+        return countries;
+    }
+    let types: Vec<IdentTopic> = config
+        .alert_types
+        .iter()
+        .filter_map(|t| type_topic(t))
+        .collect();
+    if !types.is_empty() {
+        return types;
+    }
+    vec![IdentTopic::new(GLOBAL_TOPIC)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(topics: &[IdentTopic]) -> Vec<String> {
+        topics.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_country_topic() {
+        assert_eq!(
+            country_topic(" ar ").unwrap().to_string(),
+            "alerts/v1/country/AR"
+        );
+        assert!(country_topic("ARG").is_none());
+        assert!(country_topic("A/").is_none());
+        assert!(country_topic("").is_none());
+    }
+
+    #[test]
+    fn test_type_topic() {
+        assert_eq!(
+            type_topic("Missing  Person").unwrap().to_string(),
+            "alerts/v1/type/missing-person"
+        );
+        assert!(type_topic("  ").is_none());
+        assert!(type_topic("a/b").is_none());
+    }
+
+    #[test]
+    fn test_topics_for_alert() {
+        let alert = AlertMessage {
+            country: "UY".into(),
+            type_alert: "amber".into(),
+            ..AlertMessage::default()
+        };
+        assert_eq!(
+            names(&topics_for(&alert)),
+            [
+                "alerts/v1/global",
+                "alerts/v1/country/UY",
+                "alerts/v1/type/amber"
+            ]
+        );
+
+        // Unknown country and type still reach the global topic
+        assert_eq!(
+            names(&topics_for(&AlertMessage::default())),
+            ["alerts/v1/global"]
+        );
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        assert_eq!(
+            names(&subscriptions(&P2pConfig::default())),
+            ["alerts/v1/global"]
+        );
+
+        // Jurisdictions take precedence over alert types
+        let config = P2pConfig {
+            jurisdictions: vec!["ar".into(), "UY".into()],
+            alert_types: vec!["amber".into()],
+            ..P2pConfig::default()
+        };
+        assert_eq!(
+            names(&subscriptions(&config)),
+            ["alerts/v1/country/AR", "alerts/v1/country/UY"]
+        );
+
+        let config = P2pConfig {
+            jurisdictions: vec!["ARG".into()],
+            alert_types: vec!["amber".into(), "Missing Person".into()],
+            ..P2pConfig::default()
+        };
+        assert_eq!(
+            names(&subscriptions(&config)),
+            ["alerts/v1/type/amber", "alerts/v1/type/missing-person"]
+        );
+    }
+}
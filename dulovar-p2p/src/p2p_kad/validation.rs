@@ -0,0 +1,414 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libp2p::gossipsub::{Message, MessageAcceptance};
+
+use crate::p2p_kad::alert_message::{AlertMessage, Attachment};
+use crate::p2p_kad::topics::{country_topic, topics_for};
+
+/// Longest accepted name, url, country or type field
+const MAX_FIELD_LEN: usize = 256;
+
+/// Longest accepted description
+const MAX_DESCRIPTION_LEN: usize = 4096;
+
+/// Most attachments of an alert
+const MAX_ATTACHMENTS: usize = 16;
+
+/// Oldest accepted year of birth, 0 meaning unknown
+const MIN_YOB: i32 = 1900;
+
+/// Reasons a gossiped alert is not delivered nor forwarded
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The payload is not a JSON alert
+    Undecodable(serde_json::Error),
+    /// A required field is empty
+    MissingField(&'static str),
+    /// A field is longer than allowed
+    FieldTooLong(&'static str),
+    /// The country is set but is not an ISO 3166-1 alpha-2 code
+    InvalidCountry,
+    /// The year of birth is in the future or too old
+    InvalidYob(i32),
+    /// More attachments than allowed
+    TooManyAttachments(usize),
+    /// An attachment field is malformed
+    InvalidAttachment(&'static str),
+    /// The alert was published on a topic it does not belong to
+    WrongTopic,
+    /// The alert is not signed by the origin it claims
+    ForgedOrigin,
+    /// A revocation of an alert published by another origin
+    ForgedRevocation,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Undecodable(e) => write!(f, "undecodable alert: {e}"),
+            ValidationError::MissingField(field) => write!(f, "missing {field}"),
+            ValidationError::FieldTooLong(field) => write!(f, "{field} is too long"),
+            ValidationError::InvalidCountry => write!(f, "invalid country code"),
+            ValidationError::InvalidYob(yob) => write!(f, "invalid year of birth {yob}"),
+            ValidationError::TooManyAttachments(count) => {
+                write!(f, "{count} attachments, at most {MAX_ATTACHMENTS} allowed")
+            }
+            ValidationError::InvalidAttachment(field) => write!(f, "invalid attachment {field}"),
+            ValidationError::WrongTopic => write!(f, "alert published on the wrong topic"),
+            ValidationError::ForgedOrigin => write!(f, "alert is not signed by its origin"),
+            ValidationError::ForgedRevocation => {
+                write!(f, "revocation of an alert of another origin")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl ValidationError {
+    /// Result reported to gossipsub. Rejected messages penalize the peer
+    /// score of the sender, ignored ones do not.
+    pub fn acceptance(&self) -> MessageAcceptance {
+        match self {
+            // Topic names may differ between versions of honest nodes
+            ValidationError::WrongTopic => MessageAcceptance::Ignore,
+            _ => MessageAcceptance::Reject,
+        }
+    }
+
+    /// Short name of the error kind, used as a metric label
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ValidationError::Undecodable(_) => "undecodable",
+            ValidationError::MissingField(_) => "missing_field",
+            ValidationError::FieldTooLong(_) => "field_too_long",
+            ValidationError::InvalidCountry => "invalid_country",
+            ValidationError::InvalidYob(_) => "invalid_yob",
+            ValidationError::TooManyAttachments(_) => "too_many_attachments",
+            ValidationError::InvalidAttachment(_) => "invalid_attachment",
+            ValidationError::WrongTopic => "wrong_topic",
+            ValidationError::ForgedOrigin => "forged_origin",
+            ValidationError::ForgedRevocation => "forged_revocation",
+        }
+    }
+}
+
+/// Decodes and validates a gossiped alert
+pub fn validate_message(message: &Message) -> Result<AlertMessage, ValidationError> {
+    let alert = AlertMessage::from_bytes(&message.data).map_err(ValidationError::Undecodable)?;
+    validate_alert(&alert)?;
+
+    // Alerts of nodes that predate origins carry none
+    if !alert.origin.is_empty() && !alert.verify_signature() {
+        return Err(ValidationError::ForgedOrigin);
+    }
+    if !topics_for(&alert)
+        .iter()
+        .any(|topic| topic.hash() == message.topic)
+    {
+        return Err(ValidationError::WrongTopic);
+    }
+    Ok(alert)
+}
+
+/// Validates an alert received in a sync response, which the responding peer
+/// may relay for another origin: it must be signed by the origin it claims
+pub fn validate_synced(alert: &AlertMessage) -> Result<(), ValidationError> {
+    validate_alert(alert)?;
+    if !alert.verify_signature() {
+        return Err(ValidationError::ForgedOrigin);
+    }
+    Ok(())
+}
+
+/// Checks that a revocation comes from the origin of the alert it revokes,
+/// `target_origin` being that origin when the alert is known
+pub fn validate_revocation(
+    alert: &AlertMessage,
+    target_origin: Option<&str>,
+) -> Result<(), ValidationError> {
+    match target_origin {
+        Some(origin) if alert.revokes.is_some() && origin != alert.origin => {
+            Err(ValidationError::ForgedRevocation)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks the fields of an alert
+pub fn validate_alert(alert: &AlertMessage) -> Result<(), ValidationError> {
+    if alert.type_alert.trim().is_empty() {
+        return Err(ValidationError::MissingField("type_alert"));
+    }
+
+    for (field, value) in [
+        ("first_name", &alert.first_name),
+        ("last_name", &alert.last_name),
+        ("url_1", &alert.url_1),
+        ("url_2", &alert.url_2),
+        ("url_3", &alert.url_3),
+        ("country", &alert.country),
+        ("type_alert", &alert.type_alert),
+        ("name_alert", &alert.name_alert),
+    ] {
+        if value.len() > MAX_FIELD_LEN {
+            return Err(ValidationError::FieldTooLong(field));
+        }
+    }
+    if alert
+        .revokes
+        .as_ref()
+        .is_some_and(|revokes| revokes.len() > MAX_FIELD_LEN)
+    {
+        return Err(ValidationError::FieldTooLong("revokes"));
+    }
+    if alert.description.len() > MAX_DESCRIPTION_LEN {
+        return Err(ValidationError::FieldTooLong("description"));
+    }
+
+    if !alert.country.is_empty() && country_topic(&alert.country).is_none() {
+        return Err(ValidationError::InvalidCountry);
+    }
+    if alert.yob != 0 && !(MIN_YOB..=current_year()).contains(&alert.yob) {
+        return Err(ValidationError::InvalidYob(alert.yob));
+    }
+
+    if alert.attachments.len() > MAX_ATTACHMENTS {
+        return Err(ValidationError::TooManyAttachments(alert.attachments.len()));
+    }
+    for attachment in &alert.attachments {
+        validate_attachment(attachment)?;
+    }
+    Ok(())
+}
+
+fn validate_attachment(attachment: &Attachment) -> Result<(), ValidationError> {
+    if attachment.url.trim().is_empty() {
+        return Err(ValidationError::MissingField("attachment url"));
+    }
+    for (field, value) in [
+        ("attachment url", &attachment.url),
+        ("attachment mime_type", &attachment.mime_type),
+        ("attachment caption", &attachment.caption),
+    ] {
+        if value.len() > MAX_FIELD_LEN {
+            return Err(ValidationError::FieldTooLong(field));
+        }
+    }
+    // `type/subtype`, parameters allowed
+    let essence = attachment.mime_type.split(';').next().unwrap_or_default();
+    if !attachment.mime_type.is_empty()
+        && !essence.split_once('/').is_some_and(|(kind, subtype)| {
+            !kind.is_empty() && !subtype.is_empty() && !essence.contains(char::is_whitespace)
+        })
+    {
+        return Err(ValidationError::InvalidAttachment("mime_type"));
+    }
+    if !attachment.sha256.is_empty()
+        && (attachment.sha256.len() != 64
+            || !attachment.sha256.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(ValidationError::InvalidAttachment("sha256"));
+    }
+    Ok(())
+}
+
+fn current_year() -> i32 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    // Average Gregorian year, close enough for a plausibility check
+    1970 + (secs / 31_556_952) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::gossipsub::IdentTopic;
+    use libp2p::identity::Keypair;
+
+    fn alert() -> AlertMessage {
+        AlertMessage {
+            first_name: "Ana".into(),
+            yob: 1990,
+            country: "AR".into(),
+            type_alert: "amber".into(),
+            name_alert: "Ana missing".into(),
+            ..AlertMessage::default()
+        }
+    }
+
+    fn photo() -> Attachment {
+        Attachment {
+            url: "https://example.org/ana.jpg".into(),
+            mime_type: "image/jpeg".into(),
+            sha256: "ab".repeat(32),
+            caption: "Ana in 2024".into(),
+        }
+    }
+
+    fn message(data: Vec<u8>, topic: &str) -> Message {
+        Message {
+            source: None,
+            data,
+            sequence_number: None,
+            topic: IdentTopic::new(topic).hash(),
+        }
+    }
+
+    #[test]
+    fn test_valid_alert_is_accepted() {
+        let alert = AlertMessage {
+            attachments: vec![
+                photo(),
+                Attachment {
+                    url: "https://example.org/poster".into(),
+                    mime_type: "application/pdf; version=1.7".into(),
+                    ..Attachment::default()
+                },
+            ],
+            ..alert()
+        };
+        for topic in topics_for(&alert) {
+            let message = message(alert.to_bytes(), &topic.to_string());
+            assert_eq!(validate_message(&message).unwrap(), alert);
+        }
+    }
+
+    #[test]
+    fn test_malformed_alerts_are_rejected() {
+        let undecodable = message(b"operations".to_vec(), "alerts/v1/global");
+        let err = validate_message(&undecodable).unwrap_err();
+        assert!(matches!(err, ValidationError::Undecodable(_)));
+        assert!(matches!(err.acceptance(), MessageAcceptance::Reject));
+
+        let cases = [
+            AlertMessage {
+                type_alert: " ".into(),
+                ..alert()
+            },
+            AlertMessage {
+                first_name: "a".repeat(MAX_FIELD_LEN + 1),
+                ..alert()
+            },
+            AlertMessage {
+                description: "a".repeat(MAX_DESCRIPTION_LEN + 1),
+                ..alert()
+            },
+            AlertMessage {
+                country: "Argentina".into(),
+                ..alert()
+            },
+            AlertMessage {
+                yob: 1200,
+                ..alert()
+            },
+            AlertMessage {
+                yob: current_year() + 1,
+                ..alert()
+            },
+            AlertMessage {
+                attachments: vec![photo(); MAX_ATTACHMENTS + 1],
+                ..alert()
+            },
+            AlertMessage {
+                attachments: vec![Attachment::default()],
+                ..alert()
+            },
+            AlertMessage {
+                attachments: vec![Attachment {
+                    mime_type: "jpeg".into(),
+                    ..photo()
+                }],
+                ..alert()
+            },
+            AlertMessage {
+                attachments: vec![Attachment {
+                    sha256: "abc".into(),
+                    ..photo()
+                }],
+                ..alert()
+            },
+        ];
+        for case in cases {
+            let err = validate_alert(&case).unwrap_err();
+            assert!(
+                matches!(err.acceptance(), MessageAcceptance::Reject),
+                "{err}"
+            );
+        }
+    }
+
+    fn signed(keypair: &Keypair) -> AlertMessage {
+        let mut alert = alert();
+        alert.sign(keypair, 1);
+        alert
+    }
+
+    #[test]
+    fn test_forged_origin_is_rejected() {
+        let signed = signed(&Keypair::generate_ed25519());
+        // Relayed by any peer, the signature vouches for the origin
+        let mut relayed = message(signed.to_bytes(), "alerts/v1/global");
+        relayed.source = Some(libp2p::PeerId::random());
+        assert_eq!(validate_message(&relayed).unwrap(), signed);
+
+        let forged = AlertMessage {
+            origin: Keypair::generate_ed25519()
+                .public()
+                .to_peer_id()
+                .to_string(),
+            ..signed
+        };
+        let err = validate_message(&message(forged.to_bytes(), "alerts/v1/global")).unwrap_err();
+        assert!(matches!(err, ValidationError::ForgedOrigin));
+        assert!(matches!(err.acceptance(), MessageAcceptance::Reject));
+    }
+
+    #[test]
+    fn test_revocation_comes_from_the_origin() {
+        let revocation = AlertMessage {
+            origin: "origin-a".into(),
+            revokes: Some("target".into()),
+            ..alert()
+        };
+        assert!(validate_revocation(&revocation, Some("origin-a")).is_ok());
+        // Honoured later only if the revoked alert turns out to match
+        assert!(validate_revocation(&revocation, None).is_ok());
+        assert!(validate_revocation(&alert(), Some("origin-b")).is_ok());
+
+        let err = validate_revocation(&revocation, Some("origin-b")).unwrap_err();
+        assert!(matches!(err.acceptance(), MessageAcceptance::Reject));
+        assert_eq!(err.reason(), "forged_revocation");
+    }
+
+    #[test]
+    fn test_synced_alerts_are_signed_by_their_origin() {
+        let synced = signed(&Keypair::generate_ed25519());
+        assert!(validate_synced(&synced).is_ok());
+
+        let renumbered = AlertMessage {
+            seq: u64::MAX,
+            ..synced.clone()
+        };
+        let err = validate_synced(&renumbered).unwrap_err();
+        assert!(matches!(err, ValidationError::ForgedOrigin));
+        let unsigned = AlertMessage {
+            signature: String::new(),
+            ..synced
+        };
+        assert!(validate_synced(&unsigned).is_err());
+        assert!(validate_synced(&alert()).is_err());
+    }
+
+    #[test]
+    fn test_wrong_topic_is_ignored() {
+        let message = message(alert().to_bytes(), "alerts/v1/country/UY");
+        let err = validate_message(&message).unwrap_err();
+
+        assert!(matches!(err, ValidationError::WrongTopic));
+        assert!(matches!(err.acceptance(), MessageAcceptance::Ignore));
+    }
+}
@@ -14,6 +14,28 @@ diesel::table! {
         type_alert -> Nullable<Text>,
         name_alert -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
+        alert_id -> Nullable<Text>,
+        origin -> Nullable<Text>,
+        seq -> Nullable<BigInt>,
+        revokes -> Nullable<Text>,
+        signature -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    banned_peers (peer_id) {
+        peer_id -> Text,
+        banned_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    outbox (id) {
+        id -> Integer,
+        alert_id -> Text,
+        payload -> Binary,
+        attempts -> Integer,
+        created_at -> Timestamp,
     }
 }
 
@@ -23,9 +45,19 @@ diesel::table! {
         alert_id -> Nullable<Integer>,
         url -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
+        mime_type -> Nullable<Text>,
+        sha256 -> Nullable<Text>,
+        caption -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    seen_messages (message_id) {
+        message_id -> Text,
+        seen_at -> Timestamp,
     }
 }
 
 diesel::joinable!(photos -> alerts (alert_id));
 
-diesel::allow_tables_to_appear_in_same_query!(alerts, photos,);
+diesel::allow_tables_to_appear_in_same_query!(alerts, banned_peers, outbox, photos, seen_messages,);
@@ -0,0 +1,275 @@
+use std::error::Error;
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber, span};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::field::{MakeExt, RecordFields};
+use tracing_subscriber::fmt::format::{self, FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::{FmtContext, FormattedFields, MakeWriter};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Fields holding personal data of the people alerts are about. Their values
+/// never reach the logs, whatever the level.
+pub const PII_FIELDS: &[&str] = &[
+    "first_name",
+    "last_name",
+    "description",
+    "yob",
+    "name_alert",
+];
+
+/// Filter used when `RUST_LOG` is not set
+const DEFAULT_FILTER: &str = "info";
+
+const REDACTED: &str = "[redacted]";
+
+pub fn is_pii(field: &str) -> bool {
+    PII_FIELDS.contains(&field)
+}
+
+/// Output format of the logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl LogFormat {
+    /// Reads `DULOVAR_LOG_FORMAT`, text unless it is `json`
+    pub fn from_env() -> Self {
+        match std::env::var("DULOVAR_LOG_FORMAT") {
+            Ok(value) if value.trim().eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+/// Installs the global subscriber writing to stdout, filtered by `RUST_LOG`
+pub fn init(format: LogFormat) -> Result<(), Box<dyn Error>> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    tracing::subscriber::set_global_default(subscriber(format, filter, std::io::stdout))?;
+    Ok(())
+}
+
+/// Subscriber writing the events accepted by `filter` to `writer` in
+/// `format`, with the [`PII_FIELDS`] redacted
+pub fn subscriber<W>(
+    format: LogFormat,
+    filter: EnvFilter,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => Box::new(
+            builder
+                .fmt_fields(
+                    format::debug_fn(|writer, field, value| match field.name() {
+                        name if is_pii(name) => write!(writer, "{name}={REDACTED}"),
+                        "message" => write!(writer, "{value:?}"),
+                        name => write!(writer, "{name}={value:?}"),
+                    })
+                    .delimited(" "),
+                )
+                .finish(),
+        ),
+        LogFormat::Json => Box::new(
+            builder
+                .fmt_fields(JsonFields)
+                .event_format(JsonFormat)
+                .finish(),
+        ),
+    }
+}
+
+/// Collects fields into a JSON object, redacting the [`PII_FIELDS`]
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let value = if is_pii(field.name()) {
+            Value::from(REDACTED)
+        } else {
+            value
+        };
+        self.0.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::from(format!("{value:?}")));
+    }
+}
+
+/// Formats span fields as a JSON object, so [`JsonFormat`] can nest them
+struct JsonFields;
+
+impl<'w> FormatFields<'w> for JsonFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: Writer<'w>, fields: R) -> fmt::Result {
+        let mut map = Map::new();
+        fields.record(&mut JsonVisitor(&mut map));
+        write!(writer, "{}", Value::Object(map))
+    }
+
+    fn add_fields(
+        &self,
+        current: &'w mut FormattedFields<Self>,
+        fields: &span::Record<'_>,
+    ) -> fmt::Result {
+        let mut map: Map<String, Value> = serde_json::from_str(&current.fields).unwrap_or_default();
+        fields.record(&mut JsonVisitor(&mut map));
+        current.fields = Value::Object(map).to_string();
+        Ok(())
+    }
+}
+
+/// Writes each event as a JSON line with its level, target, fields and the
+/// fields of the spans it happened in
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+
+        let spans: Vec<Value> = ctx
+            .event_scope()
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+            .map(|span| {
+                let mut entry: Map<String, Value> = span
+                    .extensions()
+                    .get::<FormattedFields<N>>()
+                    .and_then(|formatted| serde_json::from_str(&formatted.fields).ok())
+                    .unwrap_or_default();
+                entry.insert("name".into(), Value::from(span.name()));
+                Value::Object(entry)
+            })
+            .collect();
+
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert("timestamp".into(), Value::from(timestamp));
+        line.insert("level".into(), Value::from(metadata.level().as_str()));
+        line.insert("target".into(), Value::from(metadata.target()));
+        line.insert("fields".into(), Value::Object(fields));
+        if !spans.is_empty() {
+            line.insert("spans".into(), Value::from(spans));
+        }
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Log output captured in memory
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(format: LogFormat, log: impl FnOnce()) -> String {
+        let captured = Captured::default();
+        let output = captured.clone();
+        let subscriber = subscriber(format, EnvFilter::new("info"), move || output.clone());
+        tracing::subscriber::with_default(subscriber, log);
+        String::from_utf8(captured.0.lock().unwrap().clone()).unwrap()
+    }
+
+    fn log_alert() {
+        let span = tracing::info_span!("alert", alert_id = "abc", first_name = "Ana");
+        let _entered = span.enter();
+        tracing::info!(
+            peer_id = "12D3KooW",
+            first_name = "Ana",
+            last_name = %"Pérez",
+            yob = 2015,
+            "alert received"
+        );
+    }
+
+    #[test]
+    fn test_text_output_redacts_pii() {
+        let output = capture(LogFormat::Text, log_alert);
+
+        assert!(output.contains("alert received"));
+        assert!(output.contains("peer_id=\"12D3KooW\""));
+        assert!(output.contains("alert_id=\"abc\""));
+        assert!(output.contains("first_name=[redacted]"));
+        assert!(!output.contains("Ana"));
+        assert!(!output.contains("Pérez"));
+        assert!(!output.contains("2015"));
+    }
+
+    #[test]
+    fn test_json_output_redacts_pii() {
+        let output = capture(LogFormat::Json, log_alert);
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["fields"]["message"], "alert received");
+        assert_eq!(line["fields"]["peer_id"], "12D3KooW");
+        assert_eq!(line["fields"]["first_name"], REDACTED);
+        assert_eq!(line["fields"]["last_name"], REDACTED);
+        assert_eq!(line["fields"]["yob"], REDACTED);
+        assert_eq!(line["spans"][0]["name"], "alert");
+        assert_eq!(line["spans"][0]["alert_id"], "abc");
+        assert_eq!(line["spans"][0]["first_name"], REDACTED);
+    }
+}
@@ -0,0 +1,99 @@
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::command::{self, Command};
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::event_loop::event_loop;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+
+use futures::StreamExt;
+use libp2p::identity::Keypair;
+use libp2p::kad;
+use libp2p::swarm::SwarmEvent;
+use tokio::sync::oneshot;
+use tokio::time::{Duration, sleep, timeout};
+use tokio_util::sync::CancellationToken;
+
+mod test_utils;
+use test_utils::test_node_state;
+
+#[tokio::test]
+async fn test_connected_peer_is_introspected_and_disconnected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut state = test_node_state(dir.path());
+    let mut node = build_swarm(&P2pConfig::default(), state.keypair.clone()).unwrap();
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    tokio::spawn(async move {
+        let _ = event_loop(
+            &mut receiver,
+            &mut node,
+            &mut state,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
+    });
+
+    // Answers DHT queries, so the node adds it to its routing table
+    let mut peer = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let peer_id = *peer.local_peer_id();
+    peer.behaviour_mut().kad.set_mode(Some(kad::Mode::Server));
+    peer.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let peer_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = peer.select_next_some().await {
+            break address;
+        }
+    };
+    let peer_task = tokio::spawn(async move {
+        loop {
+            if let SwarmEvent::ConnectionClosed { .. } = peer.select_next_some().await {
+                break;
+            }
+        }
+    });
+
+    let (reply, dialing) = oneshot::channel();
+    sender
+        .send(Command::Dial {
+            address: peer_addr,
+            reply,
+        })
+        .await
+        .unwrap();
+    dialing.await.unwrap().unwrap();
+
+    // Identified and added to the routing table
+    timeout(Duration::from_secs(20), async {
+        loop {
+            let (reply, status) = oneshot::channel();
+            sender.send(Command::Status { reply }).await.unwrap();
+            let status = status.await.unwrap().unwrap();
+            let (reply, peers) = oneshot::channel();
+            sender.send(Command::Peers { reply }).await.unwrap();
+            let peers = peers.await.unwrap();
+
+            let identified = peers
+                .iter()
+                .any(|(id, connected)| *id == peer_id && connected.agent_version.is_some());
+            if identified && status.kademlia_peers == 1 {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("peer was not introspected");
+
+    let (reply, disconnected) = oneshot::channel();
+    sender
+        .send(Command::Disconnect {
+            peer: peer_id,
+            reply,
+        })
+        .await
+        .unwrap();
+    assert!(disconnected.await.unwrap());
+    timeout(Duration::from_secs(20), peer_task)
+        .await
+        .expect("peer was not disconnected")
+        .unwrap();
+}
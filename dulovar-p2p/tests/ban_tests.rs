@@ -0,0 +1,127 @@
+use dulovar_p2p::db::ban_store::BanStore;
+use dulovar_p2p::db::establish_connection;
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::command::{self, Command};
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::event_loop::event_loop;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+
+use futures::StreamExt;
+use libp2p::identity::Keypair;
+use libp2p::swarm::SwarmEvent;
+use tokio::sync::oneshot;
+use tokio::time::{Duration, timeout};
+use tokio_util::sync::CancellationToken;
+
+mod test_utils;
+use test_utils::test_node_state;
+
+#[tokio::test]
+async fn test_banned_peer_is_disconnected_and_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("database.db");
+    let mut state = test_node_state(dir.path());
+
+    let mut node = build_swarm(&P2pConfig::default(), state.keypair.clone()).unwrap();
+    node.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let node_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = node.select_next_some().await {
+            break address;
+        }
+    };
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    tokio::spawn(async move {
+        let _ = event_loop(
+            &mut receiver,
+            &mut node,
+            &mut state,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
+    });
+
+    let mut peer = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let peer_id = *peer.local_peer_id();
+    peer.dial(node_addr.clone()).unwrap();
+
+    timeout(Duration::from_secs(20), async {
+        loop {
+            match peer.select_next_some().await {
+                SwarmEvent::ConnectionEstablished { .. } => {
+                    let (reply, response) = oneshot::channel();
+                    sender
+                        .send(Command::Ban {
+                            peer: peer_id,
+                            reply,
+                        })
+                        .await
+                        .unwrap();
+                    assert!(response.await.unwrap().unwrap());
+                }
+                SwarmEvent::ConnectionClosed { .. } => break,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("banned peer was not disconnected");
+
+    // Dialing again is refused once the connection is upgraded
+    peer.dial(node_addr).unwrap();
+    timeout(Duration::from_secs(20), async {
+        loop {
+            match peer.select_next_some().await {
+                SwarmEvent::ConnectionEstablished { .. } => {}
+                SwarmEvent::ConnectionClosed { .. }
+                | SwarmEvent::OutgoingConnectionError { .. } => break,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("banned peer could reconnect");
+
+    let mut bans = BanStore::new(establish_connection(&database).unwrap());
+    assert_eq!(bans.banned().unwrap(), [peer_id]);
+}
+
+#[tokio::test]
+async fn test_configured_blocks_are_not_lifted() {
+    let dir = tempfile::tempdir().unwrap();
+    let blocked = libp2p::PeerId::random();
+    let config = P2pConfig {
+        blocked_peers: vec![blocked],
+        ..P2pConfig::default()
+    };
+    let mut state = test_node_state(dir.path());
+    state.blocked_peers.insert(blocked);
+    state.bans.ban(&blocked).unwrap();
+
+    let mut node = build_swarm(&config, state.keypair.clone()).unwrap();
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    tokio::spawn(async move {
+        let _ = event_loop(
+            &mut receiver,
+            &mut node,
+            &mut state,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
+    });
+
+    let (reply, response) = oneshot::channel();
+    sender
+        .send(Command::Unban {
+            peer: blocked,
+            reply,
+        })
+        .await
+        .unwrap();
+    assert!(!response.await.unwrap().unwrap());
+
+    let mut bans = BanStore::new(establish_connection(dir.path().join("database.db")).unwrap());
+    assert_eq!(bans.banned().unwrap(), [blocked]);
+}
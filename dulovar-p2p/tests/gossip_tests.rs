@@ -0,0 +1,159 @@
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::alert_message::AlertMessage;
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::events::handle_swarm_event;
+use dulovar_p2p::p2p_kad::my_behaviour::MyBehaviourEvent;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+use dulovar_p2p::p2p_kad::topics::{GLOBAL_TOPIC, topics_for};
+
+use futures::StreamExt;
+use libp2p::{gossipsub, identity::Keypair, swarm::SwarmEvent};
+use prometheus_client::encoding::text::encode;
+use prometheus_client::registry::Registry;
+use tokio::time::{Duration, timeout};
+
+mod test_utils;
+use test_utils::test_node_state;
+
+#[tokio::test]
+async fn test_peer_sending_invalid_alerts_is_graylisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut state = test_node_state(dir.path());
+
+    let mut victim = build_swarm(&P2pConfig::default(), state.keypair.clone()).unwrap();
+    let topic = gossipsub::IdentTopic::new(GLOBAL_TOPIC);
+    victim.behaviour_mut().gossipsub.subscribe(&topic).unwrap();
+    victim
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let victim_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = victim.select_next_some().await {
+            break address;
+        }
+    };
+
+    let mut attacker = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let attacker_id = *attacker.local_peer_id();
+    attacker.dial(victim_addr).unwrap();
+
+    let valid = AlertMessage {
+        type_alert: "amber".into(),
+        ..AlertMessage::default()
+    };
+    timeout(Duration::from_secs(20), async {
+        loop {
+            tokio::select! {
+                event = victim.select_next_some() => {
+                    handle_swarm_event(event, &mut victim, &mut state).await;
+                    let score = victim.behaviour().gossipsub.peer_score(&attacker_id);
+                    if score.is_some_and(|score| score < -80.0) {
+                        break;
+                    }
+                }
+                event = attacker.select_next_some() => {
+                    // Once the victim subscription is known, publish one
+                    // valid alert followed by invalid ones
+                    if let SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(
+                        gossipsub::Event::Subscribed { .. },
+                    )) = event
+                    {
+                        let gossipsub = &mut attacker.behaviour_mut().gossipsub;
+                        gossipsub.publish(topic.clone(), valid.to_bytes()).unwrap();
+                        for i in 0..5 {
+                            gossipsub
+                                .publish(topic.clone(), format!("garbage {i}"))
+                                .unwrap();
+                        }
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("Attacker was not graylisted");
+}
+
+#[tokio::test]
+async fn test_alert_on_several_subscribed_topics_is_handled_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut state = test_node_state(dir.path());
+    let mut registry = Registry::default();
+    state.metrics = Metrics::new(&mut registry);
+    let mut feed = state.feed.subscribe();
+
+    let keypair = Keypair::generate_ed25519();
+    let mut publisher = build_swarm(&P2pConfig::default(), keypair.clone()).unwrap();
+    let mut alert = AlertMessage {
+        type_alert: "amber".into(),
+        country: "AR".into(),
+        ..AlertMessage::default()
+    };
+    alert.sign(&keypair, 1);
+    let topics = topics_for(&alert);
+
+    let mut subscriber = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    for topic in &topics {
+        subscriber
+            .behaviour_mut()
+            .gossipsub
+            .subscribe(topic)
+            .unwrap();
+    }
+    subscriber
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let subscriber_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = subscriber.select_next_some().await {
+            break address;
+        }
+    };
+    publisher.dial(subscriber_addr).unwrap();
+
+    let mut subscribed = 0;
+    let mut received = 0;
+    timeout(Duration::from_secs(20), async {
+        loop {
+            tokio::select! {
+                event = subscriber.select_next_some() => {
+                    if let SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(
+                        gossipsub::Event::Message { .. },
+                    )) = event
+                    {
+                        received += 1;
+                    }
+                    handle_swarm_event(event, &mut subscriber, &mut state).await;
+                    if received == topics.len() {
+                        break;
+                    }
+                }
+                event = publisher.select_next_some() => {
+                    if let SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(
+                        gossipsub::Event::Subscribed { .. },
+                    )) = event
+                    {
+                        subscribed += 1;
+                        if subscribed == topics.len() {
+                            for topic in &topics {
+                                publisher
+                                    .behaviour_mut()
+                                    .gossipsub
+                                    .publish(topic.clone(), alert.to_bytes())
+                                    .unwrap();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("alert was not received on every topic");
+
+    assert_eq!(feed.try_recv().unwrap(), alert);
+    assert!(feed.try_recv().is_err());
+    assert!(!state.seen.insert(&alert.content_id()).unwrap());
+    assert_eq!(state.alerts.recent(10).unwrap(), [alert]);
+    let mut body = String::new();
+    encode(&mut body, &registry).unwrap();
+    assert!(body.contains("dulovar_alerts_received_total{source=\"gossip\"} 1\n"));
+}
@@ -0,0 +1,314 @@
+use dulovar_p2p::grpc_daemon::GrpcDaemon;
+use dulovar_p2p::grpc_daemon::alert::alert_service_client::AlertServiceClient;
+use dulovar_p2p::grpc_daemon::alert::{
+    AlertRequestData, Attachment, ExportAlertsRequest, GetAlertRequest, ImportAlertsRequest,
+    ListAlertsRequest, RecordFormat, RevokeAlertRequest, SearchAlertsRequest,
+    SubscribeAlertsRequest,
+};
+use dulovar_p2p::grpc_daemon::records;
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::command;
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::event_loop::event_loop;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+
+use futures::StreamExt;
+use tokio::time::{Duration, sleep, timeout};
+use tokio_util::sync::CancellationToken;
+use tonic::transport::Channel;
+
+mod test_utils;
+use test_utils::test_node_state;
+
+fn alert(country: &str, name_alert: &str) -> AlertRequestData {
+    AlertRequestData {
+        first_name: "Ana".into(),
+        last_name: "Pérez".into(),
+        description: "last seen near the harbour".into(),
+        yob: 1990,
+        country: country.into(),
+        type_alert: "amber".into(),
+        name_alert: name_alert.into(),
+        ..AlertRequestData::default()
+    }
+}
+
+/// Runs a node and its gRPC services until the token is cancelled, and
+/// connects a client to them
+async fn start_node() -> (
+    AlertServiceClient<Channel>,
+    CancellationToken,
+    tempfile::TempDir,
+) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut state = test_node_state(dir.path());
+    let mut node = build_swarm(&P2pConfig::default(), state.keypair.clone()).unwrap();
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    let shutdown = CancellationToken::new();
+    let node_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        let _ = event_loop(&mut receiver, &mut node, &mut state, None, &node_shutdown).await;
+    });
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let grpc_shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        let daemon = GrpcDaemon::new(sender, Metrics::default());
+        daemon.run_server(addr, grpc_shutdown).await.is_ok()
+    });
+
+    let client = timeout(Duration::from_secs(10), async {
+        loop {
+            match AlertServiceClient::connect(format!("http://{addr}")).await {
+                Ok(client) => break client,
+                Err(_) => sleep(Duration::from_millis(50)).await,
+            }
+        }
+    })
+    .await
+    .expect("gRPC server did not start");
+    (client, shutdown, dir)
+}
+
+#[tokio::test]
+async fn test_alerts_are_read_followed_and_revoked() {
+    let (mut client, shutdown, _dir) = start_node().await;
+
+    let mut feed = client
+        .subscribe_alerts(SubscribeAlertsRequest {
+            country: "ar".into(),
+            type_alert: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let photo = |url: &str| Attachment {
+        url: format!("https://example.org/{url}"),
+        mime_type: "image/jpeg".into(),
+        ..Attachment::default()
+    };
+    let with_photos = AlertRequestData {
+        url_1: "https://example.org/legacy".into(),
+        attachments: vec![photo("1"), photo("2"), photo("3")],
+        ..alert("AR", "harbour 100%")
+    };
+    for submitted in [alert("UY", "other"), with_photos] {
+        let confirmations: Vec<_> = client
+            .process_and_stream(submitted)
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        assert_eq!(confirmations.len(), 2);
+    }
+
+    // Only the alert of the subscribed country is streamed
+    let streamed = timeout(Duration::from_secs(10), feed.next())
+        .await
+        .expect("alert was not streamed")
+        .unwrap()
+        .unwrap();
+    assert_eq!(streamed.data.as_ref().unwrap().name_alert, "harbour 100%");
+    assert!(!streamed.origin.is_empty());
+    let alert_id = streamed.alert_id.clone();
+
+    let record = client
+        .get_alert(GetAlertRequest {
+            alert_id: alert_id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(record.seq, 2);
+    assert!(!record.created_at.is_empty());
+    assert!(!record.revoked);
+    // The legacy url is attached last and the legacy urls hold the first
+    // three attachments
+    let data = record.data.unwrap();
+    let urls: Vec<&str> = data.attachments.iter().map(|a| a.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        [
+            "https://example.org/1",
+            "https://example.org/2",
+            "https://example.org/3",
+            "https://example.org/legacy",
+        ]
+    );
+    assert_eq!(data.attachments[0].mime_type, "image/jpeg");
+    assert_eq!(
+        (data.url_1.as_str(), data.url_3.as_str()),
+        ("https://example.org/1", "https://example.org/3")
+    );
+
+    let listed = client
+        .list_alerts(ListAlertsRequest::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .alerts;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].alert_id, alert_id);
+    let page = client
+        .list_alerts(ListAlertsRequest {
+            limit: 1,
+            offset: 1,
+            ..ListAlertsRequest::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .alerts;
+    assert_eq!(page[0].data.as_ref().unwrap().country, "UY");
+
+    let found = client
+        .search_alerts(SearchAlertsRequest {
+            query: "harbor 100".into(),
+            limit: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .matches;
+    // Both mention a harbour, only one well enough
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].alert.as_ref().unwrap().alert_id, alert_id);
+    assert!(found[0].score < 1.0);
+
+    let revocation_id = client
+        .revoke_alert(RevokeAlertRequest {
+            alert_id: alert_id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .revocation_id;
+    assert_ne!(revocation_id, alert_id);
+
+    let record = client
+        .get_alert(GetAlertRequest {
+            alert_id: alert_id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(record.revoked);
+    // Revocations are not listed
+    let listed = client
+        .list_alerts(ListAlertsRequest::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .alerts;
+    assert_eq!(listed.len(), 2);
+
+    let status = client
+        .revoke_alert(RevokeAlertRequest {
+            alert_id: alert_id.clone(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    let status = client
+        .get_alert(GetAlertRequest {
+            alert_id: "unknown".into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    shutdown.cancel();
+}
+
+#[tokio::test]
+async fn test_alerts_are_imported_and_exported() {
+    let (mut client, shutdown, _dir) = start_node().await;
+
+    let format = RecordFormat::Csv;
+    let records = [
+        records::header(format).unwrap(),
+        records::write(format, &alert("AR", "first")),
+        "not,enough".to_string(),
+        records::write(format, &alert("A1", "invalid country")),
+        records::write(format, &alert("UY", "second")),
+    ];
+    let import = |dry_run: bool| {
+        futures::stream::iter(records.clone().map(|record| ImportAlertsRequest {
+            format: format.into(),
+            dry_run,
+            record,
+        }))
+    };
+
+    let dry_run = client
+        .import_alerts(import(true))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(dry_run.dry_run);
+    assert_eq!((dry_run.imported, dry_run.failed), (2, 2));
+    let failed: Vec<u64> = dry_run
+        .results
+        .iter()
+        .filter(|result| !result.error.is_empty())
+        .map(|result| result.record)
+        .collect();
+    assert_eq!(failed, [2, 3]);
+    let listed = client
+        .list_alerts(ListAlertsRequest::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .alerts;
+    assert!(listed.is_empty());
+
+    let imported = client
+        .import_alerts(import(false))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!((imported.imported, imported.failed), (2, 2));
+
+    let exported: Vec<String> = client
+        .export_alerts(ExportAlertsRequest {
+            format: RecordFormat::JsonLines.into(),
+            ..ExportAlertsRequest::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|response| response.unwrap().record)
+        .collect()
+        .await;
+    let names: Vec<String> = exported
+        .iter()
+        .map(|record| {
+            serde_json::from_str::<AlertRequestData>(record)
+                .unwrap()
+                .name_alert
+        })
+        .collect();
+    assert_eq!(names, ["first", "second"]);
+
+    let exported: Vec<String> = client
+        .export_alerts(ExportAlertsRequest {
+            format: format.into(),
+            country: "UY".into(),
+            ..ExportAlertsRequest::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|response| response.unwrap().record)
+        .collect()
+        .await;
+    assert_eq!(exported[0], records[0]);
+    assert_eq!(exported[1..], records[4..]);
+
+    shutdown.cancel();
+}
@@ -0,0 +1,148 @@
+use dulovar_p2p::db::alert_store::AlertStore;
+use dulovar_p2p::db::establish_connection;
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::alert_message::AlertMessage;
+use dulovar_p2p::p2p_kad::command::{self, Command};
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::event_loop::event_loop;
+use dulovar_p2p::p2p_kad::my_behaviour::MyBehaviourEvent;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+use dulovar_p2p::p2p_kad::topics::subscriptions;
+
+use futures::StreamExt;
+use libp2p::gossipsub;
+use libp2p::identity::Keypair;
+use libp2p::swarm::SwarmEvent;
+use prometheus_client::encoding::text::encode;
+use prometheus_client::registry::Registry;
+use tokio::sync::oneshot;
+use tokio::time::{Duration, timeout};
+use tokio_util::sync::CancellationToken;
+
+mod test_utils;
+use test_utils::test_node_state;
+
+#[tokio::test]
+async fn test_alert_without_peers_is_gossiped_once_a_peer_joins() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("database.db");
+    let mut state = test_node_state(dir.path());
+    let config = P2pConfig::default();
+    let mut node = build_swarm(&config, state.keypair.clone()).unwrap();
+    for topic in subscriptions(&config) {
+        node.behaviour_mut().gossipsub.subscribe(&topic).unwrap();
+    }
+    node.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let node_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = node.select_next_some().await {
+            break address;
+        }
+    };
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    tokio::spawn(async move {
+        let _ = event_loop(
+            &mut receiver,
+            &mut node,
+            &mut state,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
+    });
+
+    // Stored while the node has no peer
+    let (reply, stored) = oneshot::channel();
+    let alert = AlertMessage {
+        name_alert: "outbox".into(),
+        type_alert: "amber".into(),
+        country: "AR".into(),
+        ..AlertMessage::default()
+    };
+    sender
+        .send(Command::Publish {
+            alert: Box::new(alert),
+            reply,
+        })
+        .await
+        .unwrap();
+    let alert_id = stored.await.unwrap().unwrap();
+    let mut alerts = AlertStore::new(establish_connection(&database).unwrap());
+    assert_eq!(alerts.outbox_len().unwrap(), 1);
+
+    let mut peer = build_swarm(&config, Keypair::generate_ed25519()).unwrap();
+    for topic in subscriptions(&config) {
+        peer.behaviour_mut().gossipsub.subscribe(&topic).unwrap();
+    }
+    peer.dial(node_addr).unwrap();
+
+    let received = timeout(Duration::from_secs(20), async {
+        loop {
+            if let SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                message,
+                ..
+            })) = peer.select_next_some().await
+            {
+                break AlertMessage::from_bytes(&message.data).unwrap();
+            }
+        }
+    })
+    .await
+    .expect("alert was not gossiped from the outbox");
+
+    assert_eq!(received.name_alert, "outbox");
+    assert_eq!(received.seq, 1);
+    assert_eq!(received.content_id(), alert_id);
+    assert_eq!(alerts.outbox_len().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_alert_too_large_to_publish_is_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("database.db");
+    let mut state = test_node_state(dir.path());
+    let mut registry = Registry::default();
+    state.metrics = Metrics::new(&mut registry);
+    let mut node = build_swarm(&P2pConfig::default(), state.keypair.clone()).unwrap();
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    tokio::spawn(async move {
+        let _ = event_loop(
+            &mut receiver,
+            &mut node,
+            &mut state,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
+    });
+
+    let (reply, stored) = oneshot::channel();
+    let alert = AlertMessage {
+        description: "x".repeat(300_000),
+        type_alert: "amber".into(),
+        ..AlertMessage::default()
+    };
+    sender
+        .send(Command::Publish {
+            alert: Box::new(alert),
+            reply,
+        })
+        .await
+        .unwrap();
+    stored.await.unwrap().unwrap();
+
+    // Not kept for retries, unlike alerts waiting for peers
+    let mut alerts = AlertStore::new(establish_connection(&database).unwrap());
+    timeout(Duration::from_secs(5), async {
+        while alerts.outbox_len().unwrap() > 0 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("alert too large to publish was kept in the outbox");
+    assert_eq!(alerts.recent(1).unwrap().len(), 1);
+    let mut body = String::new();
+    encode(&mut body, &registry).unwrap();
+    assert!(body.contains("dulovar_alerts_published_total 0\n"));
+    assert!(body.contains("dulovar_alerts_publish_failed_total 1\n"));
+}
@@ -0,0 +1,104 @@
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::my_behaviour::{MyBehaviour, MyBehaviourEvent};
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+
+use futures::StreamExt;
+use libp2p::{Multiaddr, Swarm, identity::Keypair, multiaddr::Protocol, relay, swarm::SwarmEvent};
+use tokio::time::{Duration, timeout};
+
+fn relay_swarm() -> Swarm<MyBehaviour> {
+    build_swarm(
+        &P2pConfig {
+            relay_server: true,
+            ..P2pConfig::default()
+        },
+        Keypair::generate_ed25519(),
+    )
+    .unwrap()
+}
+
+/// Starts the relay in its own task and returns its dialable address
+async fn spawn_relay() -> Multiaddr {
+    let mut relay = relay_swarm();
+    relay
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+
+    let listen_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = relay.select_next_some().await {
+            break address;
+        }
+    };
+    // The relay hands its external addresses out in reservations
+    relay.add_external_address(listen_addr.clone());
+    let relay_addr = listen_addr.with(Protocol::P2p(*relay.local_peer_id()));
+
+    tokio::spawn(async move {
+        loop {
+            relay.select_next_some().await;
+        }
+    });
+    relay_addr
+}
+
+/// The relay, listener and dialer are separate swarms, each with its own
+/// identity and transports, talking over loopback TCP sockets: what the relay
+/// and DCUtR protocols see is the same as across processes. Running the
+/// `dulovar-p2p` binary instead would also start its gRPC server on a fixed
+/// port and query the registry, and would not give the relay the external
+/// address it hands out in reservations.
+#[tokio::test]
+async fn test_connection_through_relay() {
+    let relay_addr = spawn_relay().await;
+
+    // The listener only listens through the relay, it has no direct address
+    let mut listener = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let listener_id = *listener.local_peer_id();
+    listener
+        .listen_on(relay_addr.clone().with(Protocol::P2pCircuit))
+        .unwrap();
+
+    let reserved = timeout(Duration::from_secs(10), async {
+        loop {
+            if let SwarmEvent::Behaviour(MyBehaviourEvent::RelayClient(
+                relay::client::Event::ReservationReqAccepted { .. },
+            )) = listener.select_next_some().await
+            {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(reserved.is_ok(), "Reservation on the relay timed out");
+
+    tokio::spawn(async move {
+        loop {
+            listener.select_next_some().await;
+        }
+    });
+
+    let mut dialer = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let circuit_addr = relay_addr
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(listener_id));
+    dialer.dial(circuit_addr).unwrap();
+
+    let connected = timeout(Duration::from_secs(10), async {
+        loop {
+            if let SwarmEvent::ConnectionEstablished {
+                peer_id, endpoint, ..
+            } = dialer.select_next_some().await
+                && peer_id == listener_id
+            {
+                break endpoint.get_remote_address().clone();
+            }
+        }
+    })
+    .await
+    .expect("Relayed connection timed out");
+
+    assert!(
+        connected.iter().any(|p| p == Protocol::P2pCircuit),
+        "Expected a relayed connection, got {connected}"
+    );
+}
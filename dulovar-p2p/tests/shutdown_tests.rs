@@ -0,0 +1,150 @@
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::alert_message::AlertMessage;
+use dulovar_p2p::p2p_kad::command::{self, Command};
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::event_loop::event_loop;
+use dulovar_p2p::p2p_kad::identity::load_or_create;
+use dulovar_p2p::p2p_kad::peer_store::PeerStore;
+use dulovar_p2p::p2p_kad::revocation::RevokeError;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+use dulovar_p2p::p2p_kad::topics::subscriptions;
+
+use libp2p::identity::Keypair;
+use std::collections::HashMap;
+use tokio::sync::oneshot;
+use tokio::time::{Duration, timeout};
+use tokio_util::sync::CancellationToken;
+
+mod test_utils;
+use test_utils::test_node_state;
+
+#[tokio::test]
+async fn test_queued_alerts_are_stored_on_shutdown() {
+    let dir = tempfile::tempdir().unwrap();
+    let peers = dir.path().join("peers.json");
+    let mut state = test_node_state(dir.path());
+    let config = P2pConfig::default();
+    let mut node = build_swarm(&config, state.keypair.clone()).unwrap();
+    for topic in subscriptions(&config) {
+        node.behaviour_mut().gossipsub.subscribe(&topic).unwrap();
+    }
+    let origin = node.local_peer_id().to_string();
+    let known: libp2p::Multiaddr = "/ip4/203.0.113.7/tcp/4001".parse().unwrap();
+    state
+        .peer_store
+        .record_connection(&libp2p::PeerId::random(), [&known]);
+
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    let mut replies = Vec::new();
+    for i in 0..5 {
+        let alert = AlertMessage {
+            name_alert: format!("alert {i}"),
+            type_alert: "amber".into(),
+            country: "AR".into(),
+            ..AlertMessage::default()
+        };
+        let (reply, stored) = oneshot::channel();
+        sender
+            .send(Command::Publish {
+                alert: Box::new(alert),
+                reply,
+            })
+            .await
+            .unwrap();
+        replies.push(stored);
+    }
+    // Cancelled before the node ever polls the queue
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+
+    timeout(
+        Duration::from_secs(10),
+        event_loop(&mut receiver, &mut node, &mut state, None, &shutdown),
+    )
+    .await
+    .expect("node did not shut down")
+    .unwrap();
+
+    for stored in replies {
+        stored.await.unwrap().unwrap();
+    }
+    assert_eq!(
+        state.alerts.watermarks().unwrap(),
+        HashMap::from([(origin, 5)])
+    );
+    // Without peers they are gossiped by the next run
+    assert_eq!(state.alerts.outbox_len().unwrap(), 5);
+    assert_eq!(node.behaviour().gossipsub.topics().count(), 0);
+    // Peers recorded since the last flush are saved
+    assert_eq!(PeerStore::load(&peers).len(), 1);
+    // Nothing is accepted once the node is gone
+    let (reply, _) = oneshot::channel();
+    let alert = AlertMessage::default();
+    assert!(
+        sender
+            .send(Command::Publish {
+                alert: Box::new(alert),
+                reply,
+            })
+            .await
+            .is_err()
+    );
+}
+
+/// Runs a node of `keypair` on the database in `dir` until the queued
+/// commands are handled
+async fn run_node(dir: &std::path::Path, keypair: Keypair, command: Command) {
+    let mut state = test_node_state(dir);
+    state.keypair = keypair.clone();
+    let mut node = build_swarm(&P2pConfig::default(), keypair).unwrap();
+    let (sender, mut receiver) = command::channel(16, Metrics::default());
+    sender.send(command).await.unwrap();
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+
+    timeout(
+        Duration::from_secs(10),
+        event_loop(&mut receiver, &mut node, &mut state, None, &shutdown),
+    )
+    .await
+    .expect("node did not shut down")
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_alert_is_revoked_after_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let identity = dir.path().join("identity.key");
+
+    let (reply, stored) = oneshot::channel();
+    let alert = AlertMessage {
+        name_alert: "before restart".into(),
+        type_alert: "amber".into(),
+        country: "AR".into(),
+        ..AlertMessage::default()
+    };
+    let publish = Command::Publish {
+        alert: Box::new(alert),
+        reply,
+    };
+    run_node(dir.path(), load_or_create(&identity).unwrap(), publish).await;
+    let alert_id = stored.await.unwrap().unwrap();
+
+    // A node with another identity is not the origin
+    let (reply, refused) = oneshot::channel();
+    let revoke = Command::Revoke {
+        alert_id: alert_id.clone(),
+        reply,
+    };
+    run_node(dir.path(), Keypair::generate_ed25519(), revoke).await;
+    assert!(matches!(
+        refused.await.unwrap(),
+        Err(RevokeError::NotOrigin)
+    ));
+
+    // Restarted with the identity it published the alert with
+    let (reply, revoked) = oneshot::channel();
+    let revoke = Command::Revoke { alert_id, reply };
+    run_node(dir.path(), load_or_create(&identity).unwrap(), revoke).await;
+    assert!(revoked.await.unwrap().is_ok());
+}
@@ -0,0 +1,92 @@
+use dulovar_p2p::p2p_kad::alert_message::AlertMessage;
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::events::handle_swarm_event;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+
+use futures::StreamExt;
+use libp2p::identity::Keypair;
+use libp2p::swarm::SwarmEvent;
+use tokio::time::{Duration, timeout};
+
+mod test_utils;
+use test_utils::test_node_state;
+
+#[tokio::test]
+async fn test_reconnecting_peer_receives_missed_alerts() {
+    let online_dir = tempfile::tempdir().unwrap();
+    let offline_dir = tempfile::tempdir().unwrap();
+    let mut online_state = test_node_state(online_dir.path());
+    let mut offline_state = test_node_state(offline_dir.path());
+
+    let mut online = build_swarm(&P2pConfig::default(), online_state.keypair.clone()).unwrap();
+    let origin = online.local_peer_id().to_string();
+    // More alerts than fit in one response
+    for i in 0..150 {
+        let mut alert = AlertMessage {
+            name_alert: format!("alert {i}"),
+            type_alert: "amber".into(),
+            country: "AR".into(),
+            ..AlertMessage::default()
+        };
+        online_state
+            .alerts
+            .insert_local(&mut alert, &online_state.keypair)
+            .unwrap();
+    }
+    // Alerts of other origins are relayed when signed by their origin
+    let other = Keypair::generate_ed25519();
+    let other_origin = other.public().to_peer_id().to_string();
+    let mut relayed = AlertMessage {
+        name_alert: "relayed".into(),
+        type_alert: "amber".into(),
+        ..AlertMessage::default()
+    };
+    relayed.sign(&other, 1);
+    online_state.alerts.insert(&relayed).unwrap();
+    let forged = AlertMessage {
+        name_alert: "forged".into(),
+        seq: 2,
+        ..relayed.clone()
+    };
+    online_state.alerts.insert(&forged).unwrap();
+    online
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let online_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = online.select_next_some().await {
+            break address;
+        }
+    };
+
+    let mut offline = build_swarm(&P2pConfig::default(), offline_state.keypair.clone()).unwrap();
+    offline.dial(online_addr).unwrap();
+
+    timeout(Duration::from_secs(20), async {
+        loop {
+            tokio::select! {
+                event = online.select_next_some() => {
+                    handle_swarm_event(event, &mut online, &mut online_state).await;
+                }
+                event = offline.select_next_some() => {
+                    handle_swarm_event(event, &mut offline, &mut offline_state).await;
+                    let watermarks = offline_state.alerts.watermarks().unwrap();
+                    if watermarks.get(&origin) == Some(&150)
+                        && watermarks.contains_key(&other_origin)
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("missed alerts were not synced");
+
+    let synced = offline_state.alerts.missing(&origin, 0, 1000).unwrap();
+    assert_eq!(synced.len(), 150);
+    assert!(synced.iter().all(|alert| alert.origin == origin));
+    assert!(!offline_state.seen.insert(&synced[0].content_id()).unwrap());
+    // The forged alert was rejected
+    let relayed_synced = offline_state.alerts.missing(&other_origin, 0, 10).unwrap();
+    assert_eq!(relayed_synced, [relayed]);
+}
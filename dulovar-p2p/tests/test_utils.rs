@@ -1,3 +1,16 @@
+// Shared by several test crates, each using part of it
+#![allow(dead_code)]
+
+use dulovar_p2p::db::alert_store::AlertStore;
+use dulovar_p2p::db::ban_store::BanStore;
+use dulovar_p2p::db::establish_connection;
+use dulovar_p2p::db::seen_cache::SeenCache;
+use dulovar_p2p::metrics::Metrics;
+use dulovar_p2p::p2p_kad::http_client::HttpClientConfig;
+use dulovar_p2p::p2p_kad::identity::load_or_create;
+use dulovar_p2p::p2p_kad::node_state::NodeState;
+use dulovar_p2p::p2p_kad::peer_store::PeerStore;
+use dulovar_p2p::p2p_kad::rest_request::RestRequest;
 use futures::prelude::*;
 use libp2p::{
     Multiaddr, PeerId, Transport,
@@ -8,9 +21,26 @@ use libp2p::{
     swarm::{NetworkBehaviour, SwarmEvent},
     tcp, yamux,
 };
+use std::path::Path;
+use std::sync::Arc;
 use std::{error::Error, time::Duration};
 use tokio::time::timeout;
 
+/// Node state with its database, peer store and identity in `dir`
+pub fn test_node_state(dir: &Path) -> NodeState {
+    let database = dir.join("database.db");
+    NodeState::new(
+        load_or_create(&dir.join("identity.key")).unwrap(),
+        PeerStore::load(dir.join("peers.json")),
+        Arc::new(RestRequest::new(HttpClientConfig::default()).unwrap()),
+        SeenCache::new(establish_connection(&database).unwrap()).unwrap(),
+        AlertStore::new(establish_connection(&database).unwrap()),
+        BanStore::new(establish_connection(&database).unwrap()),
+        0,
+        Metrics::default(),
+    )
+}
+
 /// Test behaviour combining gossipsub, identify, and ping protocols
 #[derive(NetworkBehaviour)]
 pub struct TestBehaviour {
@@ -143,6 +173,7 @@ impl TestMessage {
 }
 
 /// Helper to collect events from a swarm for testing
+#[derive(Default)]
 pub struct EventCollector {
     pub listen_addrs: Vec<Multiaddr>,
     pub gossipsub_messages: Vec<TestMessage>,
@@ -152,12 +183,7 @@ pub struct EventCollector {
 
 impl EventCollector {
     pub fn new() -> Self {
-        Self {
-            listen_addrs: Vec::new(),
-            gossipsub_messages: Vec::new(),
-            identify_events: Vec::new(),
-            ping_events: Vec::new(),
-        }
+        Self::default()
     }
 
     pub fn handle_event(&mut self, event: SwarmEvent<TestBehaviourEvent>) {
@@ -0,0 +1,106 @@
+use dulovar_p2p::p2p_kad::config::P2pConfig;
+use dulovar_p2p::p2p_kad::my_behaviour::MyBehaviour;
+use dulovar_p2p::p2p_kad::swarm::build_swarm;
+
+use futures::StreamExt;
+use libp2p::{Multiaddr, Swarm, identity::Keypair, multiaddr::Protocol, swarm::SwarmEvent};
+use tokio::time::{Duration, timeout};
+
+/// Listens on `addr` in its own task and returns the address it got
+async fn spawn_listener(mut swarm: Swarm<MyBehaviour>, addr: &str) -> Multiaddr {
+    swarm.listen_on(addr.parse().unwrap()).unwrap();
+    let listen_addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+            break address;
+        }
+    };
+    let listen_addr = listen_addr.with(Protocol::P2p(*swarm.local_peer_id()));
+
+    tokio::spawn(async move {
+        loop {
+            swarm.select_next_some().await;
+        }
+    });
+    listen_addr
+}
+
+/// Dials `addr` and returns the remote address of the established connection
+async fn connect(mut dialer: Swarm<MyBehaviour>, addr: Multiaddr) -> Multiaddr {
+    dialer.dial(addr).unwrap();
+    timeout(Duration::from_secs(10), async {
+        loop {
+            match dialer.select_next_some().await {
+                SwarmEvent::ConnectionEstablished { endpoint, .. } => {
+                    break endpoint.get_remote_address().clone();
+                }
+                SwarmEvent::OutgoingConnectionError { error, .. } => {
+                    panic!("Dial failed: {error}")
+                }
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("Connection timed out")
+}
+
+#[tokio::test]
+async fn test_connection_over_quic() {
+    let listener = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let addr = spawn_listener(listener, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+
+    let dialer = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let remote = connect(dialer, addr).await;
+
+    assert!(remote.iter().any(|p| p == Protocol::QuicV1));
+}
+
+#[tokio::test]
+async fn test_quic_disabled_cannot_dial_quic() {
+    let listener = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let addr = spawn_listener(listener, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+
+    let mut dialer = build_swarm(
+        &P2pConfig {
+            quic: false,
+            ..P2pConfig::default()
+        },
+        Keypair::generate_ed25519(),
+    )
+    .unwrap();
+    dialer.dial(addr).unwrap();
+
+    let failed = timeout(Duration::from_secs(10), async {
+        loop {
+            match dialer.select_next_some().await {
+                SwarmEvent::OutgoingConnectionError { .. } => break true,
+                SwarmEvent::ConnectionEstablished { .. } => break false,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert!(failed, "QUIC address dialed with QUIC disabled");
+}
+
+#[tokio::test]
+async fn test_connection_over_websocket() {
+    let listener = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let addr = spawn_listener(listener, "/ip4/127.0.0.1/tcp/0/ws").await;
+
+    let dialer = build_swarm(&P2pConfig::default(), Keypair::generate_ed25519()).unwrap();
+    let remote = connect(dialer, addr).await;
+
+    assert!(remote.iter().any(|p| matches!(p, Protocol::Ws(_))));
+}
+
+#[test]
+fn test_no_transport_enabled_is_an_error() {
+    let config = P2pConfig {
+        tcp: false,
+        quic: false,
+        ..P2pConfig::default()
+    };
+    assert!(build_swarm(&config, Keypair::generate_ed25519()).is_err());
+}
@@ -0,0 +1,2 @@
+-- Migration number: 0002 	 2026-10-18T09:12:04.310Z
+ALTER TABLE nodes ADD COLUMN last_seen DATETIME;
@@ -0,0 +1,7 @@
+-- Migration number: 0003 	 2026-10-18T11:40:27.552Z
+CREATE TABLE rate_limits (
+  key TEXT NOT NULL,
+  window_start INTEGER NOT NULL,
+  count INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (key, window_start)
+);
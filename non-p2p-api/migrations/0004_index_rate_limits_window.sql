@@ -0,0 +1,3 @@
+-- Migration number: 0004 	 2026-10-19T10:00:00.000Z
+-- Expired counters are pruned across all keys by window start
+CREATE INDEX IF NOT EXISTS rate_limits_window_start ON rate_limits (window_start);
@@ -0,0 +1,69 @@
+use worker::{Request, RouteContext};
+
+use crate::error::ApiError;
+
+/// Worker secret holding the token expected on admin routes
+const ADMIN_TOKEN_SECRET: &str = "ADMIN_TOKEN";
+
+/// Extracts the token from an `Authorization: Bearer <token>` header value
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    let token = token.trim();
+
+    if scheme.eq_ignore_ascii_case("bearer") && !token.is_empty() {
+        Some(token)
+    } else {
+        None
+    }
+}
+
+/// Compares two tokens without returning early on the first differing byte
+pub fn tokens_match(expected: &str, provided: &str) -> bool {
+    let (expected, provided) = (expected.as_bytes(), provided.as_bytes());
+    if expected.len() != provided.len() {
+        return false;
+    }
+
+    expected
+        .iter()
+        .zip(provided)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// Checks that the request carries the admin bearer token
+pub fn require_admin(req: &Request, ctx: &RouteContext<()>) -> Result<(), ApiError> {
+    let expected = ctx
+        .secret(ADMIN_TOKEN_SECRET)
+        .map_err(|_| ApiError::Unavailable("Admin API is not configured"))?
+        .to_string();
+
+    let header = req.headers().get("Authorization")?.unwrap_or_default();
+
+    match bearer_token(&header) {
+        Some(token) if tokens_match(&expected, token) => Ok(()),
+        _ => Err(ApiError::Unauthorized),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc123"), Some("abc123"));
+        assert_eq!(bearer_token("bearer  abc123 "), Some("abc123"));
+        assert_eq!(bearer_token("Basic abc123"), None);
+        assert_eq!(bearer_token("Bearer "), None);
+        assert_eq!(bearer_token(""), None);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("secret", ""));
+    }
+}
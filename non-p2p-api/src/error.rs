@@ -0,0 +1,109 @@
+use serde::Serialize;
+use worker::Response;
+
+/// Error returned to API clients as a JSON body
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound(&'static str),
+    Conflict(String),
+    TooManyRequests { retry_after: u64 },
+    Unavailable(&'static str),
+    Internal(worker::Error),
+}
+
+/// JSON body of an error response
+#[derive(Serialize, Debug)]
+struct ErrorBody<'a> {
+    error: &'a str,
+}
+
+impl ApiError {
+    /// HTTP status code of the error
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) => 400,
+            ApiError::Unauthorized => 401,
+            ApiError::NotFound(_) => 404,
+            ApiError::Conflict(_) => 409,
+            ApiError::TooManyRequests { .. } => 429,
+            ApiError::Unavailable(_) => 503,
+            ApiError::Internal(_) => 500,
+        }
+    }
+
+    /// Message sent to the client, internal details are not exposed
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::BadRequest(msg) | ApiError::Conflict(msg) => msg.clone(),
+            ApiError::Unauthorized => "Unauthorized".to_string(),
+            ApiError::TooManyRequests { .. } => "Too many requests".to_string(),
+            ApiError::NotFound(msg) | ApiError::Unavailable(msg) => msg.to_string(),
+            ApiError::Internal(_) => "Internal server error".to_string(),
+        }
+    }
+
+    /// Builds the JSON error response
+    pub fn into_response(self) -> Result<Response, worker::Error> {
+        if let ApiError::Internal(e) = &self {
+            worker::console_error!("internal error: {e}");
+        }
+
+        let message = self.message();
+        let mut response =
+            Response::from_json(&ErrorBody { error: &message })?.with_status(self.status());
+
+        if let ApiError::TooManyRequests { retry_after } = self {
+            response
+                .headers_mut()
+                .set("Retry-After", &retry_after.to_string())?;
+        }
+        Ok(response)
+    }
+}
+
+impl From<worker::Error> for ApiError {
+    fn from(e: worker::Error) -> Self {
+        match e {
+            e if e.to_string().contains("UNIQUE constraint failed") => {
+                ApiError::Conflict("Address is already registered".to_string())
+            }
+            e => ApiError::Internal(e),
+        }
+    }
+}
+
+/// Turns a handler result into the worker response
+pub fn respond(result: Result<Response, ApiError>) -> Result<Response, worker::Error> {
+    result.or_else(ApiError::into_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_status() {
+        assert_eq!(ApiError::BadRequest("x".into()).status(), 400);
+        assert_eq!(ApiError::Unauthorized.status(), 401);
+        assert_eq!(ApiError::NotFound("x").status(), 404);
+        assert_eq!(ApiError::Conflict("x".into()).status(), 409);
+        assert_eq!(ApiError::TooManyRequests { retry_after: 1 }.status(), 429);
+        assert_eq!(ApiError::Internal(worker::Error::BodyUsed).status(), 500);
+    }
+
+    #[test]
+    fn test_internal_error_message_is_generic() {
+        let err = ApiError::Internal(worker::Error::RustError("D1 exploded".into()));
+        assert_eq!(err.message(), "Internal server error");
+    }
+
+    #[test]
+    fn test_unique_violation_is_conflict() {
+        let err = ApiError::from(worker::Error::RustError(
+            "D1_ERROR: UNIQUE constraint failed: nodes.address".into(),
+        ));
+        assert_eq!(err.status(), 409);
+    }
+}
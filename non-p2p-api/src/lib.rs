@@ -1,5 +1,9 @@
+mod auth;
+mod error;
 mod models;
-use models::{create_node, select_nodes};
+mod rate_limit;
+use error::respond;
+use models::{create_node, delete_node, select_node, select_nodes, update_node};
 use worker::*;
 
 #[event(fetch)]
@@ -7,11 +11,21 @@ async fn fetch(req: Request, env: Env, _ctx: Context) -> Result<Response> {
     let router = Router::new();
 
     router
-        .post_async(
-            "/nodes",
-            |req, ctx| async move { create_node(req, ctx).await },
-        )
-        .get_async("/nodes", |_req, ctx| async move { select_nodes(ctx).await })
+        .post_async("/nodes", |req, ctx| async move {
+            respond(create_node(req, ctx).await)
+        })
+        .get_async("/nodes", |req, ctx| async move {
+            respond(select_nodes(req, ctx).await)
+        })
+        .get_async("/nodes/:id", |req, ctx| async move {
+            respond(select_node(req, ctx).await)
+        })
+        .patch_async("/nodes/:id", |req, ctx| async move {
+            respond(update_node(req, ctx).await)
+        })
+        .delete_async("/nodes/:id", |req, ctx| async move {
+            respond(delete_node(req, ctx).await)
+        })
         .get_async("/", |_req, _ctx| async move {
             Response::error("Not found", 404)
         })
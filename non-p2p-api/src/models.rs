@@ -1,5 +1,14 @@
-use serde::{Deserialize, Serialize};
-use worker::{Request, Response, RouteContext};
+use multiaddr::{Multiaddr, Protocol};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::net::IpAddr;
+use std::str::FromStr;
+use worker::{wasm_bindgen::JsValue, Request, Response, RouteContext, Url};
+
+use crate::auth::require_admin;
+use crate::error::ApiError;
+use crate::rate_limit::{
+    client_ip, d1_limiter, now_secs, LIST_PER_IP, REGISTER_PER_IP, REGISTER_PER_PEER,
+};
 
 /// Input structure for creating nodes via API
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -22,38 +31,277 @@ impl NodeInput {
     }
 }
 
+/// Stored node as returned by the admin routes
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct Node {
+    pub id: i64,
+    pub address: String,
+    pub valid: i8,
+    pub master: i8,
+    pub last_seen: Option<String>,
+}
+
+/// Partial update of a node, absent fields are left untouched
+#[derive(Deserialize, Debug, Clone, Default, Serialize)]
+pub struct NodeUpdate {
+    pub address: Option<String>,
+    pub valid: Option<i8>,
+    pub master: Option<i8>,
+}
+
+impl NodeUpdate {
+    /// Returns true when the update would not change any column
+    pub fn is_empty(&self) -> bool {
+        self.address.is_none() && self.valid.is_none() && self.master.is_none()
+    }
+}
+
+/// Limit/offset window applied to node listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl Pagination {
+    pub const DEFAULT_LIMIT: u32 = 100;
+    pub const MAX_LIMIT: u32 = 500;
+
+    /// Reads `limit` and `offset` from the query string, clamping the limit
+    /// and falling back to the defaults for missing or invalid values
+    pub fn from_url(url: &Url) -> Self {
+        let mut pagination = Self {
+            limit: Self::DEFAULT_LIMIT,
+            offset: 0,
+        };
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "limit" => {
+                    if let Ok(limit) = value.parse::<u32>() {
+                        pagination.limit = limit.clamp(1, Self::MAX_LIMIT);
+                    }
+                }
+                "offset" => {
+                    if let Ok(offset) = value.parse::<u32>() {
+                        pagination.offset = offset;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        pagination
+    }
+}
+
+/// Checks that a submitted address is a dialable multiaddr and returns its
+/// canonical string form
+pub fn validate_address(address: &str) -> Result<String, ApiError> {
+    let address = address.trim();
+    if address.is_empty() {
+        return Err(ApiError::BadRequest("Address is required".to_string()));
+    }
+    if address.len() > MAX_ADDRESS_LEN {
+        return Err(ApiError::BadRequest("Address is too long".to_string()));
+    }
+    // Nodes that predate multiaddr registration send their bare public IP
+    if let Ok(ip) = IpAddr::from_str(address) {
+        return Ok(ip.to_string());
+    }
+
+    let multiaddr = Multiaddr::from_str(address)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid multiaddr: {e}")))?;
+
+    match multiaddr.iter().next() {
+        Some(
+            Protocol::Ip4(_)
+            | Protocol::Ip6(_)
+            | Protocol::Dns(_)
+            | Protocol::Dns4(_)
+            | Protocol::Dns6(_),
+        ) => Ok(multiaddr.to_string()),
+        _ => Err(ApiError::BadRequest(
+            "Address must start with an ip4, ip6 or dns component".to_string(),
+        )),
+    }
+}
+
+const MAX_ADDRESS_LEN: usize = 512;
+
+/// Peer id announced at the end of an address, if any
+pub fn address_peer_id(address: &str) -> Option<String> {
+    match Multiaddr::from_str(address).ok()?.iter().last()? {
+        Protocol::P2p(peer_id) => Some(peer_id.to_string()),
+        _ => None,
+    }
+}
+
 // Database operations
-const INSERT_NODE_QUERY: &str =
-    "INSERT INTO nodes (address, valid, master) VALUES (?, false, false)";
-const SELECT_NODES_QUERY: &str = "SELECT * FROM nodes";
-
-/// Creates a new node in the database
-pub async fn create_node(
-    mut req: Request,
-    ctx: RouteContext<()>,
-) -> Result<Response, worker::Error> {
-    let input_node: NodeInput = req.json().await?;
+const INSERT_NODE_QUERY: &str = "INSERT INTO nodes (address, valid, master, last_seen) \
+     VALUES (?, false, false, CURRENT_TIMESTAMP) ON CONFLICT (address) DO NOTHING";
+const TOUCH_NODE_QUERY: &str = "UPDATE nodes SET last_seen = CURRENT_TIMESTAMP WHERE address = ?";
+const SELECT_NODES_QUERY: &str = "SELECT * FROM nodes ORDER BY id LIMIT ? OFFSET ?";
+const SELECT_NODE_QUERY: &str = "SELECT * FROM nodes WHERE id = ?";
+const SELECT_NODE_BY_ADDRESS_QUERY: &str = "SELECT * FROM nodes WHERE address = ?";
+const UPDATE_NODE_QUERY: &str = "UPDATE nodes SET address = COALESCE(?, address), \
+     valid = COALESCE(?, valid), master = COALESCE(?, master) WHERE id = ?";
+const DELETE_NODE_QUERY: &str = "DELETE FROM nodes WHERE id = ?";
+
+/// Reads the request body as JSON. `Request::json` reports malformed bodies
+/// as JS errors, so the body is parsed here to answer them with a 400.
+async fn json_body<T: DeserializeOwned>(req: &mut Request) -> Result<T, ApiError> {
+    parse_body(&req.text().await?)
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|e| ApiError::BadRequest(format!("Invalid JSON body: {e}")))
+}
+
+/// Parses the `id` route parameter
+fn node_id(ctx: &RouteContext<()>) -> Result<i64, ApiError> {
+    ctx.param("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| ApiError::BadRequest("Invalid node id".to_string()))
+}
+
+/// Registers a node, or refreshes its last-seen time when the address is
+/// already known. Responds 201 for new nodes and 200 for refreshed ones.
+pub async fn create_node(mut req: Request, ctx: RouteContext<()>) -> Result<Response, ApiError> {
+    let limiter = d1_limiter(ctx.env.d1("DB")?);
+    let now = now_secs();
+    let ip = client_ip(&req)?;
+    limiter
+        .check(&format!("register:ip:{ip}"), REGISTER_PER_IP, now)
+        .await?;
+
+    let input_node: NodeInput = json_body(&mut req).await?;
     let d1 = ctx.env.d1("DB")?;
 
     let node = NodeInput::from_input(input_node);
+    let address = validate_address(&node.address)?;
+
+    if let Some(peer_id) = address_peer_id(&address) {
+        limiter
+            .check(&format!("register:peer:{peer_id}"), REGISTER_PER_PEER, now)
+            .await?;
+    }
 
     let statement = d1.prepare(INSERT_NODE_QUERY);
-    let query = statement.bind(&[node.address.into()])?;
+    let query = statement.bind(&[address.clone().into()])?;
+    let created = changed_rows(&query.run().await?)? > 0;
 
-    query.run().await?;
+    if !created {
+        let statement = d1.prepare(TOUCH_NODE_QUERY);
+        let query = statement.bind(&[address.clone().into()])?;
+        query.run().await?;
+    }
+
+    let statement = d1.prepare(SELECT_NODE_BY_ADDRESS_QUERY);
+    let query = statement.bind(&[address.into()])?;
+    let node = query
+        .first::<Node>(None)
+        .await?
+        .ok_or(ApiError::NotFound("Node not found"))?;
 
-    Response::ok("Node created successfully")
+    let status = if created { 201 } else { 200 };
+    Ok(Response::from_json(&node)?.with_status(status))
 }
 
-/// Retrieves all nodes from the database
-pub async fn select_nodes(ctx: RouteContext<()>) -> Result<Response, worker::Error> {
+/// Retrieves a page of nodes from the database, with the ids the admin
+/// routes take
+pub async fn select_nodes(req: Request, ctx: RouteContext<()>) -> Result<Response, ApiError> {
+    let ip = client_ip(&req)?;
+    d1_limiter(ctx.env.d1("DB")?)
+        .check(&format!("list:ip:{ip}"), LIST_PER_IP, now_secs())
+        .await?;
+
+    let pagination = Pagination::from_url(&req.url()?);
     let d1 = ctx.env.d1("DB")?;
 
     let statement = d1.prepare(SELECT_NODES_QUERY);
-    let results = statement.all().await?;
-    let nodes: Vec<NodeInput> = results.results()?;
+    let query = statement.bind(&[pagination.limit.into(), pagination.offset.into()])?;
+    let results = query.all().await?;
+    let nodes: Vec<Node> = results.results()?;
+
+    Ok(Response::from_json(&nodes)?)
+}
+
+/// Retrieves a single node by id (admin)
+pub async fn select_node(req: Request, ctx: RouteContext<()>) -> Result<Response, ApiError> {
+    require_admin(&req, &ctx)?;
+    let id = node_id(&ctx)?;
+    let d1 = ctx.env.d1("DB")?;
+
+    let statement = d1.prepare(SELECT_NODE_QUERY);
+    let query = statement.bind(&[JsValue::from(id as f64)])?;
+    let node = query
+        .first::<Node>(None)
+        .await?
+        .ok_or(ApiError::NotFound("Node not found"))?;
+
+    Ok(Response::from_json(&node)?)
+}
+
+/// Updates the address or flags of a node (admin)
+pub async fn update_node(mut req: Request, ctx: RouteContext<()>) -> Result<Response, ApiError> {
+    require_admin(&req, &ctx)?;
+    let id = node_id(&ctx)?;
+    let mut update: NodeUpdate = json_body(&mut req).await?;
+    if update.is_empty() {
+        return Err(ApiError::BadRequest("Nothing to update".to_string()));
+    }
+    if let Some(address) = &update.address {
+        update.address = Some(validate_address(address)?);
+    }
+    let d1 = ctx.env.d1("DB")?;
+
+    let statement = d1.prepare(UPDATE_NODE_QUERY);
+    let query = statement.bind(&[
+        update.address.into(),
+        update.valid.into(),
+        update.master.into(),
+        JsValue::from(id as f64),
+    ])?;
+    let result = query.run().await?;
+
+    if changed_rows(&result)? == 0 {
+        return Err(ApiError::NotFound("Node not found"));
+    }
+
+    let statement = d1.prepare(SELECT_NODE_QUERY);
+    let query = statement.bind(&[JsValue::from(id as f64)])?;
+    let node = query
+        .first::<Node>(None)
+        .await?
+        .ok_or(ApiError::NotFound("Node not found"))?;
 
-    Response::from_json(&nodes)
+    Ok(Response::from_json(&node)?)
+}
+
+/// Deletes a node by id (admin)
+pub async fn delete_node(req: Request, ctx: RouteContext<()>) -> Result<Response, ApiError> {
+    require_admin(&req, &ctx)?;
+    let id = node_id(&ctx)?;
+    let d1 = ctx.env.d1("DB")?;
+
+    let statement = d1.prepare(DELETE_NODE_QUERY);
+    let query = statement.bind(&[JsValue::from(id as f64)])?;
+    let result = query.run().await?;
+
+    if changed_rows(&result)? == 0 {
+        return Err(ApiError::NotFound("Node not found"));
+    }
+
+    Ok(Response::empty()?.with_status(204))
+}
+
+/// Number of rows touched by a write statement
+fn changed_rows(result: &worker::D1Result) -> Result<usize, worker::Error> {
+    Ok(result
+        .meta()?
+        .and_then(|meta| meta.changes)
+        .unwrap_or_default())
 }
 
 #[cfg(test)]
@@ -94,4 +342,109 @@ mod tests {
         assert_eq!(input.valid, 1);
         assert_eq!(input.master, 0);
     }
+
+    #[test]
+    fn test_invalid_body_is_bad_request() {
+        for body in ["{", "", r#"{"valid":1}"#, r#"{"address":7}"#] {
+            let err = parse_body::<NodeInput>(body).unwrap_err();
+            assert_eq!(err.status(), 400, "accepted {body:?}");
+            assert!(err.message().starts_with("Invalid JSON body"));
+        }
+
+        let err = parse_body::<NodeUpdate>(r#"{"valid":"yes"}"#).unwrap_err();
+        assert_eq!(err.status(), 400);
+    }
+
+    #[test]
+    fn test_listed_node_keeps_id() {
+        let row = r#"{"id":7,"address":"/ip4/203.0.113.7/tcp/4001","valid":1,"master":0,"last_seen":null}"#;
+        let node: Node = serde_json::from_str(row).unwrap();
+        let listed = serde_json::to_value(&node).unwrap();
+
+        assert_eq!(listed["id"], 7);
+        assert_eq!(listed["address"], "/ip4/203.0.113.7/tcp/4001");
+    }
+
+    #[test]
+    fn test_pagination_defaults() {
+        let url = Url::parse("https://api.dulovar.com/nodes").unwrap();
+        let pagination = Pagination::from_url(&url);
+
+        assert_eq!(pagination.limit, Pagination::DEFAULT_LIMIT);
+        assert_eq!(pagination.offset, 0);
+    }
+
+    #[test]
+    fn test_pagination_from_query() {
+        let url = Url::parse("https://api.dulovar.com/nodes?limit=20&offset=40").unwrap();
+        assert_eq!(
+            Pagination::from_url(&url),
+            Pagination {
+                limit: 20,
+                offset: 40
+            }
+        );
+
+        let url = Url::parse("https://api.dulovar.com/nodes?limit=100000&offset=-3").unwrap();
+        let pagination = Pagination::from_url(&url);
+        assert_eq!(pagination.limit, Pagination::MAX_LIMIT);
+        assert_eq!(pagination.offset, 0);
+
+        let url = Url::parse("https://api.dulovar.com/nodes?limit=0").unwrap();
+        assert_eq!(Pagination::from_url(&url).limit, 1);
+    }
+
+    #[test]
+    fn test_node_update_partial() {
+        let json = r#"{"valid":1}"#;
+        let update: NodeUpdate = serde_json::from_str(json).unwrap();
+
+        assert_eq!(update.valid, Some(1));
+        assert!(update.address.is_none());
+        assert!(update.master.is_none());
+        assert!(!update.is_empty());
+        assert!(NodeUpdate::default().is_empty());
+    }
+
+    #[test]
+    fn test_validate_address() {
+        assert_eq!(
+            validate_address(" /ip4/203.0.113.7/tcp/4001 ").unwrap(),
+            "/ip4/203.0.113.7/tcp/4001"
+        );
+        assert!(validate_address("/dns4/node.dulovar.com/tcp/443/wss").is_ok());
+        assert!(validate_address("/ip6/::1/udp/4001/quic-v1").is_ok());
+
+        // Legacy registrations
+        assert_eq!(validate_address("203.0.113.7").unwrap(), "203.0.113.7");
+        assert_eq!(validate_address("2001:DB8::1").unwrap(), "2001:db8::1");
+    }
+
+    #[test]
+    fn test_validate_address_rejects_invalid() {
+        for address in [
+            "",
+            "   ",
+            "unknown",
+            "203.0.113.7:4001",
+            "/tcp/4001",
+            "/ip4/999.0.0.1",
+        ] {
+            let err = validate_address(address).unwrap_err();
+            assert_eq!(err.status(), 400, "accepted {address:?}");
+        }
+
+        let long = format!("/dns4/{}.com/tcp/1", "a".repeat(600));
+        assert!(validate_address(&long).is_err());
+    }
+
+    #[test]
+    fn test_address_peer_id() {
+        let peer_id = "12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA";
+        let address = format!("/ip4/203.0.113.7/tcp/4001/p2p/{peer_id}");
+
+        assert_eq!(address_peer_id(&address).as_deref(), Some(peer_id));
+        assert_eq!(address_peer_id("/ip4/203.0.113.7/tcp/4001"), None);
+        assert_eq!(address_peer_id("not an address"), None);
+    }
 }
@@ -0,0 +1,236 @@
+use std::net::IpAddr;
+#[cfg(test)]
+use std::{cell::RefCell, collections::HashMap};
+
+use worker::{D1Database, Request};
+
+use crate::error::ApiError;
+
+/// Maximum number of requests allowed per fixed time window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub limit: u32,
+    pub window_secs: u64,
+}
+
+/// Node registrations from a single IP address
+pub const REGISTER_PER_IP: Policy = Policy {
+    limit: 10,
+    window_secs: 60,
+};
+
+/// Node registrations announcing the same peer id
+pub const REGISTER_PER_PEER: Policy = Policy {
+    limit: 5,
+    window_secs: 60,
+};
+
+/// Node listings from a single IP address
+pub const LIST_PER_IP: Policy = Policy {
+    limit: 60,
+    window_secs: 60,
+};
+
+/// Counters are kept this long after their window started, longer than
+/// the window of any policy
+const RETENTION_SECS: u64 = 3600;
+
+/// Storage of the per-key request counters
+pub trait RateLimitStore {
+    /// Increments the counter of `key` in the window starting at
+    /// `window_start` and returns the updated count
+    async fn hit(&self, key: &str, window_start: u64) -> Result<u32, worker::Error>;
+
+    /// Drops the counters of every key whose window started before `before`
+    async fn prune(&self, before: u64) -> Result<(), worker::Error>;
+}
+
+/// Counters kept in the `rate_limits` D1 table
+pub struct D1RateLimitStore {
+    db: D1Database,
+}
+
+impl D1RateLimitStore {
+    pub fn new(db: D1Database) -> Self {
+        Self { db }
+    }
+}
+
+const HIT_QUERY: &str = "INSERT INTO rate_limits (key, window_start, count) VALUES (?, ?, 1) \
+     ON CONFLICT (key, window_start) DO UPDATE SET count = count + 1 RETURNING count";
+const PRUNE_QUERY: &str = "DELETE FROM rate_limits WHERE window_start < ?";
+
+impl RateLimitStore for D1RateLimitStore {
+    async fn hit(&self, key: &str, window_start: u64) -> Result<u32, worker::Error> {
+        let statement = self.db.prepare(HIT_QUERY);
+        let query = statement.bind(&[key.into(), (window_start as f64).into()])?;
+        Ok(query.first::<u32>(Some("count")).await?.unwrap_or(1))
+    }
+
+    async fn prune(&self, before: u64) -> Result<(), worker::Error> {
+        let statement = self.db.prepare(PRUNE_QUERY);
+        let query = statement.bind(&[(before as f64).into()])?;
+        query.run().await?;
+        Ok(())
+    }
+}
+
+/// Counters kept in memory, used by tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    counts: RefCell<HashMap<(String, u64), u32>>,
+}
+
+#[cfg(test)]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn hit(&self, key: &str, window_start: u64) -> Result<u32, worker::Error> {
+        let mut counts = self.counts.borrow_mut();
+        let count = counts.entry((key.to_string(), window_start)).or_insert(0);
+        *count += 1;
+        Ok(*count)
+    }
+
+    async fn prune(&self, before: u64) -> Result<(), worker::Error> {
+        self.counts
+            .borrow_mut()
+            .retain(|(_, start), _| *start >= before);
+        Ok(())
+    }
+}
+
+/// Fixed-window rate limiter over a counter store
+pub struct RateLimiter<S: RateLimitStore> {
+    store: S,
+}
+
+impl<S: RateLimitStore> RateLimiter<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Records a request for `key` at `now_secs`, failing with
+    /// `TooManyRequests` once the policy limit is exceeded
+    pub async fn check(&self, key: &str, policy: Policy, now_secs: u64) -> Result<(), ApiError> {
+        let window_start = now_secs - now_secs % policy.window_secs;
+        let count = self.store.hit(key, window_start).await?;
+
+        // The first hit of a key in a window samples the requests that
+        // clean up, so keys that are never seen again do not stay
+        if count == 1 {
+            self.store
+                .prune(now_secs.saturating_sub(RETENTION_SECS))
+                .await?;
+        }
+
+        if count > policy.limit {
+            return Err(ApiError::TooManyRequests {
+                retry_after: window_start + policy.window_secs - now_secs,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Rate limiter backed by the worker D1 database
+pub fn d1_limiter(db: D1Database) -> RateLimiter<D1RateLimitStore> {
+    RateLimiter::new(D1RateLimitStore::new(db))
+}
+
+/// Address of the client as reported by Cloudflare. Workers have no
+/// socket address, and any other header can be set by the client to rotate
+/// through counters, so requests without it are refused rather than sharing
+/// one counter.
+pub fn client_ip(req: &Request) -> Result<String, ApiError> {
+    parse_ip(req.headers().get("CF-Connecting-IP")?.as_deref())
+        .map(|ip| ip.to_string())
+        .ok_or_else(|| ApiError::BadRequest("Client address is unknown".to_string()))
+}
+
+fn parse_ip(value: Option<&str>) -> Option<IpAddr> {
+    value?.trim().parse().ok()
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    worker::Date::now().as_millis() / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    const POLICY: Policy = Policy {
+        limit: 3,
+        window_secs: 60,
+    };
+
+    #[test]
+    fn test_allows_up_to_limit() {
+        let limiter = RateLimiter::new(MemoryRateLimitStore::default());
+
+        for _ in 0..POLICY.limit {
+            assert!(block_on(limiter.check("ip:1.2.3.4", POLICY, 120)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rejects_over_limit_with_retry_after() {
+        let limiter = RateLimiter::new(MemoryRateLimitStore::default());
+
+        for _ in 0..POLICY.limit {
+            block_on(limiter.check("ip:1.2.3.4", POLICY, 130)).unwrap();
+        }
+
+        match block_on(limiter.check("ip:1.2.3.4", POLICY, 135)) {
+            Err(ApiError::TooManyRequests { retry_after }) => assert_eq!(retry_after, 45),
+            other => panic!("expected TooManyRequests, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_keys_are_independent() {
+        let limiter = RateLimiter::new(MemoryRateLimitStore::default());
+
+        for _ in 0..POLICY.limit {
+            block_on(limiter.check("ip:1.2.3.4", POLICY, 0)).unwrap();
+        }
+
+        assert!(block_on(limiter.check("ip:5.6.7.8", POLICY, 0)).is_ok());
+        assert!(block_on(limiter.check("ip:1.2.3.4", POLICY, 0)).is_err());
+    }
+
+    #[test]
+    fn test_window_resets() {
+        let limiter = RateLimiter::new(MemoryRateLimitStore::default());
+
+        for _ in 0..=POLICY.limit {
+            let _ = block_on(limiter.check("peer:abc", POLICY, 59));
+        }
+
+        assert!(block_on(limiter.check("peer:abc", POLICY, 60)).is_ok());
+    }
+
+    #[test]
+    fn test_expired_counters_of_every_key_are_pruned() {
+        let limiter = RateLimiter::new(MemoryRateLimitStore::default());
+
+        block_on(limiter.check("ip:1.2.3.4", POLICY, 0)).unwrap();
+        block_on(limiter.check("ip:5.6.7.8", POLICY, RETENTION_SECS - 60)).unwrap();
+        block_on(limiter.check("peer:abc", POLICY, RETENTION_SECS + 60)).unwrap();
+
+        let counts = limiter.store.counts.borrow();
+        assert!(!counts.contains_key(&("ip:1.2.3.4".to_string(), 0)));
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn test_client_ip_from_header() {
+        assert_eq!(parse_ip(Some("203.0.113.7")), "203.0.113.7".parse().ok());
+        assert_eq!(parse_ip(Some(" 2001:db8::1 ")), "2001:db8::1".parse().ok());
+        assert_eq!(parse_ip(Some("unknown")), None);
+        assert_eq!(parse_ip(Some("203.0.113.7, 198.51.100.1")), None);
+        assert_eq!(parse_ip(None), None);
+    }
+}